@@ -601,7 +601,7 @@ fn run_app_internal(
                                 }
                                 ModalResult::AnnotationCopyAll => {
                                     let formatted = state.format_annotations_for_export();
-                                    let _ = super::clipboard::copy_osc52(&formatted);
+                                    let _ = super::clipboard::copy_osc52(&formatted, false);
                                     active_modal = None;
                                 }
                                 ModalResult::AnnotationExport(filename) => {
@@ -1401,6 +1401,7 @@ fn run_app_internal(
                             if !state.file_diffs.is_empty() {
                                 let _ = super::clipboard::copy_osc52(
                                     &state.file_diffs[state.current_file].filename,
+                                    false,
                                 );
                             }
                         }
@@ -1505,9 +1506,9 @@ fn run_app_internal(
                             }
                         }
                         KeyCode::Char('c') => {
-                            // Commit viewed files - only supported for git
-                            if backend.name() != "git" {
-                                // Show error for non-git backends
+                            // Commit viewed files - needs a staging area
+                            if !backend.capabilities().partial_commit {
+                                // Show error for backends without partial commits
                                 active_modal = Some(Modal::info(
                                     "Not Supported",
                                     "Commit from diff view is only supported for git repositories",
@@ -1,13 +1,146 @@
 use base64::Engine;
+use std::borrow::Cow;
 use std::io::{self, Write};
 
+/// Environment variable selecting which clipboard OSC52 targets.
+const CLIPBOARD_ENV: &str = "LUMEN_CLIPBOARD";
+
+/// Which selection an OSC52 copy should set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClipboardTarget {
+    /// The system clipboard (`c`)
+    #[default]
+    Clipboard,
+    /// The X11 primary selection (`p`)
+    Primary,
+    /// Both the clipboard and the primary selection
+    Both,
+}
+
+impl ClipboardTarget {
+    /// Parse a `LUMEN_CLIPBOARD` value. Unknown values fall back to the clipboard.
+    fn parse(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "primary" => ClipboardTarget::Primary,
+            "both" => ClipboardTarget::Both,
+            _ => ClipboardTarget::Clipboard,
+        }
+    }
+
+    /// Read the target from `LUMEN_CLIPBOARD`, defaulting to the clipboard.
+    pub fn from_env() -> Self {
+        std::env::var(CLIPBOARD_ENV)
+            .map(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+
+    /// OSC52 selection parameters to emit for this target.
+    fn selections(self) -> &'static [char] {
+        match self {
+            ClipboardTarget::Clipboard => &['c'],
+            ClipboardTarget::Primary => &['p'],
+            ClipboardTarget::Both => &['c', 'p'],
+        }
+    }
+}
+
+/// Build the OSC52 escape sequence(s) that copy text to the given target.
+fn osc52_sequences(text: &str, target: ClipboardTarget) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    // OSC 52 sequence: \x1b]52;<selection>;<base64-encoded-text>\x07
+    target
+        .selections()
+        .iter()
+        .map(|selection| format!("\x1b]52;{};{}\x07", selection, encoded))
+        .collect()
+}
+
+/// Text to encode in the OSC52 payload, optionally with a trailing newline added.
+fn osc52_payload(text: &str, trailing_newline: bool) -> Cow<'_, str> {
+    if trailing_newline {
+        Cow::Owned(format!("{}\n", text))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
 /// Copy text to clipboard using OSC52 escape sequence.
 /// This works through the terminal emulator, which then sets the system clipboard.
-pub fn copy_osc52(text: &str) -> io::Result<()> {
-    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+/// The selection is chosen by `LUMEN_CLIPBOARD` (`clipboard`, `primary` or `both`).
+/// With `trailing_newline`, a `\n` is appended to the copied text; otherwise
+/// it is copied as-is.
+pub fn copy_osc52(text: &str, trailing_newline: bool) -> io::Result<()> {
     let mut stdout = io::stdout();
-    // OSC 52 sequence: \x1b]52;c;<base64-encoded-text>\x07
-    // 'c' specifies the clipboard selection
-    write!(stdout, "\x1b]52;c;{}\x07", encoded)?;
+    write!(
+        stdout,
+        "{}",
+        osc52_sequences(
+            &osc52_payload(text, trailing_newline),
+            ClipboardTarget::from_env()
+        )
+    )?;
     stdout.flush()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trailing_newline_flag() {
+        let decode = |payload: &str| {
+            let encoded = osc52_sequences(payload, ClipboardTarget::Clipboard);
+            let encoded = encoded
+                .strip_prefix("\x1b]52;c;")
+                .and_then(|rest| rest.strip_suffix('\x07'))
+                .expect("well-formed sequence")
+                .to_string();
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .expect("valid base64");
+            String::from_utf8(bytes).expect("valid utf-8")
+        };
+
+        assert_eq!(decode(&osc52_payload("line", false)), "line");
+        assert_eq!(decode(&osc52_payload("line\n", false)), "line\n");
+        assert_eq!(decode(&osc52_payload("line", true)), "line\n");
+    }
+
+    #[test]
+    fn test_env_selects_osc52_target() {
+        // Single test so the env var isn't raced by parallel tests
+        let original = std::env::var(CLIPBOARD_ENV).ok();
+
+        std::env::remove_var(CLIPBOARD_ENV);
+        assert_eq!(
+            osc52_sequences("hi", ClipboardTarget::from_env()),
+            "\x1b]52;c;aGk=\x07"
+        );
+
+        std::env::set_var(CLIPBOARD_ENV, "clipboard");
+        assert_eq!(
+            osc52_sequences("hi", ClipboardTarget::from_env()),
+            "\x1b]52;c;aGk=\x07"
+        );
+
+        std::env::set_var(CLIPBOARD_ENV, "primary");
+        assert_eq!(
+            osc52_sequences("hi", ClipboardTarget::from_env()),
+            "\x1b]52;p;aGk=\x07"
+        );
+
+        std::env::set_var(CLIPBOARD_ENV, "both");
+        assert_eq!(
+            osc52_sequences("hi", ClipboardTarget::from_env()),
+            "\x1b]52;c;aGk=\x07\x1b]52;p;aGk=\x07"
+        );
+
+        std::env::set_var(CLIPBOARD_ENV, "bogus");
+        assert_eq!(ClipboardTarget::from_env(), ClipboardTarget::Clipboard);
+
+        match original {
+            Some(value) => std::env::set_var(CLIPBOARD_ENV, value),
+            None => std::env::remove_var(CLIPBOARD_ENV),
+        }
+    }
+}
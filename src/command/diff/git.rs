@@ -2,7 +2,7 @@ use std::fs;
 use std::path::Path;
 use std::process::Command;
 
-use super::types::{is_binary_content, FileDiff, FileStatus};
+use super::types::{detect_language, is_binary_content, FileDiff, FileStatus};
 use super::{DiffOptions, PrInfo};
 use crate::commit_reference::CommitReference;
 use crate::vcs::VcsBackend;
@@ -123,12 +123,14 @@ pub fn load_file_diffs(options: &DiffOptions, backend: &dyn VcsBackend) -> Vec<F
                 FileStatus::Modified
             };
             let is_binary = is_binary_content(&old_content) || is_binary_content(&new_content);
+            let language = detect_language(Path::new(&filename));
             FileDiff {
                 filename,
                 old_content,
                 new_content,
                 status,
                 is_binary,
+                language,
             }
         })
         .collect()
@@ -182,12 +184,14 @@ pub fn load_pr_file_diffs(pr_info: &PrInfo) -> Result<Vec<FileDiff>, String> {
             };
 
             let is_binary = is_binary_content(&old_content) || is_binary_content(&new_content);
+            let language = detect_language(Path::new(&filename));
             FileDiff {
                 filename,
                 old_content,
                 new_content,
                 status,
                 is_binary,
+                language,
             }
         })
         .collect();
@@ -281,12 +285,14 @@ pub fn load_single_commit_diffs(
             };
 
             let is_binary = is_binary_content(&old_content) || is_binary_content(&new_content);
+            let language = detect_language(Path::new(&filename));
             FileDiff {
                 filename,
                 old_content,
                 new_content,
                 status,
                 is_binary,
+                language,
             }
         })
         .collect()
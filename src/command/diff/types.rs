@@ -1,5 +1,7 @@
 use super::context::ContextConfig;
 
+pub use crate::vcs::detect_language;
+
 pub fn expand_tabs(s: &str, tab_width: usize) -> String {
     if tab_width == 0 {
         return s.replace('\t', "");
@@ -44,6 +46,9 @@ pub struct FileDiff {
     pub new_content: String,
     pub status: FileStatus,
     pub is_binary: bool,
+    /// Language detected from the file extension, if known
+    #[allow(dead_code)] // Not yet shown in the diff view
+    pub language: Option<&'static str>,
 }
 
 /// Detect if content is binary by checking for null bytes in the first 8KB
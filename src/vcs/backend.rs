@@ -17,6 +17,12 @@ pub enum VcsError {
     #[error("command failed: {0}")]
     CommandFailed(String),
 
+    #[error("conflicts in: {}", .0.join(", "))]
+    Conflict(Vec<String>),
+
+    #[error("diff too large: aborted after {bytes} bytes in {files} files")]
+    DiffTooLarge { bytes: usize, files: usize },
+
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -32,30 +38,104 @@ pub struct StackedCommitInfo {
     pub commit_id: String,
     /// Short ID for display (7-12 chars)
     pub short_id: String,
-    /// Change ID (jj only, None for git)
+    /// Change ID (jj, or a `Change-Id:` trailer for git)
     pub change_id: Option<String>,
     /// First line of commit message
     pub summary: String,
 }
 
+/// Line and file totals of a diff, excluding filtered files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffStats {
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+impl std::fmt::Display for DiffStats {
+    /// git's `--shortstat` line, e.g. `3 files changed, 42 insertions(+)`.
+    /// Zero insertions or deletions are left out, as git does.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        write!(
+            f,
+            "{} file{} changed",
+            self.files_changed,
+            plural(self.files_changed)
+        )?;
+        if self.insertions > 0 {
+            write!(
+                f,
+                ", {} insertion{}(+)",
+                self.insertions,
+                plural(self.insertions)
+            )?;
+        }
+        if self.deletions > 0 {
+            write!(
+                f,
+                ", {} deletion{}(-)",
+                self.deletions,
+                plural(self.deletions)
+            )?;
+        }
+        Ok(())
+    }
+}
+
 /// Information about a commit from any VCS.
 #[derive(Debug, Clone)]
 #[allow(dead_code)] // Fields used by git_entity::Commit::from_commit_info
 pub struct CommitInfo {
     /// The commit ID (git SHA or jj commit ID)
     pub commit_id: String,
-    /// The change ID (jj only, None for git)
+    /// The change ID (jj, or a `Change-Id:` trailer for git)
     pub change_id: Option<String>,
     /// Commit message
     pub message: String,
+    /// First line of the first parent's message (None for root commits)
+    pub parent_summary: Option<String>,
     /// Diff content
     pub diff: String,
+    /// File and line totals of the diff, excluding filtered files (lock
+    /// files, etc.)
+    pub stats: DiffStats,
     /// Author name and email
     pub author: String,
+    /// Committer name and email
+    pub committer: String,
     /// Commit timestamp formatted for display (YYYY-MM-DD HH:MM:SS)
     pub date: String,
 }
 
+/// Options for generating diffs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffConfig {
+    /// Unchanged lines shown around each change, as in `git diff -U<n>`
+    pub context_lines: u32,
+}
+
+impl Default for DiffConfig {
+    /// git's default of 3 context lines
+    fn default() -> Self {
+        DiffConfig { context_lines: 3 }
+    }
+}
+
+/// Optional features a backend supports, so callers can feature-detect
+/// instead of checking `name()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Capabilities {
+    /// Commits have stable change IDs separate from commit IDs
+    pub change_ids: bool,
+    /// Uncommitted changes can be stashed and listed
+    pub stash: bool,
+    /// Commit signatures can be inspected
+    pub signing: bool,
+    /// A subset of working tree files can be committed (staging area)
+    pub partial_commit: bool,
+}
+
 /// Abstraction over git and jj backends.
 ///
 /// Note: This trait intentionally does not require `Send + Sync` bounds.
@@ -64,15 +144,59 @@ pub struct CommitInfo {
 /// not be thread-safe, so adding these bounds would prevent jj support.
 #[allow(dead_code)] // Not all methods used by all commands yet
 pub trait VcsBackend {
+    /// Diff options used when the caller doesn't pass a `DiffConfig`.
+    fn diff_config(&self) -> DiffConfig {
+        DiffConfig::default()
+    }
+
     /// Get commit info for a reference (SHA, HEAD, @, etc.)
-    fn get_commit(&self, reference: &str) -> Result<CommitInfo, VcsError>;
+    fn get_commit(&self, reference: &str) -> Result<CommitInfo, VcsError> {
+        self.get_commit_with(reference, &self.diff_config())
+    }
+
+    /// Like `get_commit`, with the commit's diff generated per `config`.
+    fn get_commit_with(&self, reference: &str, config: &DiffConfig)
+        -> Result<CommitInfo, VcsError>;
+
+    /// Get the file and line totals of a commit's diff against its parent.
+    /// Excluded files (lock files, etc.) are not counted.
+    fn get_diff_stats(&self, reference: &str) -> Result<DiffStats, VcsError> {
+        Ok(self.get_commit(reference)?.stats)
+    }
 
     /// Get diff of uncommitted changes (working tree vs HEAD/parent).
     /// `staged` is only relevant for git; jj ignores it.
-    fn get_working_tree_diff(&self, staged: bool) -> Result<String, VcsError>;
+    fn get_working_tree_diff(&self, staged: bool) -> Result<String, VcsError> {
+        self.get_working_tree_diff_with(staged, &self.diff_config())
+    }
+
+    /// Like `get_working_tree_diff`, generated per `config`.
+    fn get_working_tree_diff_with(
+        &self,
+        staged: bool,
+        config: &DiffConfig,
+    ) -> Result<String, VcsError>;
+
+    /// Get the diff of the current working snapshot against its parent.
+    /// For git: staged and unstaged changes combined, relative to HEAD.
+    /// For jj: the working-copy commit's diff (`@` vs `@-`).
+    fn working_snapshot_diff(&self) -> Result<String, VcsError>;
 
     /// Get diff between two refs (e.g., commit1..commit2).
-    fn get_range_diff(&self, from: &str, to: &str, three_dot: bool) -> Result<String, VcsError>;
+    /// Compares the endpoint trees directly, so files changed and then reverted
+    /// within the range (net-zero changes) are omitted.
+    fn get_range_diff(&self, from: &str, to: &str, three_dot: bool) -> Result<String, VcsError> {
+        self.get_range_diff_with(from, to, three_dot, &self.diff_config())
+    }
+
+    /// Like `get_range_diff`, generated per `config`.
+    fn get_range_diff_with(
+        &self,
+        from: &str,
+        to: &str,
+        three_dot: bool,
+        config: &DiffConfig,
+    ) -> Result<String, VcsError>;
 
     /// Get list of changed files for a commit or range.
     fn get_changed_files(&self, reference: &str) -> Result<Vec<String>, VcsError>;
@@ -86,6 +210,12 @@ pub trait VcsBackend {
     /// Get commit log formatted for fzf selection.
     fn get_commit_log_for_fzf(&self) -> Result<String, VcsError>;
 
+    /// Get the fzf commit log without ANSI colors, for plain-text consumers.
+    fn get_commit_log_plain(&self) -> Result<String, VcsError> {
+        self.get_commit_log_for_fzf()
+            .map(|log| super::strip_ansi(&log))
+    }
+
     /// Resolve a reference to a canonical commit SHA.
     /// Works with any ref type: git SHA, jj change ID, @, @-, bookmarks, branches, etc.
     fn resolve_ref(&self, reference: &str) -> Result<String, VcsError>;
@@ -107,10 +237,23 @@ pub trait VcsBackend {
     fn working_copy_parent_ref(&self) -> &'static str;
 
     /// Get list of files changed between two refs (range diff).
+    /// Like `get_range_diff`, net-zero files are omitted.
     /// For git: runs 'git diff --name-only <from> <to>'.
     /// For jj: diffs the trees of the two commits.
     fn get_range_changed_files(&self, from: &str, to: &str) -> Result<Vec<String>, VcsError>;
 
+    /// Get the files changed from `base` through the working copy: the union
+    /// of the committed range up to the working copy parent and the
+    /// uncommitted changes, deduplicated and sorted.
+    fn combined_changed_files(&self, base: &str) -> Result<Vec<String>, VcsError> {
+        let mut files: std::collections::BTreeSet<String> = self
+            .get_range_changed_files(base, self.working_copy_parent_ref())?
+            .into_iter()
+            .collect();
+        files.extend(self.get_working_tree_changed_files()?);
+        Ok(files.into_iter().collect())
+    }
+
     /// Get the parent ref for a commit, or the empty tree SHA for root commits.
     /// This handles the edge case where a commit has no parent (first commit).
     /// For git: returns "SHA^" if parent exists, else git empty tree SHA.
@@ -118,10 +261,10 @@ pub trait VcsBackend {
     fn get_parent_ref_or_empty(&self, reference: &str) -> Result<String, VcsError>;
 
     /// Get list of commits in a range for stacked diff mode.
-    /// Returns commits in chronological order (oldest first).
+    /// Returns commits oldest first, with parents always before children.
     /// Excludes commits with no file changes (e.g., merge commits).
     ///
-    /// For git: `git log --reverse from..to`, filtered by diff-tree
+    /// For git: `git log --reverse --topo-order from..to`, filtered by diff-tree
     /// For jj: revset `from::to`, filtered by tree diff
     fn get_commits_in_range(
         &self,
@@ -129,6 +272,28 @@ pub trait VcsBackend {
         to: &str,
     ) -> Result<Vec<StackedCommitInfo>, VcsError>;
 
+    /// Visit the commits of `get_commits_in_range` one at a time, in the same
+    /// order, without collecting them. The walk stops as soon as `f` returns false.
+    ///
+    /// The default implementation collects the range first; backends that can
+    /// walk lazily should override it.
+    fn walk_commits_in_range(
+        &self,
+        from: &str,
+        to: &str,
+        f: &mut dyn FnMut(StackedCommitInfo) -> bool,
+    ) -> Result<(), VcsError> {
+        for commit in self.get_commits_in_range(from, to)? {
+            if !f(commit) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
     /// Get the name of this VCS backend ("git" or "jj").
     fn name(&self) -> &'static str;
+
+    /// Get the optional features this backend supports.
+    fn capabilities(&self) -> Capabilities;
 }
@@ -0,0 +1,160 @@
+//! Conventional Commits parsing.
+//!
+//! Subjects like `feat(api)!: drop v1` carry a type, optional scope and a
+//! breaking marker. Parsing them lets changelogs group commits by kind.
+
+/// The components of a conventional commit message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConventionalCommit {
+    /// Commit type, e.g. `feat` or `fix`
+    pub kind: String,
+    /// Scope in parentheses after the type, if any
+    pub scope: Option<String>,
+    /// Set by a `!` before the colon or a `BREAKING CHANGE:` footer
+    pub breaking: bool,
+    /// Text after the colon
+    pub description: String,
+}
+
+/// Parse a commit message as a conventional commit.
+/// Only the first line is matched against `type(scope)!: description`; the
+/// rest is searched for a `BREAKING CHANGE:` footer. Returns None for
+/// subjects that don't follow the convention.
+pub fn parse_conventional(message: &str) -> Option<ConventionalCommit> {
+    let (subject, body) = message.split_once('\n').unwrap_or((message, ""));
+    let (prefix, description) = subject.split_once(':')?;
+    let description = description.trim();
+    if description.is_empty() {
+        return None;
+    }
+
+    let (prefix, bang) = match prefix.strip_suffix('!') {
+        Some(prefix) => (prefix, true),
+        None => (prefix, false),
+    };
+    let (kind, scope) = match prefix.split_once('(') {
+        Some((kind, rest)) => {
+            let scope = rest.strip_suffix(')')?;
+            if scope.is_empty() {
+                return None;
+            }
+            (kind, Some(scope.to_string()))
+        }
+        None => (prefix, None),
+    };
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return None;
+    }
+
+    let breaking_footer = body
+        .lines()
+        .any(|line| line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:"));
+
+    Some(ConventionalCommit {
+        kind: kind.to_string(),
+        scope,
+        breaking: bang || breaking_footer,
+        description: description.to_string(),
+    })
+}
+
+/// A commit as listed in a changelog.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangelogEntry {
+    /// Full commit id
+    pub commit_id: String,
+    pub scope: Option<String>,
+    /// Conventional description, or the whole subject for other commits
+    pub description: String,
+    pub breaking: bool,
+}
+
+/// Commits grouped by conventional type, each bucket oldest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Changelog {
+    /// `feat` commits
+    pub features: Vec<ChangelogEntry>,
+    /// `fix` commits
+    pub fixes: Vec<ChangelogEntry>,
+    /// Every other type, plus commits that don't follow the convention
+    pub other: Vec<ChangelogEntry>,
+}
+
+impl Changelog {
+    /// File a commit message under its bucket.
+    pub fn push(&mut self, commit_id: String, message: &str) {
+        let Some(parsed) = parse_conventional(message) else {
+            self.other.push(ChangelogEntry {
+                commit_id,
+                scope: None,
+                description: message.lines().next().unwrap_or("").trim().to_string(),
+                breaking: false,
+            });
+            return;
+        };
+        let bucket = match parsed.kind.to_ascii_lowercase().as_str() {
+            "feat" => &mut self.features,
+            "fix" => &mut self.fixes,
+            _ => &mut self.other,
+        };
+        bucket.push(ChangelogEntry {
+            commit_id,
+            scope: parsed.scope,
+            description: parsed.description,
+            breaking: parsed.breaking,
+        });
+    }
+
+    /// Breaking changes across all buckets.
+    pub fn breaking(&self) -> impl Iterator<Item = &ChangelogEntry> {
+        self.features
+            .iter()
+            .chain(&self.fixes)
+            .chain(&self.other)
+            .filter(|entry| entry.breaking)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_scope_and_breaking_marker() {
+        assert_eq!(
+            parse_conventional("feat(api)!: drop v1"),
+            Some(ConventionalCommit {
+                kind: "feat".to_string(),
+                scope: Some("api".to_string()),
+                breaking: true,
+                description: "drop v1".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parses_plain_type() {
+        assert_eq!(
+            parse_conventional("fix: typo"),
+            Some(ConventionalCommit {
+                kind: "fix".to_string(),
+                scope: None,
+                breaking: false,
+                description: "typo".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_non_conventional_subject_is_none() {
+        assert_eq!(parse_conventional("Update README"), None);
+        assert_eq!(parse_conventional("fix(): empty scope"), None);
+        assert_eq!(parse_conventional("some words: not a type"), None);
+    }
+
+    #[test]
+    fn test_breaking_change_footer() {
+        let message = "refactor: rename config keys\n\nBREAKING CHANGE: `foo` is now `bar`";
+        assert!(parse_conventional(message).expect("should parse").breaking);
+    }
+}
@@ -37,6 +37,27 @@ pub fn detect_vcs_type(start_dir: &Path) -> VcsType {
     }
 }
 
+/// Backend to construct for a directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    /// `GitBackend`
+    Git,
+    /// `JjBackend`, also chosen for jj repos colocated with git
+    Jj,
+    /// Not inside a repository
+    None,
+}
+
+/// Pick the backend for a directory: jj when the repo root has `.jj/`,
+/// even if `.git` sits next to it, otherwise git.
+pub fn detect_backend(start_dir: &Path) -> BackendKind {
+    match detect_vcs_type(start_dir) {
+        VcsType::Jj => BackendKind::Jj,
+        VcsType::Git => BackendKind::Git,
+        VcsType::None => BackendKind::None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -84,4 +105,32 @@ mod tests {
 
         assert_eq!(detect_vcs_type(&subdir), VcsType::Git);
     }
+
+    #[test]
+    fn test_detect_backend_git_only() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir(temp.path().join(".git")).unwrap();
+
+        assert_eq!(detect_backend(temp.path()), BackendKind::Git);
+    }
+
+    #[test]
+    fn test_detect_backend_jj_only() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir(temp.path().join(".jj")).unwrap();
+
+        assert_eq!(detect_backend(temp.path()), BackendKind::Jj);
+    }
+
+    #[test]
+    fn test_detect_backend_colocated_prefers_jj() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir(temp.path().join(".git")).unwrap();
+        fs::create_dir(temp.path().join(".jj")).unwrap();
+        let subdir = temp.path().join("src");
+        fs::create_dir(&subdir).unwrap();
+
+        assert_eq!(detect_backend(temp.path()), BackendKind::Jj);
+        assert_eq!(detect_backend(&subdir), BackendKind::Jj);
+    }
 }
@@ -0,0 +1,377 @@
+//! Path exclusion rules shared by all VCS backends.
+//!
+//! Lock files and vendored dependencies add noise without helping explain a
+//! change, so they're dropped from diff output.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+
+use super::backend::VcsError;
+
+/// Files to exclude from diff output, matched against the file name.
+pub const EXCLUDED_FILES: &[&str] = &[
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "Cargo.lock",
+];
+
+/// Directory patterns to exclude from diff output.
+/// A pattern matches whole path components, so `node_modules/` excludes
+/// `a/node_modules/x.js` but not `a/my_node_modules_backup/x.js`.
+pub const EXCLUDED_PATTERNS: &[&str] = &["node_modules/"];
+
+/// Check whether a directory pattern (e.g. `node_modules/` or `vendor/cache/`)
+/// matches the directory components of a path.
+fn matches_dir_pattern(path: &str, pattern: &str) -> bool {
+    let pattern: Vec<&str> = pattern
+        .trim_matches('/')
+        .split('/')
+        .filter(|c| !c.is_empty())
+        .collect();
+    if pattern.is_empty() {
+        return false;
+    }
+
+    // Only directory components count - the last component is the file name
+    let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+    let dirs = &components[..components.len().saturating_sub(1)];
+
+    dirs.windows(pattern.len()).any(|window| window == pattern)
+}
+
+/// Check whether a root-anchored entry (with its leading `/` removed) matches
+/// a path: directories must be a prefix, files the whole path.
+fn matches_anchored(path: &str, pattern: &str) -> bool {
+    let path = path.trim_start_matches('/');
+    if pattern.ends_with('/') {
+        path.starts_with(pattern)
+    } else {
+        path == pattern
+    }
+}
+
+/// Check if a path matches a single exclusion entry.
+/// Entries ending in `/` match directory components, others match the file name.
+/// A leading `/` anchors the entry at the repo root, so `/Cargo.lock` matches
+/// `Cargo.lock` but not `crates/foo/Cargo.lock`.
+pub fn matches_exclude(path: &str, pattern: &str) -> bool {
+    if let Some(anchored) = pattern.strip_prefix('/') {
+        matches_anchored(path, anchored)
+    } else if pattern.ends_with('/') {
+        matches_dir_pattern(path, pattern)
+    } else {
+        path.rsplit('/').next() == Some(pattern)
+    }
+}
+
+/// Name of the per-directory exclude file.
+pub const IGNORE_FILE: &str = ".lumenignore";
+
+/// Per-directory `.lumenignore` files under a working directory.
+///
+/// Each file lists one glob per line (`#` starts a comment), read like
+/// `build_exclude_globs` patterns; lines that aren't valid globs are
+/// skipped. A path is judged only by the `.lumenignore` in its nearest
+/// ancestor directory, with globs relative to that directory. Files are
+/// read on first use.
+#[derive(Debug)]
+pub struct LumenIgnore {
+    root: PathBuf,
+    /// Directory (relative, `""` for the root) to its compiled globs, or
+    /// None when it has no `.lumenignore`
+    cache: Mutex<HashMap<String, Option<Arc<GlobSet>>>>,
+}
+
+impl LumenIgnore {
+    pub fn new(root: &Path) -> Self {
+        LumenIgnore {
+            root: root.to_path_buf(),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Globs of the `.lumenignore` in a directory, if it has one.
+    fn globs(&self, dir: &str) -> Option<Arc<GlobSet>> {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(globs) = cache.get(dir) {
+            return globs.clone();
+        }
+        let globs = std::fs::read_to_string(self.root.join(dir).join(IGNORE_FILE))
+            .ok()
+            .map(|content| {
+                let mut builder = GlobSetBuilder::new();
+                for line in content.lines().map(str::trim) {
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    if let Ok(glob) = compile_glob(line) {
+                        builder.add(glob);
+                    }
+                }
+                Arc::new(builder.build().unwrap_or_else(|_| GlobSet::empty()))
+            });
+        cache.insert(dir.to_string(), globs.clone());
+        globs
+    }
+
+    /// Check whether the nearest `.lumenignore` above a repo-relative path
+    /// excludes it.
+    pub fn is_ignored(&self, path: &str) -> bool {
+        let mut dir = path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+        loop {
+            if let Some(globs) = self.globs(dir) {
+                let relative = if dir.is_empty() {
+                    path
+                } else {
+                    &path[dir.len() + 1..]
+                };
+                return globs.is_match(relative);
+            }
+            if dir.is_empty() {
+                return false;
+            }
+            dir = dir.rsplit_once('/').map(|(parent, _)| parent).unwrap_or("");
+        }
+    }
+}
+
+/// The exclusion rules a repository applies to diff paths: the built-in
+/// lock files and vendored directories, `.lumen.toml` entries, the nearest
+/// `.lumenignore` and caller-supplied globs. Both backends judge paths
+/// through this.
+#[derive(Debug)]
+pub struct PathFilter {
+    /// Entries from `.lumen.toml`
+    excludes: Vec<String>,
+    /// Per-directory `.lumenignore` rules (None without a working directory)
+    ignore: Option<LumenIgnore>,
+    /// Caller-supplied exclude globs
+    globs: Option<GlobSet>,
+    /// Apply the built-in lock file and vendored directory excludes
+    default_excludes: bool,
+}
+
+impl PathFilter {
+    pub fn new(workdir: Option<&Path>, excludes: Vec<String>) -> Self {
+        PathFilter {
+            excludes,
+            ignore: workdir.map(LumenIgnore::new),
+            globs: None,
+            default_excludes: true,
+        }
+    }
+
+    /// Also exclude paths matching any of `globs` (see `build_exclude_globs`).
+    /// Errors with the offending pattern if a glob is invalid.
+    pub fn set_globs(&mut self, globs: &[String]) -> Result<(), VcsError> {
+        let globs = build_exclude_globs(globs)
+            .map_err(|e| VcsError::Other(format!("invalid exclude glob: {}", e)))?;
+        self.globs = Some(globs);
+        Ok(())
+    }
+
+    /// Stop applying the built-in excludes (lock files, `node_modules/`).
+    pub fn clear_defaults(&mut self) {
+        self.default_excludes = false;
+    }
+
+    /// Check if a path is excluded by the built-in rules, `.lumen.toml`, the
+    /// nearest `.lumenignore` or the caller's globs.
+    /// A root-anchored entry such as `/Cargo.lock` narrows the built-in rule
+    /// for that file name, so nested copies are kept.
+    pub fn is_excluded(&self, path: &str) -> bool {
+        if self
+            .excludes
+            .iter()
+            .any(|pattern| matches_exclude(path, pattern))
+            || self
+                .ignore
+                .as_ref()
+                .is_some_and(|ignore| ignore.is_ignored(path))
+            || self
+                .globs
+                .as_ref()
+                .is_some_and(|globs| globs.is_match(path))
+        {
+            return true;
+        }
+        if !self.default_excludes {
+            return false;
+        }
+        let filename = path.rsplit('/').next().unwrap_or(path);
+        let narrowed = self
+            .excludes
+            .iter()
+            .any(|pattern| pattern.strip_prefix('/') == Some(filename));
+        should_exclude_path(path) && !narrowed
+    }
+}
+
+/// Compile one exclude glob, see `build_exclude_globs`.
+fn compile_glob(pattern: &str) -> Result<Glob, globset::Error> {
+    let anchored = pattern.starts_with('/');
+    let pattern = pattern.trim_start_matches('/');
+    let (pattern, is_dir) = match pattern.strip_suffix('/') {
+        Some(dir) => (dir, true),
+        None => (pattern, false),
+    };
+    let mut glob = if anchored || (!is_dir && pattern.contains('/')) {
+        pattern.to_string()
+    } else {
+        format!("**/{}", pattern)
+    };
+    if is_dir {
+        glob.push_str("/**");
+    }
+    GlobBuilder::new(&glob).literal_separator(true).build()
+}
+
+/// Compile exclude globs into one matcher.
+///
+/// Globs without a `/` match the file name at any depth, so `*.pb.go`
+/// excludes `api/foo.pb.go`. Globs ending in `/` match a directory at any
+/// depth and everything below it. Others match the whole relative path,
+/// where `*` stays within a component and `**` crosses them. A leading `/`
+/// anchors any glob at the root.
+pub fn build_exclude_globs(patterns: &[String]) -> Result<GlobSet, globset::Error> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(compile_glob(pattern)?);
+    }
+    builder.build()
+}
+
+/// Check if a path should be excluded from diff output.
+pub fn should_exclude_path(path: &str) -> bool {
+    // Check exact file matches
+    if let Some(filename) = path.rsplit('/').next() {
+        if EXCLUDED_FILES.contains(&filename) {
+            return true;
+        }
+    }
+    // Check directory pattern matches
+    EXCLUDED_PATTERNS
+        .iter()
+        .any(|pattern| matches_dir_pattern(path, pattern))
+}
+
+/// Check whether `.gitattributes` marks a path `linguist-generated`, as
+/// GitHub does for generated and vendored files.
+pub fn is_linguist_generated(repo: &git2::Repository, path: &str) -> bool {
+    let value = repo
+        .get_attr_bytes(
+            Path::new(path),
+            "linguist-generated",
+            git2::AttrCheckFlags::FILE_THEN_INDEX,
+        )
+        .ok()
+        .flatten();
+    match git2::AttrValue::always_bytes(value) {
+        git2::AttrValue::True => true,
+        git2::AttrValue::Bytes(value) => value.eq_ignore_ascii_case(b"true"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excludes_lock_files_by_name() {
+        assert!(should_exclude_path("Cargo.lock"));
+        assert!(should_exclude_path("web/package-lock.json"));
+        assert!(!should_exclude_path("src/main.rs"));
+    }
+
+    #[test]
+    fn test_dir_pattern_matches_full_component() {
+        assert!(should_exclude_path("node_modules/x.js"));
+        assert!(should_exclude_path("a/node_modules/x.js"));
+        assert!(should_exclude_path("a/node_modules/pkg/index.js"));
+    }
+
+    #[test]
+    fn test_exclude_globs() {
+        let globs = build_exclude_globs(&["*.pb.go".to_string(), "/docs/*.md".to_string()])
+            .expect("valid globs");
+        assert!(globs.is_match("api/foo.pb.go"));
+        assert!(globs.is_match("foo.pb.go"));
+        assert!(!globs.is_match("main.go"));
+        assert!(globs.is_match("docs/intro.md"));
+        assert!(!globs.is_match("docs/guide/intro.md"));
+        assert!(!globs.is_match("src/docs/intro.md"));
+    }
+
+    #[test]
+    fn test_dir_pattern_does_not_match_substring() {
+        assert!(!should_exclude_path("a/my_node_modules_backup/x.js"));
+        assert!(!should_exclude_path("a/node_modules_old/x.js"));
+        // A file literally named node_modules is not a directory
+        assert!(!should_exclude_path("a/node_modules"));
+    }
+
+    #[test]
+    fn test_matches_exclude_entry() {
+        assert!(matches_exclude("web/dist/app.js", "dist/"));
+        assert!(matches_exclude("api/schema.graphql", "schema.graphql"));
+        assert!(!matches_exclude("api/schema.graphql.bak", "schema.graphql"));
+    }
+
+    #[test]
+    fn test_anchored_entry_matches_only_at_root() {
+        assert!(matches_exclude("Cargo.lock", "/Cargo.lock"));
+        assert!(!matches_exclude("crates/foo/Cargo.lock", "/Cargo.lock"));
+        assert!(matches_exclude("vendor/lib.rb", "/vendor/"));
+        assert!(!matches_exclude("x/vendor/lib.rb", "/vendor/"));
+    }
+
+    #[test]
+    fn test_exclude_glob_directories() {
+        let globs = build_exclude_globs(&["dist/".to_string(), "/vendor/".to_string()])
+            .expect("valid globs");
+        assert!(globs.is_match("dist/app.js"));
+        assert!(globs.is_match("web/dist/app.js"));
+        assert!(!globs.is_match("web/dist"));
+        assert!(globs.is_match("vendor/lib.rb"));
+        assert!(!globs.is_match("x/vendor/lib.rb"));
+    }
+
+    #[test]
+    fn test_nearest_lumenignore_wins() {
+        let dir = crate::vcs::test_utils::make_temp_dir("lumenignore");
+        std::fs::create_dir_all(dir.join("apps/web/src")).unwrap();
+        std::fs::write(dir.join(IGNORE_FILE), "*.lock\n").unwrap();
+        std::fs::write(
+            dir.join("apps/web").join(IGNORE_FILE),
+            "# generated\n*.css.d.ts\n",
+        )
+        .unwrap();
+
+        let ignore = LumenIgnore::new(&dir);
+        assert!(ignore.is_ignored("apps/web/src/button.css.d.ts"));
+        // The nearer file replaces the root rules
+        assert!(!ignore.is_ignored("apps/web/yarn.lock"));
+        assert!(ignore.is_ignored("apps/api/yarn.lock"));
+        assert!(!ignore.is_ignored("apps/api/button.css.d.ts"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_multi_component_dir_pattern() {
+        assert!(matches_dir_pattern(
+            "x/vendor/cache/lib.rb",
+            "vendor/cache/"
+        ));
+        assert!(!matches_dir_pattern("x/vendor/lib.rb", "vendor/cache/"));
+        assert!(!matches_dir_pattern(
+            "x/cache/vendor/lib.rb",
+            "vendor/cache/"
+        ));
+    }
+}
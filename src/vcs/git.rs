@@ -1,8 +1,16 @@
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
-use git2::{Commit, DiffFormat, DiffOptions, Repository, StatusOptions, Time, Tree};
+use git2::{
+    Commit, DiffFormat, DiffOptions, Repository, Signature, Sort, StatusOptions, Time, Tree,
+};
 
-use super::backend::{CommitInfo, StackedCommitInfo, VcsBackend, VcsError};
+use super::backend::{
+    Capabilities, CommitInfo, DiffConfig, DiffStats, StackedCommitInfo, VcsBackend, VcsError,
+};
+use super::conventional::Changelog;
+use super::exclude::{is_linguist_generated, PathFilter};
+use super::settings::RepoSettings;
 
 /// Format a duration in seconds as relative time (e.g., "2 hours ago").
 fn format_relative_time(secs_ago: i64) -> String {
@@ -56,6 +64,38 @@ fn format_relative_time(secs_ago: i64) -> String {
     )
 }
 
+/// How precisely the fzf log shows commit times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[allow(dead_code)] // Coarse is not exposed on the CLI yet
+pub enum TimeGranularity {
+    /// Exact relative time ("3 hours ago")
+    #[default]
+    Precise,
+    /// Buckets: today, yesterday, this week, this month, older
+    Coarse,
+}
+
+impl TimeGranularity {
+    fn format(self, secs_ago: i64) -> String {
+        match self {
+            TimeGranularity::Precise => format_relative_time(secs_ago),
+            TimeGranularity::Coarse => format_coarse_time(secs_ago).to_string(),
+        }
+    }
+}
+
+/// Format a duration in seconds as a coarse bucket (e.g., "this week").
+fn format_coarse_time(secs_ago: i64) -> &'static str {
+    const DAY: i64 = 24 * 60 * 60;
+    match secs_ago {
+        s if s < DAY => "today",
+        s if s < 2 * DAY => "yesterday",
+        s if s < 7 * DAY => "this week",
+        s if s < 30 * DAY => "this month",
+        _ => "older",
+    }
+}
+
 /// Format git2::Time as YYYY-MM-DD HH:MM:SS.
 fn format_git_time(time: &Time) -> String {
     // git2::Time provides seconds since epoch and offset in minutes
@@ -83,6 +123,108 @@ fn format_git_time(time: &Time) -> String {
     )
 }
 
+/// Format a signature as "Name <email>".
+fn format_signature(sig: &Signature) -> String {
+    format!(
+        "{} <{}>",
+        sig.name().unwrap_or(""),
+        sig.email().unwrap_or("")
+    )
+}
+
+/// Read a Gerrit-style `Change-Id:` trailer from a commit message.
+/// Git has no native change IDs, so this is the conventional stand-in.
+fn change_id_from_message(message: &str) -> Option<String> {
+    let trailers = git2::message_trailers_strs(message).ok()?;
+    trailers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("Change-Id"))
+        .map(|(_, value)| value.trim().to_string())
+}
+
+/// Append `Key: value` trailers to a commit message. They join the message's
+/// existing trailer block if it has one, or start a new block after a blank
+/// line. Trailers already present with the same key and value are skipped.
+fn append_trailers(message: &str, trailers: &[(String, String)]) -> String {
+    let mut seen: Vec<(String, String)> = git2::message_trailers_strs(message)
+        .map(|existing| {
+            existing
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.trim().to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+    let has_block = !seen.is_empty();
+
+    let mut lines = Vec::new();
+    for (key, value) in trailers {
+        let trailer = (key.trim().to_string(), value.trim().to_string());
+        if !seen.contains(&trailer) {
+            lines.push(format!("{}: {}", trailer.0, trailer.1));
+            seen.push(trailer);
+        }
+    }
+
+    let mut output = message.trim_end().to_string();
+    if !lines.is_empty() {
+        output.push_str(if has_block { "\n" } else { "\n\n" });
+        output.push_str(&lines.join("\n"));
+    }
+    output.push('\n');
+    output
+}
+
+/// Split a commit message into its text and trailers. The trailer block is
+/// the last paragraph; messages without one are returned whole.
+fn split_trailers(message: &str) -> (String, Vec<(String, String)>) {
+    let trailers: Vec<(String, String)> = git2::message_trailers_strs(message)
+        .map(|trailers| {
+            trailers
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.trim().to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+    let message = message.trim_end();
+    if trailers.is_empty() {
+        return (message.to_string(), trailers);
+    }
+    let text = message
+        .rsplit_once("\n\n")
+        .map(|(text, _)| text.trim_end())
+        .unwrap_or(message);
+    (text.to_string(), trailers)
+}
+
+/// 1-based numbers of the lines in `content` that are merge conflict
+/// markers, as git writes them. A bare `=======` also underlines Markdown
+/// headings and RST sections, so it only counts between a `<<<<<<<` line
+/// and the `>>>>>>>` line closing it.
+fn conflict_marker_lines(content: &str) -> Vec<usize> {
+    let mut markers = Vec::new();
+    // Separators of the open conflict, reported once it is closed
+    let mut separators = Vec::new();
+    let mut open = false;
+    for (idx, line) in content.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        let is_marker = |marker: &str| line == marker || line.starts_with(&format!("{} ", marker));
+        if is_marker("<<<<<<<") {
+            open = true;
+            separators.clear();
+            markers.push(idx + 1);
+        } else if is_marker(">>>>>>>") {
+            open = false;
+            markers.append(&mut separators);
+            markers.push(idx + 1);
+        } else if open && (line == "=======" || is_marker("|||||||")) {
+            separators.push(idx + 1);
+        } else if is_marker("|||||||") {
+            markers.push(idx + 1);
+        }
+    }
+    markers
+}
+
 /// Convert days since Unix epoch to (year, month, day).
 fn days_to_ymd(days: i64) -> (i32, u32, u32) {
     // Algorithm from Howard Hinnant's date algorithms
@@ -100,45 +242,316 @@ fn days_to_ymd(days: i64) -> (i32, u32, u32) {
     (y as i32, m, d)
 }
 
-/// Files to exclude from diff output.
-const EXCLUDED_FILES: &[&str] = &[
-    "package-lock.json",
-    "yarn.lock",
-    "pnpm-lock.yaml",
-    "Cargo.lock",
+/// Identifies a hunk within a diff by new path and old/new start lines.
+type HunkKey = (Option<PathBuf>, u32, u32);
+
+/// Per-call narrowing of `format_diff_filtered` output, on top of the
+/// backend's own options.
+#[derive(Default)]
+struct DiffFilter<'a> {
+    /// Keep only hunks with an added or removed line matching this
+    grep: Option<&'a regex::Regex>,
+    /// Keep only files accepted by this
+    keep_path: Option<&'a dyn Fn(&git2::DiffDelta) -> bool>,
+}
+
+/// Output of `render_diff`.
+struct RenderedDiff {
+    /// The rendered files
+    text: String,
+    /// Offset in `text` where each rendered file starts, by new path
+    file_starts: Vec<(Option<PathBuf>, usize)>,
+    /// Trailing `... and N more files` note when `max_files` cut files
+    note: String,
+}
+
+/// Hunks and files `format_diff` leaves out.
+#[derive(Default)]
+struct HunkSelection {
+    /// Excluded files, judged once per delta rather than per printed line
+    excluded: HashSet<Option<PathBuf>>,
+    dropped: HashSet<HunkKey>,
+    /// Files with every hunk dropped
+    dropped_files: HashSet<Option<PathBuf>>,
+    /// Number of hunks cut from each file by `max_hunks_per_file`
+    omitted: HashMap<Option<PathBuf>, usize>,
+}
+
+/// A changed file with its change kind and both sides' paths.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedFile {
+    /// Kind of change (added, modified, deleted, renamed, ...)
+    pub status: git2::Delta,
+    /// Path before the change (None for additions)
+    pub old_path: Option<String>,
+    /// Path after the change (None for deletions)
+    pub new_path: Option<String>,
+}
+
+/// Where repos keep their pull request template, in lookup order.
+const PR_TEMPLATE_PATHS: &[&str] = &[
+    ".github/pull_request_template.md",
+    ".github/PULL_REQUEST_TEMPLATE.md",
+    "docs/pull_request_template.md",
+    "docs/PULL_REQUEST_TEMPLATE.md",
+    "pull_request_template.md",
+    "PULL_REQUEST_TEMPLATE.md",
 ];
 
-/// Path patterns to exclude from diff output.
-const EXCLUDED_PATTERNS: &[&str] = &["node_modules/"];
+/// Rename and copy detection settings, as in `git diff -M<n> -C -B<n>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenameOptions {
+    /// Similarity percentage at which a deleted/added pair counts as a rename
+    pub rename_threshold: u16,
+    /// Break modifications that rewrite most of a file into a delete and an
+    /// add, so each half can pair with another file as a rename
+    pub break_rewrites: bool,
+    /// Dissimilarity percentage at which a modification is broken
+    pub break_rewrite_threshold: u16,
+    /// Also pair added files with modified files they were copied from
+    pub copies: bool,
+}
 
-/// Check if a path should be excluded from diff output.
-fn should_exclude_path(path: &str) -> bool {
-    // Check exact file matches
-    if let Some(filename) = path.rsplit('/').next() {
-        if EXCLUDED_FILES.contains(&filename) {
-            return true;
+impl Default for RenameOptions {
+    /// git's `-M50% -C` thresholds, without breaking rewrites
+    fn default() -> Self {
+        RenameOptions {
+            rename_threshold: 50,
+            break_rewrites: false,
+            break_rewrite_threshold: 60,
+            copies: true,
         }
     }
-    // Check pattern matches
-    for pattern in EXCLUDED_PATTERNS {
-        if path.contains(pattern) {
-            return true;
+}
+
+/// Hard ceilings on formatted diff size. Unlike `max_files` and the other
+/// truncation options, exceeding one aborts the diff with
+/// `VcsError::DiffTooLarge` instead of cutting it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffLimits {
+    /// Maximum bytes of formatted diff output
+    pub max_total_bytes: Option<usize>,
+    /// Maximum number of files in the diff
+    pub max_files: Option<usize>,
+}
+
+impl DiffLimits {
+    fn exceeded(&self, bytes: usize, files: usize) -> bool {
+        self.max_total_bytes.is_some_and(|max| bytes > max)
+            || self.max_files.is_some_and(|max| files > max)
+    }
+}
+
+/// Kinds of refs a picker can offer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)] // The fzf picker still lists commits only
+pub enum PickKind {
+    Commit,
+    Branch,
+    Tag,
+}
+
+impl PickKind {
+    /// Label shown before each item of this kind
+    pub fn label(self) -> &'static str {
+        match self {
+            PickKind::Commit => "commit",
+            PickKind::Branch => "branch",
+            PickKind::Tag => "tag",
         }
     }
-    false
 }
 
+/// A ref offered by a picker, with the line to show for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PickItem {
+    pub kind: PickKind,
+    /// Reference to resolve once picked: short SHA, branch or tag name
+    pub reference: String,
+    /// `<label> <description>`, e.g. `branch main`
+    pub display: String,
+}
+
+/// Everything needed to describe a branch as a pull request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrSummary {
+    /// Merge base of the base and head refs
+    pub merge_base: String,
+    /// Number of non-empty commits from the merge base to head
+    pub commits: usize,
+    /// Lines added across the branch, excluding filtered files
+    pub insertions: usize,
+    /// Lines removed across the branch, excluding filtered files
+    pub deletions: usize,
+    /// Changed files relative to the merge base, excluding filtered files
+    pub files: Vec<ChangedFile>,
+}
+
+/// A stash entry compared against the current index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StashComparison {
+    /// Unified diff from the index to the stash's working tree state
+    pub diff: String,
+    /// Whether applying the stash onto the index would conflict
+    pub conflicts: bool,
+}
+
+/// A top-level entry of a tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeEntryInfo {
+    pub name: String,
+    /// Blob for files, tree for directories, commit for submodules
+    pub kind: Option<git2::ObjectType>,
+    pub id: String,
+}
+
+/// What kind of thing a ref name points to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefKind {
+    Branch,
+    RemoteBranch,
+    Tag,
+    /// A commit named directly, e.g. by SHA or `HEAD~2`
+    Commit,
+    /// Anything else, such as a tree or a non-branch reference like HEAD
+    Other,
+}
+
+/// The three sides of a conflicted path. A side is None when the file
+/// doesn't exist there (add/add has no ancestor, delete/modify lacks one side).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictDetails {
+    pub ancestor: Option<String>,
+    pub ours: Option<String>,
+    pub theirs: Option<String>,
+}
+
+/// Changed lines longer than this (in chars) are replaced with a
+/// `<long line: N chars>` summary by default.
+pub const DEFAULT_LONG_LINE_THRESHOLD: usize = 2000;
+
 /// Git backend using git2 (libgit2) for repository access.
 pub struct GitBackend {
     repo: Repository,
+    /// Resolve author/committer identities through `.mailmap`
+    use_mailmap: bool,
+    /// Per-repo settings from `.lumen.toml`
+    settings: RepoSettings,
+    /// Drop context lines and hunk positions for token-minimized output
+    minimal_headers: bool,
+    /// Follow only first parents when enumerating commits
+    first_parent: bool,
+    /// Maximum number of files to include in formatted diffs
+    max_files: Option<usize>,
+    /// Base fzf log relative times on committer time rather than author time
+    use_committer_time: bool,
+    /// Maximum diff lines per file before the rest is cut
+    per_file_max_lines: Option<usize>,
+    /// Precision of relative times in the fzf log
+    time_granularity: TimeGranularity,
+    /// Prefix content lines with their old/new line numbers
+    line_numbers: bool,
+    /// Changed lines longer than this many chars are summarized
+    long_line_threshold: Option<usize>,
+    /// Omit hunks whose changed lines are all whitespace
+    drop_blank_only_hunks: bool,
+    /// New files with fewer lines than this are shown as plain content
+    inline_new_files_under: Option<usize>,
+    /// Keep only this many of the largest hunks per file
+    max_hunks_per_file: Option<usize>,
+    /// Runs of more context lines than this are folded to a marker
+    fold_context_over: Option<usize>,
+    /// Exclusion rules shared with the jj backend
+    paths: PathFilter,
+    /// Rename detection tuning for diff output, or None to skip it
+    rename_options: Option<RenameOptions>,
+    /// Size ceilings that abort diff formatting
+    diff_limits: Option<DiffLimits>,
 }
 
 impl GitBackend {
     /// Open a git repository at the given path.
     /// Uses git2::Repository::discover to find the repo from any subdirectory.
+    /// Settings are read from `.lumen.toml` at the workdir root, if present.
     pub fn new(path: &Path) -> Result<Self, VcsError> {
-        let repo = Repository::discover(path).map_err(|_| VcsError::NotARepository)?;
-        Ok(GitBackend { repo })
+        let git_dir = std::env::var_os("GIT_DIR");
+        let work_tree = std::env::var_os("GIT_WORK_TREE");
+        let repo = Self::open_repository(
+            path,
+            git_dir.as_deref().map(Path::new),
+            work_tree.as_deref().map(Path::new),
+        )?;
+        let settings = match repo.workdir() {
+            Some(workdir) => RepoSettings::load(workdir)?,
+            None => RepoSettings::default(),
+        };
+        let paths = PathFilter::new(repo.workdir(), settings.excludes.clone());
+        Ok(GitBackend {
+            repo,
+            use_mailmap: true,
+            settings,
+            minimal_headers: false,
+            first_parent: false,
+            max_files: None,
+            use_committer_time: true,
+            per_file_max_lines: None,
+            time_granularity: TimeGranularity::Precise,
+            line_numbers: false,
+            long_line_threshold: Some(DEFAULT_LONG_LINE_THRESHOLD),
+            drop_blank_only_hunks: false,
+            inline_new_files_under: None,
+            max_hunks_per_file: None,
+            fold_context_over: None,
+            paths,
+            rename_options: Some(RenameOptions::default()),
+            diff_limits: None,
+        })
+    }
+
+    /// Open the repository, honoring `GIT_DIR` / `GIT_WORK_TREE` overrides.
+    /// Without `GIT_DIR`, the repo is discovered from `path`. Relative
+    /// override paths are resolved against `path`, like git does with cwd.
+    fn open_repository(
+        path: &Path,
+        git_dir: Option<&Path>,
+        work_tree: Option<&Path>,
+    ) -> Result<Repository, VcsError> {
+        let Some(git_dir) = git_dir else {
+            return Repository::discover(path).map_err(|_| VcsError::NotARepository);
+        };
+
+        let repo = Repository::open_ext(
+            path.join(git_dir),
+            git2::RepositoryOpenFlags::NO_SEARCH,
+            std::iter::empty::<&std::ffi::OsStr>(),
+        )
+        .map_err(|_| VcsError::NotARepository)?;
+        if let Some(work_tree) = work_tree {
+            repo.set_workdir(&path.join(work_tree), false)
+                .map_err(|e| VcsError::Other(format!("failed to set work tree: {}", e)))?;
+        }
+        Ok(repo)
+    }
+
+    /// Lines of context around each hunk (`.lumen.toml` or 3).
+    /// Minimal-header mode always uses zero context.
+    fn context_lines(&self) -> u32 {
+        if self.minimal_headers {
+            return 0;
+        }
+        self.settings.context_lines.unwrap_or(3)
+    }
+
+    /// Branch to compare against when none is given, from `.lumen.toml`.
+    #[allow(dead_code)] // Not yet consulted by commands
+    pub fn default_base(&self) -> Option<&str> {
+        self.settings.default_base.as_deref()
+    }
+
+    /// Check if a path is excluded by the shared path rules or
+    /// `.gitattributes`.
+    fn is_excluded_path(&self, path: &str) -> bool {
+        self.paths.is_excluded(path) || is_linguist_generated(&self.repo, path)
     }
 
     /// Open a git repository from the current working directory.
@@ -159,186 +572,231 @@ impl GitBackend {
         Ok(())
     }
 
-    /// Generate unified diff for a commit, comparing to its parent.
-    /// For root commits (no parent), compares to an empty tree.
-    fn generate_commit_diff(&self, commit: &Commit) -> Result<String, VcsError> {
-        let tree = commit
-            .tree()
-            .map_err(|e| VcsError::Other(format!("failed to get commit tree: {}", e)))?;
+    /// Resolve a reference to a commit, validating its format first.
+    fn resolve_commit(&self, reference: &str) -> Result<Commit<'_>, VcsError> {
+        let reference = reference.trim();
+        Self::validate_ref_format(reference)?;
+        self.repo
+            .revparse_single(reference)
+            .and_then(|obj| obj.peel_to_commit())
+            .map_err(|_| VcsError::InvalidRef(reference.to_string()))
+    }
 
-        // Get parent tree (or None for root commits)
-        let parent_tree: Option<Tree> = if commit.parent_count() > 0 {
+    /// Get the first parent's tree, or None for root commits.
+    fn first_parent_tree<'r>(commit: &Commit<'r>) -> Option<Tree<'r>> {
+        if commit.parent_count() > 0 {
             commit.parent(0).ok().and_then(|p| p.tree().ok())
         } else {
             None
-        };
+        }
+    }
 
-        // Create diff with options
-        let mut opts = DiffOptions::new();
-        opts.show_binary(true);
-        opts.context_lines(3);
+    /// Whether a commit records no change: its tree is its first parent's tree
+    /// (or empty for root commits). Compares OIDs only, without diffing.
+    fn commit_is_empty(commit: &Commit) -> bool {
+        match commit.parent(0) {
+            Ok(parent) => parent.tree_id() == commit.tree_id(),
+            Err(_) => commit.tree().map(|tree| tree.is_empty()).unwrap_or(false),
+        }
+    }
 
-        let diff = self
-            .repo
-            .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut opts))
-            .map_err(|e| VcsError::Other(format!("failed to create diff: {}", e)))?;
+    /// Diff a commit against its first parent (or the empty tree for root commits).
+    fn diff_commit_to_parent(
+        &self,
+        commit: &Commit,
+        opts: Option<&mut DiffOptions>,
+    ) -> Result<git2::Diff<'_>, VcsError> {
+        let tree = commit
+            .tree()
+            .map_err(|e| VcsError::Other(format!("failed to get commit tree: {}", e)))?;
+        let parent_tree = Self::first_parent_tree(commit);
+        self.repo
+            .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), opts)
+            .map_err(|e| VcsError::Other(format!("failed to create diff: {}", e)))
+    }
 
-        // Format diff as unified patch, filtering excluded files
-        let mut output = String::new();
-        diff.print(DiffFormat::Patch, |delta, _hunk, line| {
-            // Check if this file should be excluded
-            if let Some(path) = delta.new_file().path().and_then(|p| p.to_str()) {
-                if should_exclude_path(path) {
-                    return true; // Skip this line
-                }
-            }
-            if let Some(path) = delta.old_file().path().and_then(|p| p.to_str()) {
-                if should_exclude_path(path) {
-                    return true; // Skip this line
-                }
-            }
+    /// Check if either side of a delta is an excluded path.
+    fn is_excluded_delta(&self, delta: &git2::DiffDelta) -> bool {
+        [delta.new_file().path(), delta.old_file().path()]
+            .into_iter()
+            .flatten()
+            .filter_map(|p| p.to_str())
+            .any(|path| self.is_excluded_path(path))
+    }
 
-            // Determine line prefix based on origin
-            let prefix = match line.origin() {
-                '+' | '-' | ' ' => line.origin(),
-                'F' | 'H' | 'B' => '\0', // File header, hunk header, binary - no prefix
-                _ => '\0',
-            };
+    /// Count the files in a diff, excluding filtered paths.
+    fn count_included_files(&self, diff: &git2::Diff) -> usize {
+        diff.deltas()
+            .filter(|delta| !self.is_excluded_delta(delta))
+            .count()
+    }
 
-            if prefix != '\0' {
-                output.push(prefix);
-            }
-            if let Ok(content) = std::str::from_utf8(line.content()) {
-                output.push_str(content);
+    /// Read the target of a symlink on one side of a delta.
+    /// Uses the blob when the side is in the object database, and falls back
+    /// to reading the link from the working directory.
+    fn symlink_target(&self, file: &git2::DiffFile) -> Option<String> {
+        if file.mode() != git2::FileMode::Link {
+            return None;
+        }
+        if !file.id().is_zero() {
+            if let Ok(blob) = self.repo.find_blob(file.id()) {
+                return Some(String::from_utf8_lossy(blob.content()).into_owned());
             }
-            true
-        })
-        .map_err(|e| VcsError::Other(format!("failed to format diff: {}", e)))?;
-
-        Ok(output)
+        }
+        let path = self.repo.workdir()?.join(file.path()?);
+        std::fs::read_link(path)
+            .ok()
+            .map(|target| target.to_string_lossy().into_owned())
     }
 
-    /// Stage specific files for commit.
-    /// Files should be relative paths from the repository root.
-    pub fn stage_files(&self, paths: &[&Path]) -> Result<(), VcsError> {
-        let mut index = self
-            .repo
-            .index()
-            .map_err(|e| VcsError::Other(format!("failed to get index: {}", e)))?;
-
-        for path in paths {
-            index.add_path(path).map_err(|e| {
-                VcsError::Other(format!("failed to stage {}: {}", path.display(), e))
-            })?;
+    /// Build a `symlink path: <old> -> <new>` label for deltas where either
+    /// side is a symlink. A side that isn't a link is shown as `/dev/null`.
+    fn symlink_label(&self, delta: &git2::DiffDelta) -> Option<String> {
+        let old = self.symlink_target(&delta.old_file());
+        let new = self.symlink_target(&delta.new_file());
+        if old.is_none() && new.is_none() {
+            return None;
         }
-
-        index
-            .write()
-            .map_err(|e| VcsError::Other(format!("failed to write index: {}", e)))?;
-
-        Ok(())
+        Some(format!(
+            "symlink path: {} -> {}\n",
+            old.as_deref().unwrap_or("/dev/null"),
+            new.as_deref().unwrap_or("/dev/null")
+        ))
     }
 
-    /// Create a commit with the given message using the currently staged files.
-    /// Returns the commit SHA on success.
-    pub fn commit(&self, message: &str) -> Result<String, VcsError> {
-        // Get user's git config for author/committer
-        let config = self
+    /// Look up the textconv command for a path from its `diff` attribute
+    /// (`*.plist diff=plist` plus `diff.plist.textconv` in git config).
+    fn textconv_command(&self, path: &Path) -> Option<String> {
+        let value = self
             .repo
+            .get_attr(path, "diff", git2::AttrCheckFlags::FILE_THEN_INDEX)
+            .ok()??;
+        let git2::AttrValue::String(driver) = git2::AttrValue::from_string(Some(value)) else {
+            return None;
+        };
+        self.repo
             .config()
-            .map_err(|e| VcsError::Other(format!("failed to get git config: {}", e)))?;
-
-        let name = config.get_string("user.name").map_err(|_| {
-            VcsError::Other(
-                "git user.name not configured. Run: git config user.name \"Your Name\"".to_string(),
-            )
-        })?;
-
-        let email = config.get_string("user.email").map_err(|_| {
-            VcsError::Other(
-                "git user.email not configured. Run: git config user.email \"you@example.com\""
-                    .to_string(),
-            )
-        })?;
-
-        let sig = git2::Signature::now(&name, &email)
-            .map_err(|e| VcsError::Other(format!("failed to create signature: {}", e)))?;
-
-        let mut index = self
-            .repo
-            .index()
-            .map_err(|e| VcsError::Other(format!("failed to get index: {}", e)))?;
-
-        let tree_oid = index
-            .write_tree()
-            .map_err(|e| VcsError::Other(format!("failed to write tree: {}", e)))?;
-
-        let tree = self
-            .repo
-            .find_tree(tree_oid)
-            .map_err(|e| VcsError::Other(format!("failed to find tree: {}", e)))?;
-
-        let parent = self.repo.head().ok().and_then(|h| h.peel_to_commit().ok());
-        let parents: Vec<&git2::Commit> = parent.iter().collect();
-
-        let oid = self
-            .repo
-            .commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
-            .map_err(|e| VcsError::Other(format!("failed to create commit: {}", e)))?;
-
-        Ok(oid.to_string())
+            .ok()?
+            .get_string(&format!("diff.{}.textconv", driver))
+            .ok()
     }
-}
-
-impl VcsBackend for GitBackend {
-    fn get_commit(&self, reference: &str) -> Result<CommitInfo, VcsError> {
-        let reference = reference.trim();
-        Self::validate_ref_format(reference)?;
-
-        // Use git2 to get commit metadata
-        let obj = self
-            .repo
-            .revparse_single(reference)
-            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
-        let commit = obj
-            .peel_to_commit()
-            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
 
-        let commit_id = commit.id().to_string();
-        let author_sig = commit.author();
-        let author_name = author_sig.name().unwrap_or("");
-        let author_email = author_sig.email().unwrap_or("");
-        let author = format!("{} <{}>", author_name, author_email);
+    /// Read the content of one side of a delta, from the object database or
+    /// the working directory. Missing sides (additions/deletions) are empty.
+    fn delta_side_content(&self, file: &git2::DiffFile) -> Vec<u8> {
+        if !file.exists() {
+            return Vec::new();
+        }
+        if !file.id().is_zero() {
+            if let Ok(blob) = self.repo.find_blob(file.id()) {
+                return blob.content().to_vec();
+            }
+        }
+        file.path()
+            .and_then(|path| Some(self.repo.workdir()?.join(path)))
+            .and_then(|path| std::fs::read(path).ok())
+            .unwrap_or_default()
+    }
 
-        // Format time as YYYY-MM-DD HH:MM:SS
-        let time = commit.time();
-        let date = format_git_time(&time);
+    /// Run a textconv command on content. Like git, the command receives the
+    /// path of a temporary file holding the content as its last argument.
+    fn run_textconv(command: &str, content: &[u8]) -> Result<String, VcsError> {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let tmp =
+            std::env::temp_dir().join(format!("lumen-textconv-{}-{}", std::process::id(), nanos));
+        std::fs::write(&tmp, content)?;
+
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$@\"", command))
+            .arg(command)
+            .arg(&tmp)
+            .output();
+        let _ = std::fs::remove_file(&tmp);
+
+        let output = output?;
+        if !output.status.success() {
+            return Err(VcsError::CommandFailed(format!(
+                "textconv `{}` failed: {}",
+                command,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
 
-        let message = commit
-            .message()
-            .unwrap_or("")
-            .trim_end_matches('\n')
-            .to_string();
+    /// Render a file diff from textconv output, or None if the delta has no
+    /// textconv driver. Falls back to the regular diff if the command fails.
+    fn textconv_diff(&self, delta: &git2::DiffDelta) -> Option<String> {
+        let path = delta.new_file().path().or(delta.old_file().path())?;
+        let command = self.textconv_command(path)?;
+
+        let old = Self::run_textconv(&command, &self.delta_side_content(&delta.old_file())).ok()?;
+        let new = Self::run_textconv(&command, &self.delta_side_content(&delta.new_file())).ok()?;
+
+        let display = path.to_string_lossy();
+        let diff = similar::TextDiff::from_lines(&old, &new);
+        let mut output = format!("diff --git a/{} b/{}\n", display, display);
+        output.push_str(
+            &diff
+                .unified_diff()
+                .context_radius(self.context_lines() as usize)
+                .header(&format!("a/{}", display), &format!("b/{}", display))
+                .to_string(),
+        );
+        Some(output)
+    }
 
-        // Generate diff using git2
-        let diff = self.generate_commit_diff(&commit)?;
+    /// Render an added text file with fewer than `inline_new_files_under`
+    /// lines as its git file header, then `New file <path>:` and its content,
+    /// or None to diff it normally. Keeping the header lets patch parsers
+    /// attribute the file.
+    fn inline_new_file(&self, delta: &git2::DiffDelta, header: &[u8]) -> Option<String> {
+        let max_lines = self.inline_new_files_under?;
+        if !matches!(delta.status(), git2::Delta::Added | git2::Delta::Untracked) {
+            return None;
+        }
+        let file = delta.new_file();
+        let content = String::from_utf8(self.delta_side_content(&file)).ok()?;
+        if content.contains('\0') || content.lines().count() >= max_lines {
+            return None;
+        }
 
-        Ok(CommitInfo {
-            commit_id,
-            change_id: None, // Git doesn't have change IDs
-            message,
-            diff,
-            author,
-            date,
-        })
+        let mut output = String::from_utf8_lossy(header).into_owned();
+        output.push_str(&format!(
+            "New file {}:\n{}",
+            file.path()?.display(),
+            content
+        ));
+        if !output.ends_with('\n') {
+            output.push('\n');
+        }
+        Some(output)
     }
 
-    fn get_working_tree_diff(&self, staged: bool) -> Result<String, VcsError> {
+    /// Diff staged (HEAD to index) or unstaged (index to workdir) changes,
+    /// optionally limited to the given paths.
+    fn working_tree_diff_for_paths(
+        &self,
+        staged: bool,
+        paths: &[&Path],
+        context_lines: u32,
+    ) -> Result<String, VcsError> {
         let mut opts = DiffOptions::new();
         opts.show_binary(true);
-        opts.context_lines(3);
+        opts.context_lines(context_lines);
+        if !paths.is_empty() {
+            // Match paths literally rather than as globs
+            opts.disable_pathspec_match(true);
+            for path in paths {
+                opts.pathspec(path);
+            }
+        }
 
-        let diff = if staged {
+        let mut diff = if staged {
             // Staged: diff HEAD tree to index
             let head = self.repo.head().ok().and_then(|h| h.peel_to_tree().ok());
             self.repo
@@ -350,883 +808,6304 @@ impl VcsBackend for GitBackend {
                 .diff_index_to_workdir(None, Some(&mut opts))
                 .map_err(|e| VcsError::Other(format!("failed to create unstaged diff: {}", e)))?
         };
+        self.find_renames(&mut diff)?;
+
+        self.format_diff(&diff, true)
+    }
+
+    /// Pair deleted and added files into renames, and added files with their
+    /// copy sources, per the configured `RenameOptions`. Does nothing when
+    /// detection is off.
+    fn find_renames(&self, diff: &mut git2::Diff) -> Result<(), VcsError> {
+        let Some(renames) = self.rename_options else {
+            return Ok(());
+        };
+        let mut opts = git2::DiffFindOptions::new();
+        opts.renames(true)
+            .rename_threshold(renames.rename_threshold)
+            .copies(renames.copies)
+            .copy_threshold(renames.rename_threshold);
+        if renames.break_rewrites {
+            opts.rewrites(true)
+                .break_rewrites(true)
+                .break_rewrite_threshold(renames.break_rewrite_threshold)
+                .renames_from_rewrites(true)
+                .rename_from_rewrite_threshold(renames.rename_threshold);
+        }
+        diff.find_similar(Some(&mut opts))
+            .map_err(|e| VcsError::Other(format!("failed to detect renames: {}", e)))
+    }
+
+    /// Totals of a commit's diff against its first parent as
+    /// `generate_commit_diff` shows it: renames count as one file, and
+    /// excluded files are skipped.
+    fn commit_stats(&self, commit: &Commit) -> Result<DiffStats, VcsError> {
+        self.diff_stats(&self.commit_diff(commit, self.context_lines())?)
+    }
+
+    /// Totals of a diff, skipping excluded files.
+    fn diff_stats(&self, diff: &git2::Diff) -> Result<DiffStats, VcsError> {
+        let (insertions, deletions) = self.line_totals(diff)?;
+        Ok(DiffStats {
+            files_changed: self.count_included_files(diff),
+            insertions,
+            deletions,
+        })
+    }
+
+    /// Count (insertions, deletions) across a diff, skipping excluded files.
+    fn line_totals(&self, diff: &git2::Diff) -> Result<(usize, usize), VcsError> {
+        let (mut insertions, mut deletions) = (0, 0);
+        for (idx, delta) in diff.deltas().enumerate() {
+            if self.is_excluded_delta(&delta) {
+                continue;
+            }
+            let patch = git2::Patch::from_diff(diff, idx)
+                .map_err(|e| VcsError::Other(format!("failed to create patch: {}", e)))?;
+            if let Some(patch) = patch {
+                let (_, added, removed) = patch
+                    .line_stats()
+                    .map_err(|e| VcsError::Other(format!("failed to count lines: {}", e)))?;
+                insertions += added;
+                deletions += removed;
+            }
+        }
+        Ok((insertions, deletions))
+    }
+
+    /// Format a diff as a unified patch, filtering excluded files.
+    /// With `label_symlinks`, symlink deltas get a label line after the
+    /// file header.
+    /// Files past the `max_files` limit are dropped and summarized in a
+    /// trailing `... and N more files` note. Files with a textconv diff
+    /// driver are diffed on their converted text. Files longer than
+    /// `per_file_max_lines` are cut with a truncation marker, and overlong
+    /// changed lines (minified code) are summarized. Hunks chosen by
+    /// `select_hunks` are left out, and long context runs are folded with
+    /// `fold_context_over`. Exceeding `diff_limits` aborts with
+    /// `VcsError::DiffTooLarge`.
+    fn format_diff(&self, diff: &git2::Diff, label_symlinks: bool) -> Result<String, VcsError> {
+        self.format_diff_filtered(diff, label_symlinks, &DiffFilter::default())
+    }
+
+    /// `format_diff`, further narrowed by `filter`. Filtering works on the
+    /// diff's hunks before rendering, so it is unaffected by output options
+    /// such as line numbers, inlined files or folded context.
+    fn format_diff_filtered(
+        &self,
+        diff: &git2::Diff,
+        label_symlinks: bool,
+        filter: &DiffFilter,
+    ) -> Result<String, VcsError> {
+        let rendered = self.render_diff(diff, label_symlinks, filter)?;
+        Ok(rendered.text + &rendered.note)
+    }
 
-        // Format diff as unified patch, filtering excluded files
+    /// Render a diff for `format_diff_filtered`, keeping track of where each
+    /// file starts.
+    fn render_diff(
+        &self,
+        diff: &git2::Diff,
+        label_symlinks: bool,
+        filter: &DiffFilter,
+    ) -> Result<RenderedDiff, VcsError> {
+        let selection = self.select_hunks(diff, filter)?;
         let mut output = String::new();
-        diff.print(DiffFormat::Patch, |delta, _hunk, line| {
-            // Check if this file should be excluded
-            if let Some(path) = delta.new_file().path().and_then(|p| p.to_str()) {
-                if should_exclude_path(path) {
-                    return true;
+        let mut file_starts = Vec::new();
+        let mut files_seen = 0usize;
+        let mut over_limit = false;
+        // Set once the current file has been rendered whole (textconv or inlined)
+        let mut file_rendered = false;
+        // Content lines emitted for the current file
+        let mut file_lines = 0usize;
+        let mut file_truncated = false;
+        // Context lines since the last change, held back for folding
+        let mut pending_context: Vec<String> = Vec::new();
+        // Whether the current hunk has shown a change before the held run
+        let mut hunk_changed = false;
+        let fold_context_over = self.fold_context_over;
+        // `after_change` / `before_change`: a change precedes / follows the run
+        let flush_context = |output: &mut String,
+                             run: &mut Vec<String>,
+                             after_change: bool,
+                             before_change: bool| {
+            match fold_context_over {
+                Some(max) if run.len() > max => {
+                    // Keep the lines next to the surrounding changes: the
+                    // tail of leading context, the head of trailing context,
+                    // and both ends of a run between two changes
+                    let head = match (after_change, before_change) {
+                        (true, true) => max / 2,
+                        (false, true) => 0,
+                        _ => max,
+                    };
+                    let tail = max - head;
+                    for line in &run[..head] {
+                        output.push_str(line);
+                    }
+                    output.push_str(&format!("... {} unchanged lines ...\n", run.len() - max));
+                    for line in &run[run.len() - tail..] {
+                        output.push_str(line);
+                    }
+                }
+                _ => {
+                    for line in run.iter() {
+                        output.push_str(line);
+                    }
+                }
+            }
+            run.clear();
+        };
+        let mut too_large = false;
+        let print_result = diff.print(DiffFormat::Patch, |delta, hunk, line| {
+            if self
+                .diff_limits
+                .is_some_and(|limits| limits.exceeded(output.len(), files_seen))
+            {
+                too_large = true;
+                return false;
+            }
+            if line.origin() != ' ' {
+                let is_change = matches!(line.origin(), '+' | '-');
+                flush_context(&mut output, &mut pending_context, hunk_changed, is_change);
+                match line.origin() {
+                    'F' | 'H' => hunk_changed = false,
+                    '+' | '-' => hunk_changed = true,
+                    _ => {}
                 }
             }
-            if let Some(path) = delta.old_file().path().and_then(|p| p.to_str()) {
-                if should_exclude_path(path) {
+            let path = delta.new_file().path().map(Path::to_path_buf);
+            if selection.excluded.contains(&path) || selection.dropped_files.contains(&path) {
+                return true; // Skip this line
+            }
+            if let Some(hunk) = hunk {
+                if selection
+                    .dropped
+                    .contains(&(path.clone(), hunk.old_start(), hunk.new_start()))
+                {
                     return true;
                 }
             }
 
-            let prefix = match line.origin() {
-                '+' | '-' | ' ' => line.origin(),
-                _ => '\0',
-            };
-            if prefix != '\0' {
-                output.push(prefix);
+            // Deltas arrive sorted by path, so the first N files are stable
+            if line.origin() == 'F' {
+                files_seen += 1;
+                over_limit = self.max_files.is_some_and(|max| files_seen > max);
+                file_rendered = false;
+                file_lines = 0;
+                file_truncated = false;
+                if !over_limit {
+                    file_starts.push((path.clone(), output.len()));
+                    let rendered = self
+                        .textconv_diff(&delta)
+                        .or_else(|| self.inline_new_file(&delta, line.content()));
+                    if let Some(rendered) = rendered {
+                        output.push_str(&rendered);
+                        file_rendered = true;
+                    }
+                }
             }
-            if let Ok(content) = std::str::from_utf8(line.content()) {
-                output.push_str(content);
+            if over_limit || file_rendered || file_truncated {
+                return true;
+            }
+            if matches!(line.origin(), '+' | '-' | ' ') {
+                file_lines += 1;
+                if self.per_file_max_lines.is_some_and(|max| file_lines > max) {
+                    flush_context(&mut output, &mut pending_context, hunk_changed, false);
+                    output.push_str("... [file truncated] ...\n");
+                    file_truncated = true;
+                    return true;
+                }
             }
-            true
-        })
-        .map_err(|e| VcsError::Other(format!("failed to format diff: {}", e)))?;
 
-        Ok(output)
+            // Content lines get their origin as prefix; file headers, hunk
+            // headers, and binary notices are printed as-is
+            let mut rendered = String::new();
+            if matches!(line.origin(), '+' | '-' | ' ') {
+                if self.line_numbers {
+                    let column = |n: Option<u32>| n.map(|n| n.to_string()).unwrap_or_default();
+                    rendered.push_str(&format!(
+                        "{:>4} {:>4} ",
+                        column(line.old_lineno()),
+                        column(line.new_lineno())
+                    ));
+                }
+                rendered.push(line.origin());
+            }
+            if self.minimal_headers && line.origin() == 'H' {
+                output.push_str("@@ hunk @@\n");
+                return true;
+            }
+            if let Ok(content) = std::str::from_utf8(line.content()) {
+                let text = content.trim_end_matches('\n');
+                let is_changed = matches!(line.origin(), '+' | '-');
+                match self.long_line_threshold {
+                    // Minified files: summarize instead of spending the budget
+                    Some(max) if is_changed && text.chars().count() > max => {
+                        rendered
+                            .push_str(&format!("<long line: {} chars>\n", text.chars().count()));
+                    }
+                    _ => rendered.push_str(content),
+                }
+            }
+            if label_symlinks && line.origin() == 'F' {
+                if let Some(label) = self.symlink_label(&delta) {
+                    rendered.push_str(&label);
+                }
+            }
+            // Noted in the file header, so patch parsers don't take it for
+            // hunk content
+            if line.origin() == 'F' {
+                if let Some(omitted) = selection.omitted.get(&path) {
+                    rendered.push_str(&format!("... [{} smaller hunks omitted] ...\n", omitted));
+                }
+            }
+            if fold_context_over.is_some() && line.origin() == ' ' {
+                pending_context.push(rendered);
+            } else {
+                output.push_str(&rendered);
+            }
+            true
+        });
+        flush_context(&mut output, &mut pending_context, hunk_changed, false);
+        if too_large
+            || self
+                .diff_limits
+                .is_some_and(|limits| limits.exceeded(output.len(), files_seen))
+        {
+            return Err(VcsError::DiffTooLarge {
+                bytes: output.len(),
+                files: files_seen,
+            });
+        }
+        print_result.map_err(|e| VcsError::Other(format!("failed to format diff: {}", e)))?;
+
+        let mut note = String::new();
+        if let Some(max) = self.max_files {
+            if files_seen > max {
+                note = format!("... and {} more files\n", files_seen - max);
+            }
+        }
+
+        Ok(RenderedDiff {
+            text: output,
+            file_starts,
+            note,
+        })
     }
 
-    fn get_range_diff(&self, from: &str, to: &str, three_dot: bool) -> Result<String, VcsError> {
-        Self::validate_ref_format(from)?;
-        Self::validate_ref_format(to)?;
+    /// Decide which files and hunks `format_diff` leaves out: excluded
+    /// files, blank-line-only hunks when `drop_blank_only_hunks` is set,
+    /// hunks without a `filter.grep` match, and all but the largest
+    /// `max_hunks_per_file` hunks of each file by changed-line count.
+    fn select_hunks(
+        &self,
+        diff: &git2::Diff,
+        filter: &DiffFilter,
+    ) -> Result<HunkSelection, VcsError> {
+        let mut selection = HunkSelection::default();
+        let select = self.drop_blank_only_hunks
+            || self.max_hunks_per_file.is_some()
+            || filter.grep.is_some();
+
+        for (idx, delta) in diff.deltas().enumerate() {
+            if self.is_excluded_delta(&delta) {
+                selection
+                    .excluded
+                    .insert(delta.new_file().path().map(Path::to_path_buf));
+                continue;
+            }
+            if filter.keep_path.is_some_and(|keep| !keep(&delta)) {
+                selection
+                    .dropped_files
+                    .insert(delta.new_file().path().map(Path::to_path_buf));
+                continue;
+            }
+            if !select {
+                continue;
+            }
+            let patch = git2::Patch::from_diff(diff, idx)
+                .map_err(|e| VcsError::Other(format!("failed to create patch: {}", e)))?;
+            let path = delta.new_file().path().map(Path::to_path_buf);
+            let Some(patch) = patch else {
+                // Nothing in a file without hunks can match
+                if filter.grep.is_some() {
+                    selection.dropped_files.insert(path);
+                }
+                continue;
+            };
 
-        // Resolve both refs to commits
-        let from_obj = self
-            .repo
-            .revparse_single(from)
-            .map_err(|_| VcsError::InvalidRef(from.to_string()))?;
-        let from_commit = from_obj
-            .peel_to_commit()
-            .map_err(|_| VcsError::InvalidRef(from.to_string()))?;
+            // (key, changed lines) of the hunks that survive the blank and
+            // grep filters
+            let mut kept: Vec<(HunkKey, usize)> = Vec::new();
+            for hunk_idx in 0..patch.num_hunks() {
+                let (hunk, line_count) = patch
+                    .hunk(hunk_idx)
+                    .map_err(|e| VcsError::Other(format!("failed to get hunk: {}", e)))?;
+                let key = (path.clone(), hunk.old_start(), hunk.new_start());
+                let mut changed = 0;
+                let mut blank = true;
+                let mut matched = false;
+                for line_idx in 0..line_count {
+                    let line = patch
+                        .line_in_hunk(hunk_idx, line_idx)
+                        .map_err(|e| VcsError::Other(format!("failed to get hunk line: {}", e)))?;
+                    if matches!(line.origin(), '+' | '-') {
+                        changed += 1;
+                        blank &= line.content().iter().all(u8::is_ascii_whitespace);
+                        matched |= filter.grep.is_some_and(|regex| {
+                            let text = String::from_utf8_lossy(line.content());
+                            regex.is_match(text.trim_end_matches('\n'))
+                        });
+                    }
+                }
+                if (blank && self.drop_blank_only_hunks) || (filter.grep.is_some() && !matched) {
+                    selection.dropped.insert(key);
+                } else {
+                    kept.push((key, changed));
+                }
+            }
+            if (patch.num_hunks() > 0 || filter.grep.is_some()) && kept.is_empty() {
+                selection.dropped_files.insert(path);
+                continue;
+            }
 
-        let to_obj = self
-            .repo
-            .revparse_single(to)
-            .map_err(|_| VcsError::InvalidRef(to.to_string()))?;
-        let to_commit = to_obj
-            .peel_to_commit()
-            .map_err(|_| VcsError::InvalidRef(to.to_string()))?;
+            if let Some(max) = self.max_hunks_per_file {
+                if kept.len() > max {
+                    // Stable sort: equally sized hunks keep file order
+                    kept.sort_by_key(|(_, changed)| std::cmp::Reverse(*changed));
+                    let omitted = kept.len() - max;
+                    selection
+                        .dropped
+                        .extend(kept.drain(max..).map(|(key, _)| key));
+                    selection.omitted.insert(path, omitted);
+                }
+            }
+        }
+        Ok(selection)
+    }
 
-        // For three-dot syntax, compare merge-base to 'to'
-        // For two-dot syntax, compare 'from' to 'to'
-        let base_tree = if three_dot {
-            // Find merge base
-            let merge_base_oid = self
-                .repo
-                .merge_base(from_commit.id(), to_commit.id())
-                .map_err(|e| VcsError::Other(format!("failed to find merge base: {}", e)))?;
-            let merge_base = self
-                .repo
-                .find_commit(merge_base_oid)
-                .map_err(|e| VcsError::Other(format!("failed to find merge base commit: {}", e)))?;
-            merge_base
-                .tree()
-                .map_err(|e| VcsError::Other(format!("failed to get merge base tree: {}", e)))?
-        } else {
-            from_commit
-                .tree()
-                .map_err(|e| VcsError::Other(format!("failed to get from tree: {}", e)))?
-        };
+    /// Generate unified diff for a commit, comparing to its parent.
+    /// For root commits (no parent), compares to an empty tree, so every file
+    /// carries a `new file mode` header marking it as an addition.
+    fn generate_commit_diff(
+        &self,
+        commit: &Commit,
+        context_lines: u32,
+    ) -> Result<String, VcsError> {
+        self.format_diff(&self.commit_diff(commit, context_lines)?, false)
+    }
 
-        let to_tree = to_commit
+    /// Diff a commit against its parent (or the empty tree) with renames
+    /// detected, as `generate_commit_diff` formats it.
+    fn commit_diff(&self, commit: &Commit, context_lines: u32) -> Result<git2::Diff<'_>, VcsError> {
+        let tree = commit
             .tree()
-            .map_err(|e| VcsError::Other(format!("failed to get to tree: {}", e)))?;
+            .map_err(|e| VcsError::Other(format!("failed to get commit tree: {}", e)))?;
+
+        // Get parent tree (or None for root commits)
+        let parent_tree: Option<Tree> = if commit.parent_count() > 0 {
+            commit.parent(0).ok().and_then(|p| p.tree().ok())
+        } else {
+            None
+        };
 
+        // Create diff with options
         let mut opts = DiffOptions::new();
         opts.show_binary(true);
-        opts.context_lines(3);
+        opts.context_lines(context_lines);
 
-        let diff = self
+        let mut diff = self
             .repo
-            .diff_tree_to_tree(Some(&base_tree), Some(&to_tree), Some(&mut opts))
-            .map_err(|e| VcsError::Other(format!("failed to create range diff: {}", e)))?;
+            .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut opts))
+            .map_err(|e| VcsError::Other(format!("failed to create diff: {}", e)))?;
+        self.find_renames(&mut diff)?;
+        Ok(diff)
+    }
 
-        // Format diff as unified patch, filtering excluded files
-        let mut output = String::new();
-        diff.print(DiffFormat::Patch, |delta, _hunk, line| {
-            if let Some(path) = delta.new_file().path().and_then(|p| p.to_str()) {
-                if should_exclude_path(path) {
-                    return true;
-                }
+    /// Stage specific files for commit.
+    /// Files should be relative paths from the repository root. A tracked
+    /// file missing from the working tree is staged as a deletion.
+    /// Returns the staged paths; fails with `VcsError::FileNotFound` naming
+    /// the first path that is neither in the working tree nor tracked, in
+    /// which case nothing is staged.
+    pub fn stage_files(&self, paths: &[&Path]) -> Result<Vec<PathBuf>, VcsError> {
+        let workdir = self
+            .repo
+            .workdir()
+            .ok_or_else(|| VcsError::Other("cannot stage in a bare repository".to_string()))?;
+        let mut index = self
+            .repo
+            .index()
+            .map_err(|e| VcsError::Other(format!("failed to get index: {}", e)))?;
+
+        // Validate everything first so a typo doesn't leave a partial stage
+        let mut deletions = HashSet::new();
+        for path in paths {
+            if workdir.join(path).symlink_metadata().is_ok() {
+                continue;
             }
-            if let Some(path) = delta.old_file().path().and_then(|p| p.to_str()) {
-                if should_exclude_path(path) {
-                    return true;
-                }
+            if index.get_path(path, 0).is_some() {
+                deletions.insert(*path);
+            } else {
+                return Err(VcsError::FileNotFound(path.display().to_string()));
             }
+        }
 
-            let prefix = match line.origin() {
-                '+' | '-' | ' ' => line.origin(),
-                _ => '\0',
+        for path in paths {
+            let result = if deletions.contains(path) {
+                index.remove_path(path)
+            } else {
+                index.add_path(path)
             };
-            if prefix != '\0' {
-                output.push(prefix);
-            }
-            if let Ok(content) = std::str::from_utf8(line.content()) {
-                output.push_str(content);
-            }
-            true
-        })
-        .map_err(|e| VcsError::Other(format!("failed to format diff: {}", e)))?;
+            result.map_err(|e| {
+                VcsError::Other(format!("failed to stage {}: {}", path.display(), e))
+            })?;
+        }
 
-        Ok(output)
+        index
+            .write()
+            .map_err(|e| VcsError::Other(format!("failed to write index: {}", e)))?;
+
+        Ok(paths.iter().map(|p| p.to_path_buf()).collect())
     }
 
-    fn get_changed_files(&self, reference: &str) -> Result<Vec<String>, VcsError> {
-        let reference = reference.trim();
+    /// Build the author or committer signature (`role` is `AUTHOR` or
+    /// `COMMITTER`). Like git, `GIT_<role>_NAME` / `GIT_<role>_EMAIL` take
+    /// precedence over `user.name` / `user.email`, so CI can pass an
+    /// identity through the environment without any git config.
+    fn identity(config: &git2::Config, role: &str) -> Result<Signature<'static>, VcsError> {
+        let from_env = |var: &str| {
+            std::env::var(format!("GIT_{}_{}", role, var))
+                .ok()
+                .filter(|v| !v.is_empty())
+        };
 
-        // Check if this is a range (contains ..)
-        if reference.contains("..") {
-            let parts: Vec<&str> = if reference.contains("...") {
-                reference.split("...").collect()
-            } else {
-                reference.split("..").collect()
-            };
+        let name = match from_env("NAME") {
+            Some(name) => name,
+            None => config.get_string("user.name").map_err(|_| {
+                VcsError::Other(
+                    "git user.name not configured. Run: git config user.name \"Your Name\""
+                        .to_string(),
+                )
+            })?,
+        };
 
-            if parts.len() == 2 {
-                Self::validate_ref_format(parts[0])?;
-                Self::validate_ref_format(parts[1])?;
+        let email = match from_env("EMAIL") {
+            Some(email) => email,
+            None => config.get_string("user.email").map_err(|_| {
+                VcsError::Other(
+                    "git user.email not configured. Run: git config user.email \"you@example.com\""
+                        .to_string(),
+                )
+            })?,
+        };
 
-                let from_obj = self
-                    .repo
-                    .revparse_single(parts[0])
-                    .map_err(|_| VcsError::InvalidRef(parts[0].to_string()))?;
-                let from_commit = from_obj
-                    .peel_to_commit()
-                    .map_err(|_| VcsError::InvalidRef(parts[0].to_string()))?;
-                let from_tree = from_commit
-                    .tree()
-                    .map_err(|e| VcsError::Other(format!("failed to get from tree: {}", e)))?;
+        Signature::now(&name, &email)
+            .map_err(|e| VcsError::Other(format!("failed to create signature: {}", e)))
+    }
 
-                let to_obj = self
-                    .repo
-                    .revparse_single(parts[1])
-                    .map_err(|_| VcsError::InvalidRef(parts[1].to_string()))?;
-                let to_commit = to_obj
-                    .peel_to_commit()
-                    .map_err(|_| VcsError::InvalidRef(parts[1].to_string()))?;
-                let to_tree = to_commit
-                    .tree()
-                    .map_err(|e| VcsError::Other(format!("failed to get to tree: {}", e)))?;
+    /// Create a commit with the given message using the currently staged files.
+    /// Returns the commit SHA on success.
+    pub fn commit(&self, message: &str) -> Result<String, VcsError> {
+        // Get user's git config for author/committer
+        let config = self
+            .repo
+            .config()
+            .map_err(|e| VcsError::Other(format!("failed to get git config: {}", e)))?;
 
-                let diff = self
-                    .repo
-                    .diff_tree_to_tree(Some(&from_tree), Some(&to_tree), None)
-                    .map_err(|e| VcsError::Other(format!("failed to create diff: {}", e)))?;
+        let author = Self::identity(&config, "AUTHOR")?;
+        let committer = Self::identity(&config, "COMMITTER")?;
 
-                return Ok(diff
-                    .deltas()
-                    .filter_map(|d| {
-                        d.new_file()
-                            .path()
-                            .and_then(|p| p.to_str().map(String::from))
-                    })
-                    .collect());
-            }
-        }
+        let mut index = self
+            .repo
+            .index()
+            .map_err(|e| VcsError::Other(format!("failed to get index: {}", e)))?;
 
-        // Single commit - compare to parent tree (or empty tree for root)
-        Self::validate_ref_format(reference)?;
-        let obj = self
+        let tree_oid = index
+            .write_tree()
+            .map_err(|e| VcsError::Other(format!("failed to write tree: {}", e)))?;
+
+        let tree = self
             .repo
-            .revparse_single(reference)
-            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
-        let commit = obj
-            .peel_to_commit()
-            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
-        let tree = commit
-            .tree()
-            .map_err(|e| VcsError::Other(format!("failed to get commit tree: {}", e)))?;
+            .find_tree(tree_oid)
+            .map_err(|e| VcsError::Other(format!("failed to find tree: {}", e)))?;
 
-        let parent_tree: Option<Tree> = if commit.parent_count() > 0 {
-            commit.parent(0).ok().and_then(|p| p.tree().ok())
-        } else {
-            None
-        };
+        let parent = self.repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+
+        let oid = self
+            .repo
+            .commit(Some("HEAD"), &author, &committer, message, &tree, &parents)
+            .map_err(|e| VcsError::Other(format!("failed to create commit: {}", e)))?;
+
+        Ok(oid.to_string())
+    }
 
+    /// Stage a single hunk of a file's unstaged changes, like picking one
+    /// hunk in `git add -p`. `hunk_index` counts from 0 in workdir diff order.
+    #[allow(dead_code)] // Not exposed on the CLI yet
+    pub fn stage_hunk(&self, path: &Path, hunk_index: usize) -> Result<(), VcsError> {
+        let mut opts = DiffOptions::new();
+        opts.pathspec(path);
+        opts.disable_pathspec_match(true);
         let diff = self
             .repo
-            .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)
-            .map_err(|e| VcsError::Other(format!("failed to create diff: {}", e)))?;
+            .diff_index_to_workdir(None, Some(&mut opts))
+            .map_err(|e| VcsError::Other(format!("failed to create unstaged diff: {}", e)))?;
+        if diff.deltas().len() == 0 {
+            return Err(VcsError::FileNotFound(path.display().to_string()));
+        }
 
-        Ok(diff
-            .deltas()
-            .filter_map(|d| {
-                d.new_file()
-                    .path()
-                    .and_then(|p| p.to_str().map(String::from))
+        let num_hunks = git2::Patch::from_diff(&diff, 0)
+            .map_err(|e| VcsError::Other(format!("failed to create patch: {}", e)))?
+            .map(|patch| patch.num_hunks())
+            .unwrap_or(0);
+        if hunk_index >= num_hunks {
+            return Err(VcsError::Other(format!(
+                "hunk index {} out of range ({} has {} hunks)",
+                hunk_index,
+                path.display(),
+                num_hunks
+            )));
+        }
+
+        // Apply only the chosen hunk to the index
+        let mut seen = 0usize;
+        let mut apply_opts = git2::ApplyOptions::new();
+        apply_opts.hunk_callback(|_| {
+            seen += 1;
+            seen - 1 == hunk_index
+        });
+        self.repo
+            .apply(&diff, git2::ApplyLocation::Index, Some(&mut apply_opts))
+            .map_err(|e| VcsError::Other(format!("failed to stage hunk: {}", e)))
+    }
+
+    /// Commit the staged files with trailers such as `Signed-off-by` appended
+    /// to the message in a well-formed trailer block. Returns the commit SHA.
+    #[allow(dead_code)] // Not exposed on the CLI yet
+    pub fn commit_with_trailers(
+        &self,
+        message: &str,
+        trailers: &[(String, String)],
+    ) -> Result<String, VcsError> {
+        self.commit(&append_trailers(message, trailers))
+    }
+
+    /// The character starting comment lines in commit messages, from
+    /// `core.commentChar` (`#` when unset or `auto`).
+    fn comment_char(&self) -> u8 {
+        self.repo
+            .config()
+            .ok()
+            .and_then(|config| config.get_string("core.commentChar").ok())
+            .and_then(|value| match value.as_bytes() {
+                [c] => Some(*c),
+                _ => None, // "auto" or unset: use the default
+            })
+            .unwrap_or(b'#')
+    }
+
+    /// Commit the staged files with a message read from a file, like
+    /// `git commit -F`. Comment lines (per `core.commentChar`, default `#`)
+    /// and surrounding blank lines are stripped; an empty result is an error.
+    #[allow(dead_code)] // Not exposed on the CLI yet
+    pub fn commit_from_file(&self, path: &Path) -> Result<String, VcsError> {
+        let raw = std::fs::read_to_string(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => VcsError::FileNotFound(path.display().to_string()),
+            _ => VcsError::Io(e),
+        })?;
+
+        let message = git2::message_prettify(raw, Some(self.comment_char()))
+            .map_err(|e| VcsError::Other(format!("failed to clean up message: {}", e)))?;
+        if message.trim().is_empty() {
+            return Err(VcsError::Other(
+                "aborting commit due to empty commit message".to_string(),
+            ));
+        }
+
+        self.commit(&message)
+    }
+}
+
+/// Builder-style configuration.
+#[allow(dead_code)] // Not all options are exposed on the CLI yet
+impl GitBackend {
+    /// Enable or disable `.mailmap` resolution of author/committer identities.
+    /// Enabled by default.
+    pub fn with_mailmap(mut self, use_mailmap: bool) -> Self {
+        self.use_mailmap = use_mailmap;
+        self
+    }
+
+    /// Emit only changed lines, replacing `@@ -a,b +c,d @@` hunk headers
+    /// with a plain `@@ hunk @@` separator. File headers are kept.
+    pub fn with_minimal_headers(mut self, minimal_headers: bool) -> Self {
+        self.minimal_headers = minimal_headers;
+        self
+    }
+
+    /// Follow only the first parent of merges in `get_commits_in_range`,
+    /// like `git log --first-parent`. Disabled by default.
+    pub fn with_first_parent(mut self, first_parent: bool) -> Self {
+        self.first_parent = first_parent;
+        self
+    }
+
+    /// Limit formatted diffs to the first `max_files` files (by path).
+    /// The rest are summarized as `... and N more files`. Unlimited by default.
+    pub fn with_max_files(mut self, max_files: Option<usize>) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    /// Choose between committer time (the default, as `commit.time()`) and
+    /// author time for the fzf log. Committer time suits rebase-heavy
+    /// workflows, where author time reflects the original work.
+    pub fn with_committer_time(mut self, use_committer_time: bool) -> Self {
+        self.use_committer_time = use_committer_time;
+        self
+    }
+
+    /// Cut any single file's diff after `max_lines` lines, marking the cut
+    /// with `... [file truncated] ...`. Other files are unaffected.
+    pub fn with_per_file_max_lines(mut self, max_lines: Option<usize>) -> Self {
+        self.per_file_max_lines = max_lines;
+        self
+    }
+
+    /// Set how precisely the fzf log shows commit times.
+    pub fn with_time_granularity(mut self, time_granularity: TimeGranularity) -> Self {
+        self.time_granularity = time_granularity;
+        self
+    }
+
+    /// Prefix each content line with its old and new line numbers, e.g.
+    /// `  41   42  context`. Deletions show only the old number and additions
+    /// only the new one. The output is no longer a valid patch.
+    pub fn with_line_numbers(mut self, line_numbers: bool) -> Self {
+        self.line_numbers = line_numbers;
+        self
+    }
+
+    /// Summarize changed lines longer than `threshold` chars as
+    /// `<long line: N chars>`, or keep every line with None.
+    /// Defaults to `DEFAULT_LONG_LINE_THRESHOLD`.
+    pub fn with_long_line_threshold(mut self, threshold: Option<usize>) -> Self {
+        self.long_line_threshold = threshold;
+        self
+    }
+
+    /// Omit hunks that only add or remove blank lines. Files left without
+    /// hunks are omitted entirely.
+    pub fn with_drop_blank_only_hunks(mut self, drop_blank_only_hunks: bool) -> Self {
+        self.drop_blank_only_hunks = drop_blank_only_hunks;
+        self
+    }
+
+    /// Show added text files with fewer than `max_lines` lines as
+    /// `New file <path>:` followed by their content instead of `+` lines,
+    /// after the usual file header.
+    /// Edits and larger additions are diffed as usual. Off with None.
+    pub fn with_inline_new_files(mut self, max_lines: Option<usize>) -> Self {
+        self.inline_new_files_under = max_lines;
+        self
+    }
+
+    /// Keep only the `max` hunks with the most changed lines in each file,
+    /// noting how many smaller hunks were omitted in a
+    /// `... [N smaller hunks omitted] ...` line at the end of the file
+    /// header, where `patch::parse_patch` keeps it out of the hunks. Off
+    /// with None.
+    pub fn with_max_hunks_per_file(mut self, max: Option<usize>) -> Self {
+        self.max_hunks_per_file = max;
+        self
+    }
+
+    /// Fold runs of more than `max` unchanged context lines inside a hunk
+    /// into `... N unchanged lines ...`, keeping the `max` lines nearest the
+    /// surrounding changes. Unlike lowering `context_lines`, context right
+    /// next to a change is never lost. `patch::parse_patch` keeps the marker
+    /// as a hunk line (see `patch::fold_marker_len`). Off with None.
+    pub fn with_fold_context_over(mut self, max: Option<usize>) -> Self {
+        self.fold_context_over = max;
+        self
+    }
+
+    /// Exclude paths matching any of `globs` from diffs, in addition to the
+    /// built-in excludes and `.lumen.toml`. Globs without a `/` match file
+    /// names at any depth (`*.pb.go`); others match the repo-relative path.
+    /// Errors with the offending pattern if a glob is invalid.
+    pub fn with_exclude_globs(mut self, globs: Vec<String>) -> Result<Self, VcsError> {
+        self.paths.set_globs(&globs)?;
+        Ok(self)
+    }
+
+    /// Stop applying the built-in excludes (lock files, `node_modules/`), so
+    /// only `.lumen.toml`, `.lumenignore` and `with_exclude_globs` apply.
+    pub fn clear_default_excludes(mut self) -> Self {
+        self.paths.clear_defaults();
+        self
+    }
+
+    /// Abort diff formatting with `VcsError::DiffTooLarge` once the output
+    /// passes any of `limits`, guarding against runaway memory on huge
+    /// commits. No limits with None.
+    pub fn with_diff_limits(mut self, limits: Option<DiffLimits>) -> Self {
+        self.diff_limits = limits;
+        self
+    }
+
+    /// Detect renames and copies in commit, range and working tree diffs
+    /// using the given settings, or skip detection with None. On with
+    /// `RenameOptions::default()` unless changed.
+    pub fn with_rename_detection(mut self, renames: Option<RenameOptions>) -> Self {
+        self.rename_options = renames;
+        self
+    }
+}
+
+/// Git-specific queries beyond the `VcsBackend` trait.
+#[allow(dead_code)] // Not all methods used by all commands yet
+impl GitBackend {
+    /// Get a single hunk of a file's diff in a commit, by zero-based index.
+    /// Returns the hunk header followed by its lines, without file headers.
+    pub fn get_file_hunk(
+        &self,
+        reference: &str,
+        path: &Path,
+        hunk_index: usize,
+    ) -> Result<String, VcsError> {
+        let commit = self.resolve_commit(reference)?;
+
+        let mut opts = DiffOptions::new();
+        opts.context_lines(self.context_lines());
+        opts.pathspec(path);
+        opts.disable_pathspec_match(true);
+        let diff = self.diff_commit_to_parent(&commit, Some(&mut opts))?;
+
+        if diff.deltas().len() == 0 {
+            return Err(VcsError::FileNotFound(path.display().to_string()));
+        }
+
+        let patch = git2::Patch::from_diff(&diff, 0)
+            .map_err(|e| VcsError::Other(format!("failed to create patch: {}", e)))?
+            .ok_or_else(|| VcsError::Other(format!("{} has no textual diff", path.display())))?;
+
+        let num_hunks = patch.num_hunks();
+        if hunk_index >= num_hunks {
+            return Err(VcsError::Other(format!(
+                "hunk index {} out of range ({} has {} hunks)",
+                hunk_index,
+                path.display(),
+                num_hunks
+            )));
+        }
+
+        let (hunk, line_count) = patch
+            .hunk(hunk_index)
+            .map_err(|e| VcsError::Other(format!("failed to get hunk: {}", e)))?;
+
+        let mut output = String::from_utf8_lossy(hunk.header()).into_owned();
+        for line_idx in 0..line_count {
+            let line = patch
+                .line_in_hunk(hunk_index, line_idx)
+                .map_err(|e| VcsError::Other(format!("failed to get hunk line: {}", e)))?;
+            if matches!(line.origin(), '+' | '-' | ' ') {
+                output.push(line.origin());
+            }
+            output.push_str(&String::from_utf8_lossy(line.content()));
+        }
+
+        Ok(output)
+    }
+
+    /// Get the branches, remote branches, and tags pointing at a commit
+    /// (the decorations `git log` shows), sorted by name.
+    pub fn get_commit_refs(&self, reference: &str) -> Result<Vec<String>, VcsError> {
+        let target = self.resolve_commit(reference)?.id();
+
+        let references = self
+            .repo
+            .references()
+            .map_err(|e| VcsError::Other(format!("failed to list references: {}", e)))?;
+
+        let mut names = Vec::new();
+        for reference in references.flatten() {
+            // Peel annotated tags through to the commit they point at
+            let points_at_target = reference
+                .peel_to_commit()
+                .map(|c| c.id() == target)
+                .unwrap_or(false);
+            if !points_at_target {
+                continue;
+            }
+            if let Some(name) = reference.shorthand() {
+                names.push(name.to_string());
+            }
+        }
+
+        names.sort();
+        Ok(names)
+    }
+
+    /// Diff the working tree (including staged changes) against a
+    /// remote-tracking branch, e.g. `origin/main` after a fetch.
+    pub fn get_workdir_diff_against_remote(
+        &self,
+        remote: &str,
+        branch: &str,
+    ) -> Result<String, VcsError> {
+        Self::validate_ref_format(remote)?;
+        Self::validate_ref_format(branch)?;
+
+        let refname = format!("refs/remotes/{}/{}", remote, branch);
+        let tree = self
+            .repo
+            .find_reference(&refname)
+            .and_then(|r| r.peel_to_tree())
+            .map_err(|_| {
+                VcsError::InvalidRef(format!(
+                    "{}/{} not found (run `git fetch {}` first?)",
+                    remote, branch, remote
+                ))
+            })?;
+
+        let mut opts = DiffOptions::new();
+        opts.show_binary(true);
+        opts.context_lines(self.context_lines());
+
+        let diff = self
+            .repo
+            .diff_tree_to_workdir_with_index(Some(&tree), Some(&mut opts))
+            .map_err(|e| VcsError::Other(format!("failed to create workdir diff: {}", e)))?;
+
+        self.format_diff(&diff, false)
+    }
+
+    /// List stash entries as (index, message), newest first.
+    /// Indices match `stash@{N}` and can be passed to `get_stash_diff`.
+    pub fn list_stashes(&self) -> Result<Vec<(usize, String)>, VcsError> {
+        // The stash is the reflog of refs/stash; reading it directly gives
+        // the same entries as stash_foreach without needing a mutable repo
+        let reflog = match self.repo.reflog("refs/stash") {
+            Ok(reflog) => reflog,
+            Err(_) => return Ok(Vec::new()),
+        };
+        Ok(reflog
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| (index, entry.message().unwrap_or_default().to_string()))
+            .collect())
+    }
+
+    /// Get the diff for a stash entry against the commit it was taken on.
+    pub fn get_stash_diff(&self, index: usize) -> Result<String, VcsError> {
+        let reference = format!("stash@{{{}}}", index);
+        let commit = self
+            .repo
+            .revparse_single(&reference)
+            .and_then(|obj| obj.peel_to_commit())
+            .map_err(|_| VcsError::InvalidRef(reference))?;
+
+        let mut opts = DiffOptions::new();
+        opts.show_binary(true);
+        opts.context_lines(self.context_lines());
+
+        let diff = self.diff_commit_to_parent(&commit, Some(&mut opts))?;
+        self.format_diff(&diff, false)
+    }
+
+    /// Preview the diff that reverting a commit onto HEAD would produce.
+    /// The revert happens in memory; the working tree and index are untouched.
+    /// Returns `VcsError::Conflict` with the conflicted paths if the revert
+    /// doesn't apply cleanly.
+    pub fn preview_revert(&self, reference: &str) -> Result<String, VcsError> {
+        let commit = self.resolve_commit(reference)?;
+        let head = self.resolve_commit("HEAD")?;
+
+        let index = self
+            .repo
+            .revert_commit(&commit, &head, 0, None)
+            .map_err(|e| VcsError::Other(format!("failed to revert {}: {}", reference, e)))?;
+
+        if index.has_conflicts() {
+            let mut paths: Vec<String> = index
+                .conflicts()
+                .map_err(|e| VcsError::Other(format!("failed to read conflicts: {}", e)))?
+                .filter_map(|conflict| conflict.ok())
+                .filter_map(|conflict| conflict.our.or(conflict.their).or(conflict.ancestor))
+                .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+                .collect();
+            paths.dedup();
+            return Err(VcsError::Conflict(paths));
+        }
+
+        let head_tree = head
+            .tree()
+            .map_err(|e| VcsError::Other(format!("failed to get HEAD tree: {}", e)))?;
+
+        let mut opts = DiffOptions::new();
+        opts.show_binary(true);
+        opts.context_lines(self.context_lines());
+
+        let diff = self
+            .repo
+            .diff_tree_to_index(Some(&head_tree), Some(&index), Some(&mut opts))
+            .map_err(|e| VcsError::Other(format!("failed to create revert diff: {}", e)))?;
+
+        self.format_diff(&diff, false)
+    }
+
+    /// Hex SHA-256 of a commit's formatted diff, for use as a cache key.
+    /// Hashes the same filtered output `get_commit` returns, so the key
+    /// matches what is sent to the model.
+    pub fn diff_fingerprint(&self, reference: &str) -> Result<String, VcsError> {
+        use sha2::{Digest, Sha256};
+
+        let commit = self.resolve_commit(reference)?;
+        let diff = self.generate_commit_diff(&commit, self.context_lines())?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(diff.as_bytes());
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Get the files changed by a commit with their status, detecting renames
+    /// with the backend's rename settings. Deletions report only the old path
+    /// and additions only the new path.
+    pub fn get_changed_files_with_status(
+        &self,
+        reference: &str,
+    ) -> Result<Vec<ChangedFile>, VcsError> {
+        let commit = self.resolve_commit(reference)?;
+        let mut diff = self.diff_commit_to_parent(&commit, None)?;
+        self.find_renames(&mut diff)?;
+
+        Ok(Self::changed_files_from_diff(&diff, |_| true))
+    }
+
+    /// Build the status list for the deltas of a diff that pass `keep`.
+    fn changed_files_from_diff(
+        diff: &git2::Diff,
+        keep: impl Fn(&git2::DiffDelta) -> bool,
+    ) -> Vec<ChangedFile> {
+        let path_of = |file: git2::DiffFile| file.path().and_then(|p| p.to_str()).map(String::from);
+        diff.deltas()
+            .filter(|delta| keep(delta))
+            .map(|delta| {
+                let status = delta.status();
+                let old_path = match status {
+                    git2::Delta::Added | git2::Delta::Untracked => None,
+                    _ => path_of(delta.old_file()),
+                };
+                let new_path = match status {
+                    git2::Delta::Deleted => None,
+                    _ => path_of(delta.new_file()),
+                };
+                ChangedFile {
+                    status,
+                    old_path,
+                    new_path,
+                }
+            })
+            .collect()
+    }
+
+    /// Get the SHA HEAD points at, without peeling through objects.
+    /// Returns None when HEAD is unborn (no commits yet).
+    pub fn head_sha(&self) -> Result<Option<String>, VcsError> {
+        match self.repo.head() {
+            Ok(head) => Ok(head.target().map(|oid| oid.to_string())),
+            Err(e) if e.code() == git2::ErrorCode::UnbornBranch => Ok(None),
+            Err(e) => Err(VcsError::Other(format!("failed to read HEAD: {}", e))),
+        }
+    }
+
+    /// Hex SHA-256 identifying a commit's change independently of where it
+    /// applies, like `git patch-id`: only file paths and added/removed lines
+    /// are hashed, so commit metadata, hunk positions and context don't
+    /// matter. A cherry-pick shares its original's digest.
+    pub fn get_commit_change_digest(&self, reference: &str) -> Result<String, VcsError> {
+        use sha2::{Digest, Sha256};
+
+        let commit = self.resolve_commit(reference)?;
+        let diff = self.diff_commit_to_parent(&commit, None)?;
+
+        let mut hasher = Sha256::new();
+        diff.print(DiffFormat::Patch, |delta, _hunk, line| {
+            match line.origin() {
+                'F' => {
+                    // Both paths, NUL-separated, so renames hash distinctly
+                    for file in [delta.old_file(), delta.new_file()] {
+                        if let Some(path) = file.path() {
+                            hasher.update(path.to_string_lossy().as_bytes());
+                        }
+                        hasher.update([0]);
+                    }
+                }
+                origin @ ('+' | '-') => {
+                    hasher.update([origin as u8]);
+                    hasher.update(line.content());
+                }
+                _ => {}
+            }
+            true
+        })
+        .map_err(|e| VcsError::Other(format!("failed to hash diff: {}", e)))?;
+
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Get everything the next `git add -A && git commit` would record:
+    /// staged, unstaged, and untracked changes relative to HEAD in one diff.
+    pub fn get_pending_diff(&self) -> Result<String, VcsError> {
+        let diff = self.pending_diff()?;
+        self.format_diff(&diff, true)
+    }
+
+    /// Get the pending changes of `get_pending_diff` split per file, as
+    /// (path, that file's diff) in path order. Excluded files and files past
+    /// the `max_files` limit are skipped.
+    pub fn get_pending_diffs_by_file(&self) -> Result<Vec<(String, String)>, VcsError> {
+        let diff = self.pending_diff()?;
+        let RenderedDiff {
+            text, file_starts, ..
+        } = self.render_diff(&diff, true, &DiffFilter::default())?;
+
+        // Each file runs from its start to the next file's
+        let ends = file_starts
+            .iter()
+            .skip(1)
+            .map(|(_, start)| *start)
+            .chain([text.len()]);
+        Ok(file_starts
+            .iter()
+            .zip(ends)
+            .filter_map(|((path, start), end)| {
+                let path = path.as_ref()?.to_string_lossy().into_owned();
+                Some((path, text[*start..end].to_string()))
+            })
+            .collect())
+    }
+
+    /// Diff HEAD against the working tree and index, untracked files
+    /// included.
+    fn pending_diff(&self) -> Result<git2::Diff<'_>, VcsError> {
+        let mut opts = DiffOptions::new();
+        opts.show_binary(true)
+            .context_lines(self.context_lines())
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+
+        let head = self.repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+        self.repo
+            .diff_tree_to_workdir_with_index(head.as_ref(), Some(&mut opts))
+            .map_err(|e| VcsError::Other(format!("failed to create pending diff: {}", e)))
+    }
+
+    /// List remotes as (name, URL). The URL is None if unset or not UTF-8.
+    pub fn get_remotes(&self) -> Result<Vec<(String, Option<String>)>, VcsError> {
+        let names = self
+            .repo
+            .remotes()
+            .map_err(|e| VcsError::Other(format!("failed to list remotes: {}", e)))?;
+
+        let mut remotes = Vec::new();
+        for name in names.iter().flatten() {
+            let remote = self
+                .repo
+                .find_remote(name)
+                .map_err(|e| VcsError::Other(format!("failed to find remote {}: {}", name, e)))?;
+            remotes.push((name.to_string(), remote.url().map(String::from)));
+        }
+        Ok(remotes)
+    }
+
+    /// Get a commit's diff reduced to the hunks with an added or removed
+    /// line matching `pattern`. File headers are kept for surviving hunks.
+    pub fn get_commit_diff_grep(&self, reference: &str, pattern: &str) -> Result<String, VcsError> {
+        let regex = regex::Regex::new(pattern)
+            .map_err(|e| VcsError::Other(format!("invalid pattern {:?}: {}", pattern, e)))?;
+        let commit = self.resolve_commit(reference)?;
+        let filter = DiffFilter {
+            grep: Some(&regex),
+            ..DiffFilter::default()
+        };
+        self.format_diff_filtered(
+            &self.commit_diff(&commit, self.context_lines())?,
+            false,
+            &filter,
+        )
+    }
+
+    /// Get the working tree diff limited to specific files, e.g. the files
+    /// about to be committed. `staged` works as in `get_working_tree_diff`.
+    pub fn get_working_tree_diff_for(
+        &self,
+        paths: &[&Path],
+        staged: bool,
+    ) -> Result<String, VcsError> {
+        if paths.is_empty() {
+            return Ok(String::new());
+        }
+        self.working_tree_diff_for_paths(staged, paths, self.context_lines())
+    }
+
+    /// Get the ancestor/ours/theirs contents of a conflicted path from the
+    /// index's conflict entries.
+    pub fn get_conflict_details(&self, path: &Path) -> Result<ConflictDetails, VcsError> {
+        let mut index = self
+            .repo
+            .index()
+            .map_err(|e| VcsError::Other(format!("failed to read index: {}", e)))?;
+        index
+            .read(false)
+            .map_err(|e| VcsError::Other(format!("failed to read index: {}", e)))?;
+
+        let wanted = path.to_string_lossy();
+        let entry_matches = |entry: &Option<git2::IndexEntry>| {
+            entry.as_ref().is_some_and(|e| e.path == wanted.as_bytes())
+        };
+        let conflict = index
+            .conflicts()
+            .map_err(|e| VcsError::Other(format!("failed to read conflicts: {}", e)))?
+            .filter_map(|conflict| conflict.ok())
+            .find(|c| {
+                entry_matches(&c.ancestor) || entry_matches(&c.our) || entry_matches(&c.their)
+            })
+            .ok_or_else(|| VcsError::Other(format!("{} is not conflicted", wanted)))?;
+
+        let content = |entry: Option<git2::IndexEntry>| -> Result<Option<String>, VcsError> {
+            let Some(entry) = entry else {
+                return Ok(None);
+            };
+            let blob = self
+                .repo
+                .find_blob(entry.id)
+                .map_err(|e| VcsError::Other(format!("failed to read conflict blob: {}", e)))?;
+            Ok(Some(String::from_utf8_lossy(blob.content()).into_owned()))
+        };
+
+        Ok(ConflictDetails {
+            ancestor: content(conflict.ancestor)?,
+            ours: content(conflict.our)?,
+            theirs: content(conflict.their)?,
+        })
+    }
+
+    /// Classify a ref as a branch, remote branch, tag, or raw commit.
+    pub fn ref_kind(&self, reference: &str) -> Result<RefKind, VcsError> {
+        let reference = reference.trim();
+        Self::validate_ref_format(reference)?;
+
+        if let Ok(found) = self.repo.resolve_reference_from_short_name(reference) {
+            return Ok(if found.is_branch() {
+                RefKind::Branch
+            } else if found.is_remote() {
+                RefKind::RemoteBranch
+            } else if found.is_tag() {
+                RefKind::Tag
+            } else {
+                RefKind::Other
+            });
+        }
+
+        let object = self
+            .repo
+            .revparse_single(reference)
+            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+        Ok(match object.kind() {
+            Some(git2::ObjectType::Commit) => RefKind::Commit,
+            _ => RefKind::Other,
+        })
+    }
+
+    /// Get the diffs of each commit in `from..to`, oldest first, concatenated.
+    /// With `commit_headers`, each commit's section starts with a
+    /// `# <short_id>: <summary>` line so the commits stay distinguishable.
+    pub fn get_range_per_commit_diff(
+        &self,
+        from: &str,
+        to: &str,
+        commit_headers: bool,
+    ) -> Result<String, VcsError> {
+        let mut output = String::new();
+        for info in self.get_commits_in_range(from, to)? {
+            let commit = self.resolve_commit(&info.commit_id)?;
+            if commit_headers {
+                output.push_str(&format!("# {}: {}\n", info.short_id, info.summary));
+            }
+            output.push_str(&self.generate_commit_diff(&commit, self.context_lines())?);
+        }
+        Ok(output)
+    }
+
+    /// List the top-level entries of any tree-ish ref (a commit, tag, or a
+    /// tree itself such as `HEAD^{tree}`).
+    pub fn get_tree(&self, reference: &str) -> Result<Vec<TreeEntryInfo>, VcsError> {
+        let reference = reference.trim();
+        Self::validate_ref_format(reference)?;
+        let tree = self
+            .repo
+            .revparse_single(reference)
+            .and_then(|obj| obj.peel_to_tree())
+            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+
+        Ok(tree
+            .iter()
+            .map(|entry| TreeEntryInfo {
+                name: String::from_utf8_lossy(entry.name_bytes()).into_owned(),
+                kind: entry.kind(),
+                id: entry.id().to_string(),
+            })
+            .collect())
+    }
+
+    /// Get a commit's filtered diff, base64-encoded for embedding in JSON
+    /// payloads or URLs.
+    pub fn get_commit_diff_base64(&self, reference: &str) -> Result<String, VcsError> {
+        use base64::Engine;
+
+        let commit = self.resolve_commit(reference)?;
+        let diff = self.generate_commit_diff(&commit, self.context_lines())?;
+        Ok(base64::engine::general_purpose::STANDARD.encode(diff))
+    }
+
+    /// Compare a stash entry against the current index: the diff from the
+    /// index to the stash, and whether the stash would apply cleanly.
+    pub fn diff_stash_against_index(&self, index: usize) -> Result<StashComparison, VcsError> {
+        let reference = format!("stash@{{{}}}", index);
+        let stash = self
+            .repo
+            .revparse_single(&reference)
+            .and_then(|obj| obj.peel_to_commit())
+            .map_err(|_| VcsError::InvalidRef(reference.clone()))?;
+        let stash_tree = stash
+            .tree()
+            .map_err(|e| VcsError::Other(format!("failed to get stash tree: {}", e)))?;
+
+        let mut repo_index = self
+            .repo
+            .index()
+            .map_err(|e| VcsError::Other(format!("failed to read index: {}", e)))?;
+        repo_index
+            .read(true)
+            .map_err(|e| VcsError::Other(format!("failed to read index: {}", e)))?;
+
+        let mut opts = DiffOptions::new();
+        opts.show_binary(true)
+            .context_lines(self.context_lines())
+            .reverse(true); // index -> stash rather than stash -> index
+        let diff = self
+            .repo
+            .diff_tree_to_index(Some(&stash_tree), Some(&repo_index), Some(&mut opts))
+            .map_err(|e| VcsError::Other(format!("failed to create stash diff: {}", e)))?;
+        let diff = self.format_diff(&diff, false)?;
+
+        // Three-way merge with the commit the stash was taken on as the base
+        let base_tree = Self::first_parent_tree(&stash)
+            .ok_or_else(|| VcsError::Other(format!("{} has no base commit", reference)))?;
+        let index_tree_id = repo_index
+            .write_tree()
+            .map_err(|e| VcsError::Other(format!("failed to write index tree: {}", e)))?;
+        let index_tree = self
+            .repo
+            .find_tree(index_tree_id)
+            .map_err(|e| VcsError::Other(format!("failed to find index tree: {}", e)))?;
+        let merged = self
+            .repo
+            .merge_trees(&base_tree, &index_tree, &stash_tree, None)
+            .map_err(|e| VcsError::Other(format!("failed to merge stash: {}", e)))?;
+
+        Ok(StashComparison {
+            diff,
+            conflicts: merged.has_conflicts(),
+        })
+    }
+
+    /// Attribute the lines `start..=end` (1-based) of a file at HEAD to the
+    /// commits that last modified them. Returns half-open line ranges, in file
+    /// order, each with the full SHA of the responsible commit.
+    pub fn blame_range(
+        &self,
+        path: &Path,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<(std::ops::Range<usize>, String)>, VcsError> {
+        if start == 0 || end < start {
+            return Err(VcsError::Other(format!(
+                "invalid line range {}..{}",
+                start, end
+            )));
+        }
+
+        let mut opts = git2::BlameOptions::new();
+        opts.min_line(start).max_line(end);
+        let blame = self
+            .repo
+            .blame_file(path, Some(&mut opts))
+            .map_err(|_| VcsError::FileNotFound(path.display().to_string()))?;
+
+        let ranges = blame
+            .iter()
+            .filter_map(|hunk| {
+                // Clip hunks to the queried lines
+                let hunk_start = hunk.final_start_line().max(start);
+                let hunk_end = (hunk.final_start_line() + hunk.lines_in_hunk()).min(end + 1);
+                (hunk_start < hunk_end)
+                    .then(|| (hunk_start..hunk_end, hunk.final_commit_id().to_string()))
+            })
+            .collect();
+        Ok(ranges)
+    }
+
+    /// Summarize `head` as a pull request against `base`: merge base, commit
+    /// count, line totals and changed files since the merge base.
+    pub fn get_pr_summary(&self, base: &str, head: &str) -> Result<PrSummary, VcsError> {
+        let merge_base = self.get_merge_base(base, head)?;
+        let commits = self.get_commits_in_range(&merge_base, head)?.len();
+
+        let base_tree = self
+            .resolve_commit(&merge_base)?
+            .tree()
+            .map_err(|e| VcsError::Other(format!("failed to get merge base tree: {}", e)))?;
+        let head_tree = self
+            .resolve_commit(head)?
+            .tree()
+            .map_err(|e| VcsError::Other(format!("failed to get head tree: {}", e)))?;
+        let mut diff = self
+            .repo
+            .diff_tree_to_tree(Some(&base_tree), Some(&head_tree), None)
+            .map_err(|e| VcsError::Other(format!("failed to create diff: {}", e)))?;
+        self.find_renames(&mut diff)?;
+
+        let (insertions, deletions) = self.line_totals(&diff)?;
+
+        Ok(PrSummary {
+            merge_base,
+            commits,
+            insertions,
+            deletions,
+            files: Self::changed_files_from_diff(&diff, |delta| !self.is_excluded_delta(delta)),
+        })
+    }
+
+    /// Get the share of a commit's changed lines that are deletions:
+    /// deletions / (insertions + deletions), from 0.0 (only additions) to
+    /// 1.0 (only deletions). Counted like `get_diff_stats`, so excluded
+    /// files don't count and a pure rename changes nothing; a commit
+    /// without counted changes gives 0.0.
+    pub fn get_commit_change_ratio(&self, reference: &str) -> Result<f64, VcsError> {
+        let commit = self.resolve_commit(reference)?;
+        let DiffStats {
+            insertions,
+            deletions,
+            ..
+        } = self.commit_stats(&commit)?;
+        if insertions + deletions == 0 {
+            return Ok(0.0);
+        }
+        Ok(deletions as f64 / (insertions + deletions) as f64)
+    }
+
+    /// Check whether a commit is empty, i.e. its tree matches its first parent's.
+    pub fn is_empty_commit(&self, reference: &str) -> Result<bool, VcsError> {
+        Ok(Self::commit_is_empty(&self.resolve_commit(reference)?))
+    }
+
+    /// Walk `from..to` like `walk_commits_in_range`, also handing `f` each
+    /// commit's git2 object. Stops with an error if a commit can't be loaded.
+    fn walk_git_commits_in_range(
+        &self,
+        from: &str,
+        to: &str,
+        mut f: impl FnMut(StackedCommitInfo, &Commit),
+    ) -> Result<(), VcsError> {
+        let mut lookup_error = None;
+        self.walk_commits_in_range(
+            from,
+            to,
+            &mut |info| match git2::Oid::from_str(&info.commit_id)
+                .and_then(|oid| self.repo.find_commit(oid))
+            {
+                Ok(commit) => {
+                    f(info, &commit);
+                    true
+                }
+                Err(e) => {
+                    lookup_error = Some(e);
+                    false
+                }
+            },
+        )?;
+        match lookup_error {
+            Some(e) => Err(VcsError::Other(format!("failed to find commit: {}", e))),
+            None => Ok(()),
+        }
+    }
+
+    /// Group the commits in `from..to` by conventional-commit type.
+    pub fn get_changelog(&self, from: &str, to: &str) -> Result<Changelog, VcsError> {
+        let mut changelog = Changelog::default();
+        self.walk_git_commits_in_range(from, to, |info, commit| {
+            changelog.push(info.commit_id, commit.message().unwrap_or(""));
+        })?;
+        Ok(changelog)
+    }
+
+    /// Get the commits on the current branch that its upstream doesn't have,
+    /// i.e. `@{u}..HEAD`, oldest first.
+    pub fn get_commits_since_upstream(&self) -> Result<Vec<StackedCommitInfo>, VcsError> {
+        let head = self
+            .repo
+            .head()
+            .map_err(|e| VcsError::Other(format!("failed to read HEAD: {}", e)))?;
+        if !head.is_branch() {
+            return Err(VcsError::Other(
+                "HEAD is detached, so there is no upstream".to_string(),
+            ));
+        }
+        let branch_name = head.shorthand().unwrap_or("HEAD").to_string();
+        let upstream = git2::Branch::wrap(head).upstream().map_err(|_| {
+            VcsError::Other(format!("branch {} has no upstream configured", branch_name))
+        })?;
+        let upstream_oid = upstream
+            .get()
+            .target()
+            .ok_or_else(|| VcsError::Other(format!("upstream of {} has no target", branch_name)))?;
+        let head_sha = self
+            .head_sha()?
+            .ok_or_else(|| VcsError::Other("HEAD has no commits".to_string()))?;
+
+        self.get_commits_in_range(&upstream_oid.to_string(), &head_sha)
+    }
+
+    /// Guess the branch a pull request from the current branch would target.
+    /// In order: `default_base` from `.lumen.toml`, the current branch's
+    /// upstream, the branch `origin/HEAD` points to, then a local `main` or
+    /// `master`. Returns None when none of them exist.
+    pub fn infer_base_branch(&self) -> Result<Option<String>, VcsError> {
+        if let Some(base) = self.default_base() {
+            return Ok(Some(base.to_string()));
+        }
+
+        if let Ok(head) = self.repo.head() {
+            if head.is_branch() {
+                if let Ok(upstream) = git2::Branch::wrap(head).upstream() {
+                    if let Some(name) = upstream.get().shorthand() {
+                        return Ok(Some(name.to_string()));
+                    }
+                }
+            }
+        }
+
+        if let Ok(origin_head) = self.repo.find_reference("refs/remotes/origin/HEAD") {
+            if let Some(target) = origin_head.symbolic_target() {
+                if let Some(name) = target.strip_prefix("refs/remotes/") {
+                    if self.repo.find_reference(target).is_ok() {
+                        return Ok(Some(name.to_string()));
+                    }
+                }
+            }
+        }
+
+        for name in ["main", "master"] {
+            if self.repo.find_branch(name, git2::BranchType::Local).is_ok() {
+                return Ok(Some(name.to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Check whether the working tree, index included, matches a commit
+    /// exactly. Untracked files count as changes; ignored files don't.
+    pub fn workdir_matches(&self, reference: &str) -> Result<bool, VcsError> {
+        let tree = self
+            .resolve_commit(reference)?
+            .tree()
+            .map_err(|e| VcsError::Other(format!("failed to get commit tree: {}", e)))?;
+
+        let mut opts = DiffOptions::new();
+        opts.include_untracked(true).recurse_untracked_dirs(true);
+        let diff = self
+            .repo
+            .diff_tree_to_workdir_with_index(Some(&tree), Some(&mut opts))
+            .map_err(|e| VcsError::Other(format!("failed to create workdir diff: {}", e)))?;
+        Ok(diff.deltas().len() == 0)
+    }
+
+    /// Describe a commit like `git describe --tags`: the nearest tag, the
+    /// number of commits since it and the abbreviated SHA, e.g.
+    /// `v1.2.3-5-gabcdef0`. A tagged commit is just its tag. Without any
+    /// reachable tag, falls back to the abbreviated SHA.
+    pub fn describe(&self, reference: &str) -> Result<String, VcsError> {
+        let commit = self.resolve_commit(reference)?;
+
+        let mut opts = git2::DescribeOptions::new();
+        opts.describe_tags().show_commit_oid_as_fallback(true);
+        let description = commit
+            .as_object()
+            .describe(&opts)
+            .map_err(|e| VcsError::Other(format!("failed to describe {}: {}", reference, e)))?;
+
+        let mut format = git2::DescribeFormatOptions::new();
+        format.abbreviated_size(7);
+        description
+            .format(Some(&format))
+            .map_err(|e| VcsError::Other(format!("failed to format description: {}", e)))
+    }
+
+    /// Get a commit's diff limited to everything under the directory `dir`
+    /// (relative to the repo root). `a` matches `a/x.rs` but not `ab/x.rs`.
+    pub fn get_commit_diff_in_subtree(
+        &self,
+        reference: &str,
+        dir: &Path,
+    ) -> Result<String, VcsError> {
+        let commit = self.resolve_commit(reference)?;
+        let dir = dir
+            .to_str()
+            .ok_or_else(|| VcsError::Other(format!("non-UTF-8 directory: {}", dir.display())))?;
+        let dir = dir.trim_matches('/');
+
+        let mut opts = DiffOptions::new();
+        opts.show_binary(true)
+            .context_lines(self.context_lines())
+            .disable_pathspec_match(true)
+            .pathspec(format!("{}/", dir));
+        let mut diff = self.diff_commit_to_parent(&commit, Some(&mut opts))?;
+        self.find_renames(&mut diff)?;
+
+        self.format_diff(&diff, false)
+    }
+
+    /// Read the repo's pull request template from the first of the usual
+    /// locations (`.github/`, `docs/`, the root) that has one.
+    /// Returns None when there is no template or no working directory.
+    pub fn get_pr_template(&self) -> Result<Option<String>, VcsError> {
+        let Some(workdir) = self.repo.workdir() else {
+            return Ok(None);
+        };
+        for path in PR_TEMPLATE_PATHS {
+            match std::fs::read_to_string(workdir.join(path)) {
+                Ok(content) => return Ok(Some(content)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(VcsError::Io(e)),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Find leftover conflict markers (`<<<<<<<`, `=======`, `>>>>>>>`) in
+    /// the staged version of files changed in the index. Returns each
+    /// marker's path and 1-based line number. Binary files are skipped, and
+    /// `=======` outside a conflict is taken for a heading underline.
+    pub fn find_conflict_markers_in_staged(&self) -> Result<Vec<(String, usize)>, VcsError> {
+        let head = self.repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+        let diff = self
+            .repo
+            .diff_tree_to_index(head.as_ref(), None, None)
+            .map_err(|e| VcsError::Other(format!("failed to create staged diff: {}", e)))?;
+
+        let mut markers = Vec::new();
+        for delta in diff.deltas() {
+            let file = delta.new_file();
+            if !file.exists() {
+                continue;
+            }
+            let Some(path) = file.path().and_then(|p| p.to_str()) else {
+                continue;
+            };
+            let blob = self
+                .repo
+                .find_blob(file.id())
+                .map_err(|e| VcsError::Other(format!("failed to read {}: {}", path, e)))?;
+            if blob.is_binary() {
+                continue;
+            }
+            let content = String::from_utf8_lossy(blob.content());
+            markers.extend(
+                conflict_marker_lines(&content)
+                    .into_iter()
+                    .map(|line| (path.to_string(), line)),
+            );
+        }
+        Ok(markers)
+    }
+
+    /// Count the commits in `from..to` per author email, most commits first.
+    /// Emails are compared case-insensitively and resolved through `.mailmap`
+    /// when enabled. Like `get_commits_in_range`, empty commits are skipped.
+    pub fn author_commit_counts(
+        &self,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<(String, usize)>, VcsError> {
+        let mailmap = if self.use_mailmap {
+            self.repo.mailmap().ok()
+        } else {
+            None
+        };
+
+        let mut counts: std::collections::HashMap<String, usize> = Default::default();
+        self.walk_git_commits_in_range(from, to, |_, commit| {
+            let author = mailmap
+                .as_ref()
+                .and_then(|mailmap| commit.author_with_mailmap(mailmap).ok())
+                .unwrap_or_else(|| commit.author().to_owned());
+            let email = author.email().unwrap_or("").to_lowercase();
+            *counts.entry(email).or_default() += 1;
+        })?;
+
+        let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(counts)
+    }
+
+    /// Preview squashing `from..to` into one commit: the commits' messages
+    /// joined oldest first under a single deduplicated trailer block, and
+    /// the range diff.
+    pub fn preview_squash(&self, from: &str, to: &str) -> Result<(String, String), VcsError> {
+        let mut texts = Vec::new();
+        let mut trailers = Vec::new();
+        self.walk_git_commits_in_range(from, to, |_, commit| {
+            let (text, commit_trailers) = split_trailers(commit.message().unwrap_or(""));
+            texts.push(text);
+            trailers.extend(commit_trailers);
+        })?;
+
+        // Build the trailer block directly: a last subject such as
+        // `docs: explain` would otherwise parse as an existing trailer
+        let mut unique: Vec<(String, String)> = Vec::new();
+        for trailer in trailers {
+            if !unique.contains(&trailer) {
+                unique.push(trailer);
+            }
+        }
+        let mut message = texts.join("\n\n");
+        if !unique.is_empty() {
+            message.push_str("\n\n");
+            let lines: Vec<String> = unique
+                .iter()
+                .map(|(key, value)| format!("{}: {}", key, value))
+                .collect();
+            message.push_str(&lines.join("\n"));
+        }
+        message.push('\n');
+
+        Ok((message, self.get_range_diff(from, to, false)?))
+    }
+
+    /// Get the language most of a commit's changed files are written in,
+    /// judged by extension. Excluded files and files of unknown type don't
+    /// count; ties go to the alphabetically first language. None if no
+    /// changed file has a known language.
+    pub fn dominant_language(&self, reference: &str) -> Result<Option<&'static str>, VcsError> {
+        let mut counts: std::collections::BTreeMap<&'static str, usize> = Default::default();
+        for path in self.get_changed_files(reference)? {
+            if self.is_excluded_path(&path) {
+                continue;
+            }
+            if let Some(language) = super::detect_language(Path::new(&path)) {
+                *counts.entry(language).or_default() += 1;
+            }
+        }
+        // max_by_key keeps the last maximum, so walk the map in reverse
+        Ok(counts
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, count)| *count)
+            .map(|(language, _)| language))
+    }
+
+    /// Get a commit's diff limited to files in the given languages, e.g.
+    /// `["rust"]`, using the language names of `detect_language`
+    /// (case-insensitive). Files without a recognized extension never match.
+    pub fn get_commit_diff_for_languages(
+        &self,
+        reference: &str,
+        langs: &[&str],
+    ) -> Result<String, VcsError> {
+        let commit = self.resolve_commit(reference)?;
+        let matches = |path: Option<&Path>| {
+            path.and_then(super::detect_language)
+                .is_some_and(|language| langs.iter().any(|l| l.eq_ignore_ascii_case(language)))
+        };
+        // A rename matches if either side is in one of the languages
+        let keep = |delta: &git2::DiffDelta| {
+            matches(delta.new_file().path()) || matches(delta.old_file().path())
+        };
+        let filter = DiffFilter {
+            keep_path: Some(&keep),
+            ..DiffFilter::default()
+        };
+        self.format_diff_filtered(
+            &self.commit_diff(&commit, self.context_lines())?,
+            false,
+            &filter,
+        )
+    }
+
+    /// Diff a single file between HEAD and the working tree, staged changes
+    /// included. Empty if the file is unchanged; `VcsError::FileNotFound` if
+    /// it exists neither at HEAD nor in the working tree.
+    pub fn get_file_workdir_diff(&self, path: &Path) -> Result<String, VcsError> {
+        let head = self.repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+        let in_head = head
+            .as_ref()
+            .is_some_and(|tree| tree.get_path(path).is_ok());
+        let in_workdir = self
+            .repo
+            .workdir()
+            .is_some_and(|workdir| workdir.join(path).symlink_metadata().is_ok());
+        if !in_head && !in_workdir {
+            return Err(VcsError::FileNotFound(path.display().to_string()));
+        }
+
+        let mut opts = DiffOptions::new();
+        opts.show_binary(true)
+            .context_lines(self.context_lines())
+            .include_untracked(true)
+            .disable_pathspec_match(true)
+            .pathspec(path);
+        let diff = self
+            .repo
+            .diff_tree_to_workdir_with_index(head.as_ref(), Some(&mut opts))
+            .map_err(|e| VcsError::Other(format!("failed to create workdir diff: {}", e)))?;
+
+        self.format_diff(&diff, true)
+    }
+
+    /// Get the commits in `from..to` that change `path`, oldest first.
+    /// Compares the path's tree entry with the first parent's, so a file is
+    /// touched when it is added, deleted or its content or mode changes.
+    pub fn commits_touching_file(
+        &self,
+        from: &str,
+        to: &str,
+        path: &Path,
+    ) -> Result<Vec<StackedCommitInfo>, VcsError> {
+        let entry_of = |tree: Option<Tree>| {
+            tree.and_then(|tree| tree.get_path(path).ok())
+                .map(|entry| (entry.id(), entry.filemode()))
+        };
+
+        let mut commits = Vec::new();
+        self.walk_git_commits_in_range(from, to, |info, commit| {
+            if entry_of(commit.tree().ok()) != entry_of(Self::first_parent_tree(commit)) {
+                commits.push(info);
+            }
+        })?;
+        Ok(commits)
+    }
+
+    /// Check whether a commit is in HEAD's history: HEAD itself or one of its
+    /// ancestors.
+    pub fn is_reachable_from_head(&self, reference: &str) -> Result<bool, VcsError> {
+        let target = self.resolve_commit(reference)?.id();
+        let head = self.resolve_commit("HEAD")?.id();
+        if head == target {
+            return Ok(true);
+        }
+        self.repo
+            .graph_descendant_of(head, target)
+            .map_err(|e| VcsError::Other(format!("failed to walk history: {}", e)))
+    }
+
+    /// Get a merge commit's combined diff against all of its parents, in
+    /// git's dense `diff --cc` format. Only files that differ from every
+    /// parent are listed, and within them only the hunks that do; conflict
+    /// resolutions therefore show up while files taken from one side don't.
+    /// Errors with `VcsError::InvalidRef` if the commit is not a merge.
+    pub fn get_merge_cc_diff(&self, reference: &str) -> Result<String, VcsError> {
+        let commit = self.resolve_commit(reference)?;
+        if commit.parent_count() < 2 {
+            return Err(VcsError::InvalidRef(format!(
+                "{} is not a merge commit",
+                reference
+            )));
+        }
+        let tree = commit
+            .tree()
+            .map_err(|e| VcsError::Other(format!("failed to get tree: {}", e)))?;
+        let parent_trees = commit
+            .parents()
+            .map(|parent| parent.tree())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| VcsError::Other(format!("failed to get parent tree: {}", e)))?;
+
+        // Candidates are the files changed against the first parent
+        let diff = self
+            .repo
+            .diff_tree_to_tree(Some(&parent_trees[0]), Some(&tree), None)
+            .map_err(|e| VcsError::Other(format!("failed to create diff: {}", e)))?;
+        let paths: Vec<PathBuf> = diff
+            .deltas()
+            .filter_map(|delta| {
+                delta
+                    .new_file()
+                    .path()
+                    .or_else(|| delta.old_file().path())
+                    .map(Path::to_path_buf)
+            })
+            .collect();
+
+        let entry_id = |tree: &Tree, path: &Path| tree.get_path(path).ok().map(|entry| entry.id());
+        let short = |id: Option<git2::Oid>| {
+            id.map_or_else(|| "0".repeat(7), |id| id.to_string()[..7].to_string())
+        };
+        let read = |id: Option<git2::Oid>| -> Result<Option<String>, VcsError> {
+            let Some(id) = id else {
+                return Ok(Some(String::new()));
+            };
+            let blob = self
+                .repo
+                .find_blob(id)
+                .map_err(|e| VcsError::Other(format!("failed to read blob: {}", e)))?;
+            if blob.is_binary() {
+                return Ok(None);
+            }
+            Ok(Some(String::from_utf8_lossy(blob.content()).into_owned()))
+        };
+
+        let mut output = String::new();
+        for path in paths {
+            let display = path.to_string_lossy();
+            if self.is_excluded_path(&display) {
+                continue;
+            }
+            let result_id = entry_id(&tree, &path);
+            let parent_ids: Vec<_> = parent_trees
+                .iter()
+                .map(|parent| entry_id(parent, &path))
+                .collect();
+            if parent_ids.contains(&result_id) {
+                continue;
+            }
+
+            let Some(result) = read(result_id)? else {
+                continue;
+            };
+            let mut parents = Vec::with_capacity(parent_ids.len());
+            for id in &parent_ids {
+                match read(*id)? {
+                    Some(content) => parents.push(content),
+                    None => break,
+                }
+            }
+            if parents.len() < parent_ids.len() {
+                continue;
+            }
+
+            let parents: Vec<&str> = parents.iter().map(String::as_str).collect();
+            let hunks =
+                super::patch::combined_diff_hunks(&parents, &result, self.context_lines() as usize);
+            if hunks.is_empty() {
+                continue;
+            }
+            let parent_ids: Vec<String> = parent_ids.into_iter().map(short).collect();
+            output.push_str(&format!(
+                "diff --cc {path}\nindex {}..{}\n--- a/{path}\n+++ b/{path}\n",
+                parent_ids.join(","),
+                short(result_id),
+                path = display,
+            ));
+            output.push_str(&hunks);
+        }
+        Ok(output)
+    }
+
+    /// Directory hooks are run from: `core.hooksPath` when set (relative paths
+    /// resolve against the working tree root, as in git), else `hooks/` in
+    /// the common git directory.
+    pub fn hooks_dir(&self) -> PathBuf {
+        let configured = self
+            .repo
+            .config()
+            .ok()
+            .and_then(|config| config.get_path("core.hooksPath").ok());
+        match configured {
+            Some(path) if path.is_relative() => self
+                .repo
+                .workdir()
+                .unwrap_or_else(|| self.repo.path())
+                .join(path),
+            Some(path) => path,
+            None => self.repo.commondir().join("hooks"),
+        }
+    }
+
+    /// Run the hook `name` (e.g. `pre-commit`) from `hooks_dir` with `args`.
+    /// Like git, a missing or non-executable hook is skipped. Returns whether
+    /// the hook ran; a hook exiting non-zero is a `VcsError::CommandFailed`.
+    pub fn run_hook(&self, name: &str, args: &[&str]) -> Result<bool, VcsError> {
+        let hook = self.hooks_dir().join(name);
+        let Ok(metadata) = std::fs::metadata(&hook) else {
+            return Ok(false);
+        };
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if metadata.permissions().mode() & 0o111 == 0 {
+                return Ok(false);
+            }
+        }
+        if !metadata.is_file() {
+            return Ok(false);
+        }
+
+        let output = std::process::Command::new(&hook)
+            .args(args)
+            .current_dir(self.repo.workdir().unwrap_or_else(|| self.repo.path()))
+            .output()?;
+        if !output.status.success() {
+            return Err(VcsError::CommandFailed(format!(
+                "hook `{}` failed: {}",
+                name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(true)
+    }
+
+    /// Get a commit's message to seed a new draft, as `git commit -c` does:
+    /// the full message with comment lines (starting with `core.commentChar`,
+    /// `#` by default) removed.
+    pub fn get_message_for_reuse(&self, reference: &str) -> Result<String, VcsError> {
+        let commit = self.resolve_commit(reference)?;
+        let comment_char = char::from(self.comment_char());
+
+        let message = String::from_utf8_lossy(commit.message_bytes());
+        Ok(message
+            .split_inclusive('\n')
+            .filter(|line| !line.starts_with(comment_char))
+            .collect())
+    }
+
+    /// List commits, branches and tags as picker items, grouped in the order
+    /// of `kinds`. Commits come from the plain fzf log (newest first),
+    /// branches are local ones and tags are sorted by name.
+    pub fn list_pickable(&self, kinds: &[PickKind]) -> Result<Vec<PickItem>, VcsError> {
+        let item = |kind: PickKind, reference: &str, description: &str| PickItem {
+            kind,
+            reference: reference.to_string(),
+            display: format!("{} {}", kind.label(), description),
+        };
+
+        let mut items = Vec::new();
+        for &kind in kinds {
+            match kind {
+                PickKind::Commit => {
+                    for line in self.get_commit_log_plain()?.lines() {
+                        let short_id = line.split(' ').next().unwrap_or(line);
+                        items.push(item(kind, short_id, line));
+                    }
+                }
+                PickKind::Branch => {
+                    let branches = self
+                        .repo
+                        .branches(Some(git2::BranchType::Local))
+                        .map_err(|e| VcsError::Other(format!("failed to list branches: {}", e)))?;
+                    let mut names = Vec::new();
+                    for branch in branches {
+                        let (branch, _) = branch.map_err(|e| {
+                            VcsError::Other(format!("failed to read branch: {}", e))
+                        })?;
+                        if let Ok(Some(name)) = branch.name() {
+                            names.push(name.to_string());
+                        }
+                    }
+                    names.sort();
+                    items.extend(names.iter().map(|name| item(kind, name, name)));
+                }
+                PickKind::Tag => {
+                    let tags = self
+                        .repo
+                        .tag_names(None)
+                        .map_err(|e| VcsError::Other(format!("failed to list tags: {}", e)))?;
+                    let mut names: Vec<&str> = tags.iter().flatten().collect();
+                    names.sort_unstable();
+                    items.extend(names.iter().map(|name| item(kind, name, name)));
+                }
+            }
+        }
+        Ok(items)
+    }
+
+    /// Get a commit's signature and the data it signs, as
+    /// (signature, signed data), e.g. an armored GPG signature and the raw
+    /// commit object without its `gpgsig` header. None for unsigned commits.
+    pub fn get_commit_signature(
+        &self,
+        reference: &str,
+    ) -> Result<Option<(String, String)>, VcsError> {
+        let commit = self.resolve_commit(reference)?;
+        match self.repo.extract_signature(&commit.id(), None) {
+            Ok((signature, signed_data)) => Ok(Some((
+                String::from_utf8_lossy(&signature).into_owned(),
+                String::from_utf8_lossy(&signed_data).into_owned(),
+            ))),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(VcsError::Other(format!(
+                "failed to read signature of {}: {}",
+                reference, e
+            ))),
+        }
+    }
+
+    /// Check whether two refs have diverged, i.e. each has commits the other
+    /// lacks, so neither can be fast-forwarded to the other.
+    pub fn have_diverged(&self, a: &str, b: &str) -> Result<bool, VcsError> {
+        let a_commit = self.resolve_commit(a)?;
+        let b_commit = self.resolve_commit(b)?;
+        let (ahead, behind) = self
+            .repo
+            .graph_ahead_behind(a_commit.id(), b_commit.id())
+            .map_err(|e| VcsError::Other(format!("failed to compare {} and {}: {}", a, b, e)))?;
+        Ok(ahead > 0 && behind > 0)
+    }
+}
+
+impl VcsBackend for GitBackend {
+    /// Context from `.lumen.toml` (zero in minimal-header mode)
+    fn diff_config(&self) -> DiffConfig {
+        DiffConfig {
+            context_lines: self.context_lines(),
+        }
+    }
+
+    fn get_commit_with(
+        &self,
+        reference: &str,
+        config: &DiffConfig,
+    ) -> Result<CommitInfo, VcsError> {
+        let reference = reference.trim();
+        Self::validate_ref_format(reference)?;
+
+        // Use git2 to get commit metadata
+        let obj = self
+            .repo
+            .revparse_single(reference)
+            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+        let commit = obj
+            .peel_to_commit()
+            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+
+        let commit_id = commit.id().to_string();
+
+        // Canonicalize identities through .mailmap when enabled. A missing or
+        // unreadable mailmap falls back to the raw signatures.
+        let mailmap = if self.use_mailmap {
+            self.repo.mailmap().ok()
+        } else {
+            None
+        };
+        let (author, committer) = match &mailmap {
+            Some(mailmap) => (
+                commit
+                    .author_with_mailmap(mailmap)
+                    .map(|sig| format_signature(&sig))
+                    .unwrap_or_else(|_| format_signature(&commit.author())),
+                commit
+                    .committer_with_mailmap(mailmap)
+                    .map(|sig| format_signature(&sig))
+                    .unwrap_or_else(|_| format_signature(&commit.committer())),
+            ),
+            None => (
+                format_signature(&commit.author()),
+                format_signature(&commit.committer()),
+            ),
+        };
+
+        // Format time as YYYY-MM-DD HH:MM:SS
+        let time = commit.time();
+        let date = format_git_time(&time);
+
+        let message = commit
+            .message()
+            .unwrap_or("")
+            .trim_end_matches('\n')
+            .to_string();
+
+        let parent_summary = commit
+            .parent(0)
+            .ok()
+            .map(|parent| parent.summary().unwrap_or("").to_string());
+
+        // Generate diff using git2, and count stats from the same diff
+        let git_diff = self.commit_diff(&commit, config.context_lines)?;
+        let diff = self.format_diff(&git_diff, false)?;
+        let stats = self.diff_stats(&git_diff)?;
+
+        Ok(CommitInfo {
+            commit_id,
+            change_id: change_id_from_message(&message),
+            message,
+            parent_summary,
+            diff,
+            stats,
+            author,
+            committer,
+            date,
+        })
+    }
+
+    fn get_diff_stats(&self, reference: &str) -> Result<DiffStats, VcsError> {
+        self.commit_stats(&self.resolve_commit(reference)?)
+    }
+
+    fn get_working_tree_diff_with(
+        &self,
+        staged: bool,
+        config: &DiffConfig,
+    ) -> Result<String, VcsError> {
+        self.working_tree_diff_for_paths(staged, &[], config.context_lines)
+    }
+
+    fn working_snapshot_diff(&self) -> Result<String, VcsError> {
+        let mut opts = DiffOptions::new();
+        opts.show_binary(true);
+        opts.context_lines(self.context_lines());
+
+        // HEAD tree to workdir, through the index, so staged and unstaged
+        // edits appear together
+        let head = self.repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+        let diff = self
+            .repo
+            .diff_tree_to_workdir_with_index(head.as_ref(), Some(&mut opts))
+            .map_err(|e| VcsError::Other(format!("failed to create snapshot diff: {}", e)))?;
+
+        self.format_diff(&diff, true)
+    }
+
+    fn get_range_diff_with(
+        &self,
+        from: &str,
+        to: &str,
+        three_dot: bool,
+        config: &DiffConfig,
+    ) -> Result<String, VcsError> {
+        Self::validate_ref_format(from)?;
+        Self::validate_ref_format(to)?;
+
+        // Resolve both refs to commits
+        let from_obj = self
+            .repo
+            .revparse_single(from)
+            .map_err(|_| VcsError::InvalidRef(from.to_string()))?;
+        let from_commit = from_obj
+            .peel_to_commit()
+            .map_err(|_| VcsError::InvalidRef(from.to_string()))?;
+
+        let to_obj = self
+            .repo
+            .revparse_single(to)
+            .map_err(|_| VcsError::InvalidRef(to.to_string()))?;
+        let to_commit = to_obj
+            .peel_to_commit()
+            .map_err(|_| VcsError::InvalidRef(to.to_string()))?;
+
+        // For three-dot syntax, compare merge-base to 'to'
+        // For two-dot syntax, compare 'from' to 'to'
+        let base_tree = if three_dot {
+            // Find merge base
+            let merge_base_oid = self
+                .repo
+                .merge_base(from_commit.id(), to_commit.id())
+                .map_err(|e| VcsError::Other(format!("failed to find merge base: {}", e)))?;
+            let merge_base = self
+                .repo
+                .find_commit(merge_base_oid)
+                .map_err(|e| VcsError::Other(format!("failed to find merge base commit: {}", e)))?;
+            merge_base
+                .tree()
+                .map_err(|e| VcsError::Other(format!("failed to get merge base tree: {}", e)))?
+        } else {
+            from_commit
+                .tree()
+                .map_err(|e| VcsError::Other(format!("failed to get from tree: {}", e)))?
+        };
+
+        let to_tree = to_commit
+            .tree()
+            .map_err(|e| VcsError::Other(format!("failed to get to tree: {}", e)))?;
+
+        // Identical trees can't differ, so skip the diff machinery
+        if base_tree.id() == to_tree.id() {
+            return Ok(String::new());
+        }
+
+        let mut opts = DiffOptions::new();
+        opts.show_binary(true);
+        opts.context_lines(config.context_lines);
+
+        let mut diff = self
+            .repo
+            .diff_tree_to_tree(Some(&base_tree), Some(&to_tree), Some(&mut opts))
+            .map_err(|e| VcsError::Other(format!("failed to create range diff: {}", e)))?;
+        self.find_renames(&mut diff)?;
+
+        self.format_diff(&diff, false)
+    }
+
+    fn get_changed_files(&self, reference: &str) -> Result<Vec<String>, VcsError> {
+        let reference = reference.trim();
+
+        // Check if this is a range (contains ..)
+        if reference.contains("..") {
+            let parts: Vec<&str> = if reference.contains("...") {
+                reference.split("...").collect()
+            } else {
+                reference.split("..").collect()
+            };
+
+            if parts.len() == 2 {
+                Self::validate_ref_format(parts[0])?;
+                Self::validate_ref_format(parts[1])?;
+
+                let from_obj = self
+                    .repo
+                    .revparse_single(parts[0])
+                    .map_err(|_| VcsError::InvalidRef(parts[0].to_string()))?;
+                let from_commit = from_obj
+                    .peel_to_commit()
+                    .map_err(|_| VcsError::InvalidRef(parts[0].to_string()))?;
+                let from_tree = from_commit
+                    .tree()
+                    .map_err(|e| VcsError::Other(format!("failed to get from tree: {}", e)))?;
+
+                let to_obj = self
+                    .repo
+                    .revparse_single(parts[1])
+                    .map_err(|_| VcsError::InvalidRef(parts[1].to_string()))?;
+                let to_commit = to_obj
+                    .peel_to_commit()
+                    .map_err(|_| VcsError::InvalidRef(parts[1].to_string()))?;
+                let to_tree = to_commit
+                    .tree()
+                    .map_err(|e| VcsError::Other(format!("failed to get to tree: {}", e)))?;
+
+                let diff = self
+                    .repo
+                    .diff_tree_to_tree(Some(&from_tree), Some(&to_tree), None)
+                    .map_err(|e| VcsError::Other(format!("failed to create diff: {}", e)))?;
+
+                return Ok(diff
+                    .deltas()
+                    .filter_map(|d| {
+                        d.new_file()
+                            .path()
+                            .and_then(|p| p.to_str().map(String::from))
+                    })
+                    .collect());
+            }
+        }
+
+        // Single commit - compare to parent tree (or empty tree for root)
+        Self::validate_ref_format(reference)?;
+        let obj = self
+            .repo
+            .revparse_single(reference)
+            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+        let commit = obj
+            .peel_to_commit()
+            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+        let tree = commit
+            .tree()
+            .map_err(|e| VcsError::Other(format!("failed to get commit tree: {}", e)))?;
+
+        let parent_tree: Option<Tree> = if commit.parent_count() > 0 {
+            commit.parent(0).ok().and_then(|p| p.tree().ok())
+        } else {
+            None
+        };
+
+        let diff = self
+            .repo
+            .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)
+            .map_err(|e| VcsError::Other(format!("failed to create diff: {}", e)))?;
+
+        Ok(diff
+            .deltas()
+            .filter_map(|d| {
+                d.new_file()
+                    .path()
+                    .and_then(|p| p.to_str().map(String::from))
+            })
+            .collect())
+    }
+
+    fn get_file_content_at_ref(&self, reference: &str, path: &Path) -> Result<String, VcsError> {
+        let reference = reference.trim();
+        Self::validate_ref_format(reference)?;
+
+        // Resolve reference to commit
+        let obj = self
+            .repo
+            .revparse_single(reference)
+            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+        let commit = obj
+            .peel_to_commit()
+            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+        let tree = commit
+            .tree()
+            .map_err(|e| VcsError::Other(format!("failed to get tree: {}", e)))?;
+
+        // Look up file in tree
+        let entry = tree
+            .get_path(path)
+            .map_err(|_| VcsError::FileNotFound(path.display().to_string()))?;
+
+        // Get blob content
+        let blob = self
+            .repo
+            .find_blob(entry.id())
+            .map_err(|_| VcsError::FileNotFound(path.display().to_string()))?;
+
+        Ok(String::from_utf8_lossy(blob.content()).into_owned())
+    }
+
+    fn get_current_branch(&self) -> Result<Option<String>, VcsError> {
+        let head = self
+            .repo
+            .head()
+            .map_err(|e| VcsError::Other(format!("failed to get HEAD: {}", e)))?;
+
+        if head.is_branch() {
+            Ok(head.shorthand().map(|s| s.to_string()))
+        } else {
+            // Detached HEAD state
+            Ok(None)
+        }
+    }
+
+    fn get_commit_log_for_fzf(&self) -> Result<String, VcsError> {
+        let mut revwalk = self
+            .repo
+            .revwalk()
+            .map_err(|e| VcsError::Other(format!("failed to create revwalk: {}", e)))?;
+
+        // Start from HEAD
+        revwalk
+            .push_head()
+            .map_err(|e| VcsError::Other(format!("failed to push head: {}", e)))?;
+        // Newest first by commit time, like `git log`
+        revwalk
+            .set_sorting(Sort::TIME)
+            .map_err(|e| VcsError::Other(format!("failed to sort revwalk: {}", e)))?;
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        let no_color = super::no_color();
+
+        let mut output = String::new();
+        for oid_result in revwalk {
+            let oid = oid_result.map_err(|e| VcsError::Other(format!("revwalk error: {}", e)))?;
+            let commit = self
+                .repo
+                .find_commit(oid)
+                .map_err(|e| VcsError::Other(format!("failed to find commit: {}", e)))?;
+
+            let short_id = &oid.to_string()[..7];
+            let summary = commit.summary().unwrap_or("");
+            let time_secs = if self.use_committer_time {
+                commit.committer().when().seconds()
+            } else {
+                commit.author().when().seconds()
+            };
+            let relative_time = self.time_granularity.format(now - time_secs);
+
+            // Format: short_hash summary relative_time
+            // Using ANSI codes for color (yellow hash, default text, dim time)
+            if no_color {
+                output.push_str(&format!("{} {} {}\n", short_id, summary, relative_time));
+            } else {
+                output.push_str(&format!(
+                    "\x1b[33m{}\x1b[0m {} \x1b[90m{}\x1b[0m\n",
+                    short_id, summary, relative_time
+                ));
+            }
+        }
+
+        Ok(output)
+    }
+
+    fn resolve_ref(&self, reference: &str) -> Result<String, VcsError> {
+        let reference = reference.trim();
+        Self::validate_ref_format(reference)?;
+
+        // Use git2 to resolve reference to commit SHA
+        let obj = self
+            .repo
+            .revparse_single(reference)
+            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+
+        let commit = obj
+            .peel_to_commit()
+            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+
+        Ok(commit.id().to_string())
+    }
+
+    fn get_working_tree_changed_files(&self) -> Result<Vec<String>, VcsError> {
+        use std::collections::HashSet;
+
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true);
+        opts.exclude_submodules(true);
+        opts.include_ignored(false);
+        // Files whose stat data matches the index entry are skipped without
+        // hashing. Don't write refreshed stat data back: this is a read-only
+        // query and must not race with a concurrent `git add`. libgit2 has no
+        // untracked cache or fsmonitor support to enable beyond this.
+        opts.update_index(false);
+
+        let statuses = self
+            .repo
+            .statuses(Some(&mut opts))
+            .map_err(|e| VcsError::Other(format!("failed to get status: {}", e)))?;
+
+        let files: HashSet<String> = statuses
+            .iter()
+            .filter_map(|s| s.path().map(String::from))
+            .collect();
+
+        Ok(files.into_iter().collect())
+    }
+
+    fn get_merge_base(&self, ref1: &str, ref2: &str) -> Result<String, VcsError> {
+        let ref1 = ref1.trim();
+        let ref2 = ref2.trim();
+
+        Self::validate_ref_format(ref1)?;
+        Self::validate_ref_format(ref2)?;
+
+        let obj1 = self
+            .repo
+            .revparse_single(ref1)
+            .map_err(|_| VcsError::InvalidRef(ref1.to_string()))?;
+        let oid1 = obj1
+            .peel_to_commit()
+            .map_err(|_| VcsError::InvalidRef(ref1.to_string()))?
+            .id();
+
+        let obj2 = self
+            .repo
+            .revparse_single(ref2)
+            .map_err(|_| VcsError::InvalidRef(ref2.to_string()))?;
+        let oid2 = obj2
+            .peel_to_commit()
+            .map_err(|_| VcsError::InvalidRef(ref2.to_string()))?
+            .id();
+
+        let merge_base = self
+            .repo
+            .merge_base(oid1, oid2)
+            .map_err(|e| VcsError::Other(format!("failed to find merge base: {}", e)))?;
+
+        Ok(merge_base.to_string())
+    }
+
+    fn working_copy_parent_ref(&self) -> &'static str {
+        "HEAD"
+    }
+
+    fn get_range_changed_files(&self, from: &str, to: &str) -> Result<Vec<String>, VcsError> {
+        let from = from.trim();
+        let to = to.trim();
+
+        Self::validate_ref_format(from)?;
+        Self::validate_ref_format(to)?;
+
+        let from_obj = self
+            .repo
+            .revparse_single(from)
+            .map_err(|_| VcsError::InvalidRef(from.to_string()))?;
+        let from_tree = from_obj
+            .peel_to_commit()
+            .map_err(|_| VcsError::InvalidRef(from.to_string()))?
+            .tree()
+            .map_err(|e| VcsError::Other(format!("failed to get from tree: {}", e)))?;
+
+        let to_obj = self
+            .repo
+            .revparse_single(to)
+            .map_err(|_| VcsError::InvalidRef(to.to_string()))?;
+        let to_tree = to_obj
+            .peel_to_commit()
+            .map_err(|_| VcsError::InvalidRef(to.to_string()))?
+            .tree()
+            .map_err(|e| VcsError::Other(format!("failed to get to tree: {}", e)))?;
+
+        if from_tree.id() == to_tree.id() {
+            return Ok(Vec::new());
+        }
+
+        let diff = self
+            .repo
+            .diff_tree_to_tree(Some(&from_tree), Some(&to_tree), None)
+            .map_err(|e| VcsError::Other(format!("failed to create diff: {}", e)))?;
+
+        Ok(diff
+            .deltas()
+            .filter_map(|d| {
+                d.new_file()
+                    .path()
+                    .and_then(|p| p.to_str().map(String::from))
+            })
+            .collect())
+    }
+
+    fn get_parent_ref_or_empty(&self, reference: &str) -> Result<String, VcsError> {
+        let reference = reference.trim();
+        Self::validate_ref_format(reference)?;
+
+        let obj = self
+            .repo
+            .revparse_single(reference)
+            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+        let commit = obj
+            .peel_to_commit()
+            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+
+        if commit.parent_count() > 0 {
+            // Has parent - return the parent ref
+            Ok(format!("{}^", reference))
+        } else {
+            // No parent (root commit) - return git's empty tree SHA
+            // This is a well-known constant: the SHA of an empty tree
+            Ok("4b825dc642cb6eb9a060e54bf8d69288fbee4904".to_string())
+        }
+    }
+
+    fn get_commits_in_range(
+        &self,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<StackedCommitInfo>, VcsError> {
+        let mut commits: Vec<StackedCommitInfo> = Vec::new();
+        self.walk_commits_in_range(from, to, &mut |commit| {
+            commits.push(commit);
+            true
+        })?;
+        Ok(commits)
+    }
+
+    fn walk_commits_in_range(
+        &self,
+        from: &str,
+        to: &str,
+        f: &mut dyn FnMut(StackedCommitInfo) -> bool,
+    ) -> Result<(), VcsError> {
+        let from = from.trim();
+        let to = to.trim();
+
+        Self::validate_ref_format(from)?;
+        Self::validate_ref_format(to)?;
+
+        // Resolve refs to OIDs
+        let from_obj = self
+            .repo
+            .revparse_single(from)
+            .map_err(|_| VcsError::InvalidRef(from.to_string()))?;
+        let from_oid = from_obj
+            .peel_to_commit()
+            .map_err(|_| VcsError::InvalidRef(from.to_string()))?
+            .id();
+
+        let to_obj = self
+            .repo
+            .revparse_single(to)
+            .map_err(|_| VcsError::InvalidRef(to.to_string()))?;
+        let to_oid = to_obj
+            .peel_to_commit()
+            .map_err(|_| VcsError::InvalidRef(to.to_string()))?
+            .id();
+
+        // Set up revwalk from 'to' to 'from' (exclusive)
+        let mut revwalk = self
+            .repo
+            .revwalk()
+            .map_err(|e| VcsError::Other(format!("failed to create revwalk: {}", e)))?;
+        revwalk
+            .push(to_oid)
+            .map_err(|e| VcsError::Other(format!("failed to push to revwalk: {}", e)))?;
+        revwalk
+            .hide(from_oid)
+            .map_err(|e| VcsError::Other(format!("failed to hide from revwalk: {}", e)))?;
+        // Parents before children, oldest first, regardless of timestamps
+        revwalk
+            .set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)
+            .map_err(|e| VcsError::Other(format!("failed to sort revwalk: {}", e)))?;
+        if self.first_parent {
+            revwalk
+                .simplify_first_parent()
+                .map_err(|e| VcsError::Other(format!("failed to simplify revwalk: {}", e)))?;
+        }
+
+        // Visit commits (already oldest first)
+        for oid_result in revwalk {
+            let oid = oid_result.map_err(|e| VcsError::Other(format!("revwalk error: {}", e)))?;
+            let commit = self
+                .repo
+                .find_commit(oid)
+                .map_err(|e| VcsError::Other(format!("failed to find commit: {}", e)))?;
+
+            let commit_id = oid.to_string();
+            let short_id = commit_id[..7.min(commit_id.len())].to_string();
+            let summary = commit.summary().unwrap_or("").to_string();
+
+            // Filter commits with no file changes (e.g., merge commits)
+            if !Self::commit_is_empty(&commit) {
+                let info = StackedCommitInfo {
+                    commit_id,
+                    short_id,
+                    change_id: commit.message().and_then(change_id_from_message),
+                    summary,
+                };
+                if !f(info) {
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            change_ids: false,
+            stash: true,
+            signing: true,
+            partial_commit: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcs::test_utils::RepoGuard;
+
+    /// Build a tree from `parent` with the given files added or replaced.
+    fn tree_with<'r>(
+        repo: &'r Repository,
+        parent: &git2::Tree,
+        files: &[(&str, &str)],
+    ) -> git2::Tree<'r> {
+        let mut builder = repo.treebuilder(Some(parent)).unwrap();
+        for (name, content) in files {
+            let blob = repo.blob(content.as_bytes()).unwrap();
+            builder.insert(name, blob, 0o100644).unwrap();
+        }
+        repo.find_tree(builder.write().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_get_commit_returns_valid_info() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        let info = backend.get_commit("HEAD").expect("should get commit");
+        assert!(!info.commit_id.is_empty());
+        assert!(info.change_id.is_none()); // Git has no change IDs
+        assert_eq!(info.message, "init");
+        assert!(info.author.contains("Test User"));
+        assert!(!info.diff.is_empty());
+    }
+
+    #[test]
+    fn test_get_working_tree_diff_returns_string() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        // Should succeed even if empty
+        let diff = backend.get_working_tree_diff(false);
+        assert!(diff.is_ok());
+    }
+
+    #[test]
+    fn test_get_changed_files_returns_paths() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        let files = backend.get_changed_files("HEAD").expect("should get files");
+        assert!(files.contains(&"README.md".to_string()));
+    }
+
+    #[test]
+    fn test_get_current_branch() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        let branch = backend.get_current_branch().expect("should get branch");
+        assert!(branch.is_some());
+    }
+
+    #[test]
+    fn test_get_file_content_at_ref() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        let content = backend
+            .get_file_content_at_ref("HEAD", Path::new("README.md"))
+            .expect("should get content");
+        assert_eq!(content.trim(), "hello");
+    }
+
+    #[test]
+    fn test_invalid_ref_returns_error() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        let result = backend.get_commit("nonexistent12345");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_get_file_content_at_ref_missing_file() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        let result = backend.get_file_content_at_ref("HEAD", Path::new("nonexistent.txt"));
+        assert!(
+            matches!(result, Err(VcsError::FileNotFound(_))),
+            "Expected FileNotFound error, got: {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn test_get_commit_log_for_fzf() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        let log = backend.get_commit_log_for_fzf().expect("should get log");
+        assert!(!log.is_empty(), "commit log should not be empty");
+        // Log should contain the short hash from the commit
+        assert!(
+            log.lines().next().is_some(),
+            "log should have at least one line"
+        );
+    }
+
+    #[test]
+    fn test_get_working_tree_diff_staged() {
+        use crate::vcs::test_utils::{git, make_temp_dir};
+        use std::fs;
+
+        let _lock = crate::vcs::test_utils::cwd_lock()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = make_temp_dir("git-staged");
+        let original = std::env::current_dir().expect("get cwd");
+
+        git(&dir, &["init"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "user.name", "Test User"]);
+
+        // Initial commit
+        fs::write(dir.join("file.txt"), "initial\n").expect("write file");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "init"]);
+
+        // Stage one change, leave another unstaged
+        fs::write(dir.join("file.txt"), "staged change\n").expect("modify file");
+        git(&dir, &["add", "file.txt"]);
+        fs::write(dir.join("file.txt"), "staged change\nunstaged change\n").expect("modify again");
+
+        std::env::set_current_dir(&dir).expect("set cwd");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        // Staged diff should only show "staged change"
+        let staged_diff = backend
+            .get_working_tree_diff(true)
+            .expect("should get staged diff");
+        assert!(
+            staged_diff.contains("staged change"),
+            "staged diff should contain staged changes"
+        );
+        assert!(
+            !staged_diff.contains("unstaged change"),
+            "staged diff should NOT contain unstaged changes"
+        );
+
+        // Unstaged diff should show the additional unstaged change
+        let unstaged_diff = backend
+            .get_working_tree_diff(false)
+            .expect("should get unstaged diff");
+        assert!(
+            unstaged_diff.contains("unstaged change"),
+            "unstaged diff should contain unstaged changes"
+        );
+
+        // Cleanup
+        let _ = std::env::set_current_dir(&original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_get_range_diff() {
+        use crate::vcs::test_utils::{git, make_temp_dir};
+        use std::fs;
+
+        let _lock = crate::vcs::test_utils::cwd_lock()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = make_temp_dir("git-range");
+        let original = std::env::current_dir().expect("get cwd");
+
+        git(&dir, &["init"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "user.name", "Test User"]);
+
+        // Commit A
+        fs::write(dir.join("file.txt"), "commit A\n").expect("write file");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "commit A"]);
+
+        // Commit B
+        fs::write(dir.join("file.txt"), "commit B\n").expect("modify file");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "commit B"]);
+
+        std::env::set_current_dir(&dir).expect("set cwd");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        // Range diff HEAD~1..HEAD (two-dot)
+        let diff = backend
+            .get_range_diff("HEAD~1", "HEAD", false)
+            .expect("should get range diff");
+        assert!(
+            diff.contains("commit A") || diff.contains("commit B"),
+            "range diff should contain changes"
+        );
+
+        // Three-dot range diff also works
+        let diff_3dot = backend
+            .get_range_diff("HEAD~1", "HEAD", true)
+            .expect("should get three-dot diff");
+        assert!(
+            !diff_3dot.is_empty() || diff.contains("commit"),
+            "three-dot diff should work"
+        );
+
+        // Cleanup
+        let _ = std::env::set_current_dir(&original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_diff_config_zero_context() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("f.txt"), "one\ntwo\nthree\nfour\nfive\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "add file"]);
+        fs::write(repo.dir.join("f.txt"), "one\ntwo\nTHREE\nfour\nfive\n").expect("write file");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let zero = DiffConfig { context_lines: 0 };
+        // Hunk header, then only the changed lines
+        let expected_hunk = "\n-three\n+THREE\n";
+
+        let diff = backend
+            .get_working_tree_diff_with(false, &zero)
+            .expect("should diff working tree");
+        assert!(diff.ends_with(expected_hunk), "diff: {}", diff);
+        assert!(diff.contains("\n@@ -3 +3 @@"), "diff: {}", diff);
+        assert!(!diff.contains(" four\n"), "diff: {}", diff);
+        assert!(backend
+            .get_working_tree_diff(false)
+            .expect("should diff working tree")
+            .contains(" two\n"));
+
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "shout"]);
+        let commit = backend
+            .get_commit_with("HEAD", &zero)
+            .expect("should get commit");
+        assert!(
+            commit.diff.ends_with(expected_hunk),
+            "diff: {}",
+            commit.diff
+        );
+        let range = backend
+            .get_range_diff_with("HEAD~1", "HEAD", false, &zero)
+            .expect("should diff range");
+        assert!(range.ends_with(expected_hunk), "diff: {}", range);
+    }
+
+    #[test]
+    fn test_range_diff_excludes_lock_files() {
+        use crate::vcs::test_utils::{git, make_temp_dir};
+        use std::fs;
+
+        let _lock = crate::vcs::test_utils::cwd_lock()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = make_temp_dir("git-range-exclusion");
+        let original = std::env::current_dir().expect("get cwd");
+
+        git(&dir, &["init"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "user.name", "Test User"]);
+
+        // Commit A with lock file
+        fs::write(dir.join("file.txt"), "A\n").expect("write file");
+        fs::write(dir.join("package-lock.json"), "{\"v\":1}\n").expect("write lock");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "A"]);
+
+        // Commit B - modify both
+        fs::write(dir.join("file.txt"), "B\n").expect("modify file");
+        fs::write(dir.join("package-lock.json"), "{\"v\":2}\n").expect("modify lock");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "B"]);
+
+        std::env::set_current_dir(&dir).expect("set cwd");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let diff = backend
+            .get_range_diff("HEAD~1", "HEAD", false)
+            .expect("should get range diff");
+
+        assert!(
+            diff.contains("file.txt"),
+            "range diff should contain file.txt"
+        );
+        assert!(
+            !diff.contains("package-lock.json"),
+            "range diff should NOT contain package-lock.json"
+        );
+
+        // Cleanup
+        let _ = std::env::set_current_dir(&original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_diff_excludes_lock_files() {
+        use crate::vcs::test_utils::{git, make_temp_dir};
+        use std::fs;
+
+        let _lock = crate::vcs::test_utils::cwd_lock()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = make_temp_dir("git-exclusion");
+        let original = std::env::current_dir().expect("get cwd");
+
+        git(&dir, &["init"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "user.name", "Test User"]);
+
+        // Create files including lock files
+        fs::write(dir.join("test.txt"), "hello\n").expect("write test.txt");
+        fs::write(dir.join("package-lock.json"), "{}\n").expect("write package-lock.json");
+        fs::write(dir.join("Cargo.lock"), "lock\n").expect("write Cargo.lock");
+
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "init with lock files"]);
+
+        std::env::set_current_dir(&dir).expect("set cwd");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let info = backend.get_commit("HEAD").expect("should get commit");
+
+        // Diff should contain test.txt but NOT lock files
+        assert!(
+            info.diff.contains("test.txt"),
+            "diff should contain test.txt"
+        );
+        assert!(
+            !info.diff.contains("package-lock.json"),
+            "diff should NOT contain package-lock.json"
+        );
+        assert!(
+            !info.diff.contains("Cargo.lock"),
+            "diff should NOT contain Cargo.lock"
+        );
+
+        // Cleanup
+        let _ = std::env::set_current_dir(&original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_working_tree_diff_excludes_lock_files() {
+        use crate::vcs::test_utils::{git, make_temp_dir};
+        use std::fs;
+
+        let _lock = crate::vcs::test_utils::cwd_lock()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = make_temp_dir("git-wt-exclusion");
+        let original = std::env::current_dir().expect("get cwd");
+
+        git(&dir, &["init"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "user.name", "Test User"]);
+
+        // Initial commit
+        fs::write(dir.join("test.txt"), "hello\n").expect("write test.txt");
+        fs::write(dir.join("package-lock.json"), "{}\n").expect("write package-lock.json");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "init"]);
+
+        // Modify both files
+        fs::write(dir.join("test.txt"), "world\n").expect("modify test.txt");
+        fs::write(dir.join("package-lock.json"), "{\"v\": 2}\n").expect("modify package-lock.json");
+
+        std::env::set_current_dir(&dir).expect("set cwd");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let diff = backend
+            .get_working_tree_diff(false)
+            .expect("should get diff");
+
+        // Diff should contain test.txt but NOT package-lock.json
+        assert!(
+            diff.contains("test.txt"),
+            "working tree diff should contain test.txt"
+        );
+        assert!(
+            !diff.contains("package-lock.json"),
+            "working tree diff should NOT contain package-lock.json"
+        );
+
+        // Cleanup
+        let _ = std::env::set_current_dir(&original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_get_working_tree_diff_empty() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        // Clean working tree should return empty string
+        let diff = backend
+            .get_working_tree_diff(false)
+            .expect("should succeed on clean tree");
+        assert!(
+            diff.is_empty(),
+            "clean working tree should return empty diff"
+        );
+    }
+
+    #[test]
+    fn test_get_range_diff_identical_commits() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        // Diff of HEAD..HEAD should be empty
+        let diff = backend
+            .get_range_diff("HEAD", "HEAD", false)
+            .expect("should succeed for identical commits");
+        assert!(diff.is_empty(), "diff of identical commits should be empty");
+    }
+
+    #[test]
+    fn test_range_diff_omits_reverted_files() {
+        use crate::vcs::test_utils::{git, make_temp_dir};
+        use std::fs;
+
+        let _lock = crate::vcs::test_utils::cwd_lock()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = make_temp_dir("git-range-net-zero");
+        let original = std::env::current_dir().expect("get cwd");
+
+        git(&dir, &["init"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "user.name", "Test User"]);
+
+        // Base commit
+        fs::write(dir.join("file.txt"), "base\n").expect("write file");
+        fs::write(dir.join("reverted.txt"), "original\n").expect("write reverted");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "base"]);
+
+        // Commit 1 - change both files
+        fs::write(dir.join("file.txt"), "changed\n").expect("modify file");
+        fs::write(dir.join("reverted.txt"), "temporary\n").expect("modify reverted");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "change"]);
+
+        // Commit 2 - revert reverted.txt back to its original content
+        fs::write(dir.join("reverted.txt"), "original\n").expect("revert file");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "revert"]);
+
+        std::env::set_current_dir(&dir).expect("set cwd");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let diff = backend
+            .get_range_diff("HEAD~2", "HEAD", false)
+            .expect("should get range diff");
+        assert!(
+            diff.contains("file.txt"),
+            "range diff should contain file.txt"
+        );
+        assert!(
+            !diff.contains("reverted.txt"),
+            "net-zero file should be omitted from range diff, got: {}",
+            diff
+        );
+
+        let files = backend
+            .get_range_changed_files("HEAD~2", "HEAD")
+            .expect("should get range files");
+        assert_eq!(files, vec!["file.txt".to_string()]);
+
+        let _ = std::env::set_current_dir(&original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_root_commit_diff_marks_new_files() {
+        use crate::vcs::test_utils::{git, make_temp_dir};
+        use std::fs;
+
+        let _lock = crate::vcs::test_utils::cwd_lock()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = make_temp_dir("git-root-new-files");
+        let original = std::env::current_dir().expect("get cwd");
+
+        git(&dir, &["init"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "user.name", "Test User"]);
+
+        fs::write(dir.join("a.txt"), "a\n").expect("write a");
+        fs::write(dir.join("b.txt"), "b\n").expect("write b");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "root"]);
+
+        std::env::set_current_dir(&dir).expect("set cwd");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let info = backend.get_commit("HEAD").expect("should get commit");
+
+        for path in ["a.txt", "b.txt"] {
+            let header = format!("diff --git a/{0} b/{0}\nnew file mode 100644\n", path);
+            assert!(
+                info.diff.contains(&header),
+                "{} should have a new file header, got: {}",
+                path,
+                info.diff
+            );
+        }
+        assert_eq!(info.diff.matches("new file mode").count(), 2);
+
+        let _ = std::env::set_current_dir(&original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_get_commit_applies_mailmap() {
+        use crate::vcs::test_utils::{git, make_temp_dir};
+        use std::fs;
+
+        let _lock = crate::vcs::test_utils::cwd_lock()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = make_temp_dir("git-mailmap");
+        let original = std::env::current_dir().expect("get cwd");
+
+        git(&dir, &["init"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "user.name", "Test User"]);
+
+        fs::write(
+            dir.join(".mailmap"),
+            "Canonical Name <canonical@example.com> Test User <test@example.com>\n",
+        )
+        .expect("write mailmap");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "init"]);
+
+        std::env::set_current_dir(&dir).expect("set cwd");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let info = backend.get_commit("HEAD").expect("should get commit");
+        assert_eq!(info.author, "Canonical Name <canonical@example.com>");
+        assert_eq!(info.committer, "Canonical Name <canonical@example.com>");
+
+        // Opting out reports the raw identity
+        let raw = GitBackend::from_cwd()
+            .expect("should open repo")
+            .with_mailmap(false)
+            .get_commit("HEAD")
+            .expect("should get commit");
+        assert_eq!(raw.author, "Test User <test@example.com>");
+
+        let _ = std::env::set_current_dir(&original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_get_file_hunk_returns_requested_hunk() {
+        use crate::vcs::test_utils::{git, make_temp_dir};
+        use std::fs;
+
+        let _lock = crate::vcs::test_utils::cwd_lock()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = make_temp_dir("git-file-hunk");
+        let original = std::env::current_dir().expect("get cwd");
+
+        git(&dir, &["init"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "user.name", "Test User"]);
+
+        let lines: Vec<String> = (1..=30).map(|i| format!("line {}", i)).collect();
+        fs::write(dir.join("file.txt"), lines.join("\n") + "\n").expect("write file");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "base"]);
+
+        // Three changes far enough apart to produce three hunks
+        let mut changed = lines.clone();
+        changed[1] = "changed 2".to_string();
+        changed[14] = "changed 15".to_string();
+        changed[27] = "changed 28".to_string();
+        fs::write(dir.join("file.txt"), changed.join("\n") + "\n").expect("modify file");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "three hunks"]);
+
+        std::env::set_current_dir(&dir).expect("set cwd");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let hunk = backend
+            .get_file_hunk("HEAD", Path::new("file.txt"), 1)
+            .expect("should get middle hunk");
+
+        assert!(hunk.starts_with("@@ -12,7 +12,7 @@"), "got: {}", hunk);
+        assert!(hunk.contains("-line 15\n+changed 15\n"), "got: {}", hunk);
+        assert!(
+            !hunk.contains("changed 2\n"),
+            "should not include first hunk"
+        );
+        assert!(!hunk.contains("changed 28"), "should not include last hunk");
+
+        let out_of_range = backend.get_file_hunk("HEAD", Path::new("file.txt"), 3);
+        assert!(out_of_range.is_err(), "hunk index 3 should be out of range");
+
+        let _ = std::env::set_current_dir(&original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_get_commit_refs_returns_decorations() {
+        use crate::vcs::test_utils::{git, make_temp_dir};
+        use std::fs;
+
+        let _lock = crate::vcs::test_utils::cwd_lock()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = make_temp_dir("git-commit-refs");
+        let original = std::env::current_dir().expect("get cwd");
+
+        git(&dir, &["init"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "user.name", "Test User"]);
+
+        fs::write(dir.join("file.txt"), "first\n").expect("write file");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "first"]);
+        git(&dir, &["tag", "v0.1.0"]);
+
+        fs::write(dir.join("file.txt"), "second\n").expect("modify file");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "second"]);
+        git(&dir, &["tag", "v1.0.0"]);
+        git(&dir, &["branch", "feature"]);
+
+        std::env::set_current_dir(&dir).expect("set cwd");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let refs = backend
+            .get_commit_refs("HEAD")
+            .expect("should get decorations");
+
+        assert!(refs.contains(&"v1.0.0".to_string()), "got: {:?}", refs);
+        assert!(refs.contains(&"feature".to_string()), "got: {:?}", refs);
+        assert!(
+            !refs.contains(&"v0.1.0".to_string()),
+            "tag on another commit should not be included, got: {:?}",
+            refs
+        );
+
+        let _ = std::env::set_current_dir(&original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_get_workdir_diff_against_remote() {
+        use crate::vcs::test_utils::{git, make_temp_dir};
+        use std::fs;
+
+        let _lock = crate::vcs::test_utils::cwd_lock()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = make_temp_dir("git-remote-diff");
+        let original = std::env::current_dir().expect("get cwd");
+
+        git(&dir, &["init"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "user.name", "Test User"]);
+
+        fs::write(dir.join("file.txt"), "remote\n").expect("write file");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "remote state"]);
+
+        // Simulate a fetched remote-tracking ref at the current commit
+        {
+            let repo = Repository::open(&dir).expect("open repo");
+            let head = repo.head().unwrap().target().unwrap();
+            repo.reference("refs/remotes/origin/main", head, true, "test fetch")
+                .expect("create remote-tracking ref");
+        }
+
+        // Local commit plus an uncommitted edit
+        fs::write(dir.join("file.txt"), "local commit\n").expect("modify file");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "local"]);
+        fs::write(dir.join("file.txt"), "local commit\nuncommitted\n").expect("modify again");
+
+        std::env::set_current_dir(&dir).expect("set cwd");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let diff = backend
+            .get_workdir_diff_against_remote("origin", "main")
+            .expect("should diff against remote");
+        assert!(diff.contains("-remote"), "got: {}", diff);
+        assert!(diff.contains("+local commit"), "got: {}", diff);
+        assert!(diff.contains("+uncommitted"), "got: {}", diff);
+
+        let missing = backend.get_workdir_diff_against_remote("origin", "nope");
+        assert!(
+            matches!(&missing, Err(VcsError::InvalidRef(msg)) if msg.contains("git fetch")),
+            "missing remote ref should suggest fetching, got: {:?}",
+            missing
+        );
+
+        let _ = std::env::set_current_dir(&original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_get_commit_files_changed_excludes_lock_files() {
+        use crate::vcs::test_utils::{git, make_temp_dir};
+        use std::fs;
+
+        let _lock = crate::vcs::test_utils::cwd_lock()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = make_temp_dir("git-files-changed");
+        let original = std::env::current_dir().expect("get cwd");
+
+        git(&dir, &["init"]);
+        git(&dir, &["config", "user.email", "test@example.com"]);
+        git(&dir, &["config", "user.name", "Test User"]);
+
+        fs::write(dir.join("a.txt"), "a\n").expect("write a");
+        fs::write(dir.join("b.txt"), "b\n").expect("write b");
+        fs::write(dir.join("c.txt"), "c\n").expect("write c");
+        fs::write(dir.join("Cargo.lock"), "lock\n").expect("write lock");
+        git(&dir, &["add", "."]);
+        git(&dir, &["commit", "-m", "three files and a lockfile"]);
+
+        std::env::set_current_dir(&dir).expect("set cwd");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let info = backend.get_commit("HEAD").expect("should get commit");
+        assert_eq!(
+            info.stats.files_changed, 3,
+            "lockfile should not be counted"
+        );
+
+        let _ = std::env::set_current_dir(&original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_working_tree_diff_labels_symlink_change() {
+        use std::fs;
+        use std::os::unix::fs::symlink;
+
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("a.txt"), "a\n").expect("write a");
+        fs::write(repo.dir.join("b.txt"), "b\n").expect("write b");
+        symlink("a.txt", repo.dir.join("link")).expect("create symlink");
+        crate::vcs::test_utils::git(&repo.dir, &["add", "."]);
+        crate::vcs::test_utils::git(&repo.dir, &["commit", "-m", "add link"]);
+
+        fs::remove_file(repo.dir.join("link")).expect("remove symlink");
+        symlink("b.txt", repo.dir.join("link")).expect("retarget symlink");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let diff = backend
+            .get_working_tree_diff(false)
+            .expect("should get diff");
+
+        assert!(
+            diff.contains("symlink path: a.txt -> b.txt"),
+            "symlink change should be labeled, got: {}",
+            diff
+        );
+    }
+
+    #[test]
+    fn test_range_with_identical_trees_is_empty() {
+        let repo = RepoGuard::new();
+        crate::vcs::test_utils::git(&repo.dir, &["tag", "same"]);
+        // An empty commit has the same tree as its parent
+        crate::vcs::test_utils::git(&repo.dir, &["commit", "--allow-empty", "-m", "empty"]);
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        assert_eq!(backend.get_range_diff("HEAD", "HEAD", false).unwrap(), "");
+        assert_eq!(backend.get_range_diff("same", "HEAD", false).unwrap(), "");
+        assert!(backend
+            .get_range_changed_files("same", "HEAD")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_lumen_toml_settings_are_applied() {
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        fs::create_dir_all(repo.dir.join("dist")).expect("create dist");
+        fs::write(repo.dir.join("dist/app.js"), "old\n").expect("write app.js");
+        fs::write(repo.dir.join("notes.txt"), "1\n2\n3\n4\n5\n").expect("write notes");
+        crate::vcs::test_utils::git(&repo.dir, &["add", "."]);
+        crate::vcs::test_utils::git(&repo.dir, &["commit", "-m", "add files"]);
+
+        fs::write(repo.dir.join("dist/app.js"), "new\n").expect("modify app.js");
+        fs::write(repo.dir.join("notes.txt"), "1\n2\nthree\n4\n5\n").expect("modify notes");
+        fs::write(
+            repo.dir.join(".lumen.toml"),
+            "excludes = [\"dist/\"]\ncontext_lines = 0\ndefault_base = \"develop\"\n",
+        )
+        .expect("write settings");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert_eq!(backend.default_base(), Some("develop"));
+
+        let diff = backend
+            .get_working_tree_diff(false)
+            .expect("should get diff");
+        assert!(!diff.contains("dist/app.js"), "dist/ should be excluded");
+        assert!(diff.contains("+three"), "notes.txt change expected");
+        assert!(
+            !diff.contains(" 2\n"),
+            "context_lines = 0 should drop context, got: {}",
+            diff
+        );
+    }
+
+    #[test]
+    fn test_anchored_exclude_keeps_nested_lock_files() {
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        fs::create_dir_all(repo.dir.join("crates/foo")).expect("create crate dir");
+        fs::write(repo.dir.join("Cargo.lock"), "root\n").expect("write root lock");
+        fs::write(repo.dir.join("crates/foo/Cargo.lock"), "nested\n").expect("write nested lock");
+        fs::write(
+            repo.dir.join(".lumen.toml"),
+            "excludes = [\"/Cargo.lock\"]\n",
+        )
+        .expect("write settings");
+        crate::vcs::test_utils::git(&repo.dir, &["add", "."]);
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let diff = backend
+            .get_working_tree_diff(true)
+            .expect("should get diff");
+        assert!(diff.contains("crates/foo/Cargo.lock"), "got: {}", diff);
+        assert!(
+            !diff.contains("+root"),
+            "root lock should be excluded: {}",
+            diff
+        );
+    }
+
+    #[test]
+    fn test_lumenignore_per_directory() {
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        for dir in ["apps/web", "apps/api"] {
+            fs::create_dir_all(repo.dir.join(dir)).expect("create dir");
+            fs::write(repo.dir.join(dir).join("types.css.d.ts"), "types\n").expect("write file");
+            fs::write(repo.dir.join(dir).join("schema.sql"), "schema\n").expect("write file");
+        }
+        fs::write(repo.dir.join("apps/web/.lumenignore"), "*.css.d.ts\n").expect("write ignore");
+        fs::write(repo.dir.join("apps/api/.lumenignore"), "*.sql\n").expect("write ignore");
+        crate::vcs::test_utils::git(&repo.dir, &["add", "."]);
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let diff = backend
+            .get_working_tree_diff(true)
+            .expect("should get diff");
+        assert!(!diff.contains("apps/web/types.css.d.ts"), "got: {}", diff);
+        assert!(diff.contains("apps/web/schema.sql"), "got: {}", diff);
+        assert!(!diff.contains("apps/api/schema.sql"), "got: {}", diff);
+        assert!(diff.contains("apps/api/types.css.d.ts"), "got: {}", diff);
+    }
+
+    #[test]
+    fn test_malformed_lumen_toml_is_error() {
+        let repo = RepoGuard::new();
+        std::fs::write(repo.dir.join(".lumen.toml"), "context_lines = [").expect("write settings");
+
+        match GitBackend::from_cwd() {
+            Err(VcsError::Other(msg)) => {
+                assert!(msg.contains(".lumen.toml"), "got: {}", msg)
+            }
+            Err(e) => panic!("expected parse error, got: {}", e),
+            Ok(_) => panic!("malformed settings should fail to load"),
+        }
+    }
+
+    #[test]
+    fn test_minimal_headers_drop_positions_and_context() {
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("notes.txt"), "1\n2\n3\n4\n5\n6\n7\n8\n9\n").expect("write notes");
+        crate::vcs::test_utils::git(&repo.dir, &["add", "."]);
+        crate::vcs::test_utils::git(&repo.dir, &["commit", "-m", "add notes"]);
+        fs::write(
+            repo.dir.join("notes.txt"),
+            "one\n2\n3\n4\n5\n6\n7\n8\nnine\n",
+        )
+        .expect("modify notes");
+
+        let backend = GitBackend::from_cwd()
+            .expect("should open repo")
+            .with_minimal_headers(true);
+        let diff = backend
+            .get_working_tree_diff(false)
+            .expect("should get diff");
+
+        assert!(diff.contains("+++ b/notes.txt"), "file header kept");
+        assert_eq!(diff.matches("@@ hunk @@\n").count(), 2, "got: {}", diff);
+        assert!(!diff.contains("@@ -"), "positions should be dropped");
+        assert!(!diff.contains("\n 5\n"), "context should be dropped");
+        assert!(diff.contains("-1\n+one\n"));
+    }
+
+    #[test]
+    fn test_list_stashes_with_messages() {
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert!(backend.list_stashes().unwrap().is_empty());
+
+        let mut git_repo = Repository::open(&repo.dir).expect("open repo");
+        let sig = Signature::now("Test User", "test@example.com").expect("signature");
+        fs::write(repo.dir.join("README.md"), "first\n").expect("modify readme");
+        git_repo
+            .stash_save(&sig, "first change", None)
+            .expect("stash first");
+        fs::write(repo.dir.join("README.md"), "second\n").expect("modify readme");
+        git_repo
+            .stash_save(&sig, "second change", None)
+            .expect("stash second");
+
+        let stashes = backend.list_stashes().expect("should list stashes");
+        assert_eq!(stashes.len(), 2);
+        assert_eq!(stashes[0].0, 0);
+        assert!(stashes[0].1.contains("second change"), "got: {:?}", stashes);
+        assert_eq!(stashes[1].0, 1);
+        assert!(stashes[1].1.contains("first change"), "got: {:?}", stashes);
+
+        let diff = backend.get_stash_diff(1).expect("should get stash diff");
+        assert!(diff.contains("+first"), "got: {}", diff);
+        assert!(matches!(
+            backend.get_stash_diff(5),
+            Err(VcsError::InvalidRef(_))
+        ));
+    }
+
+    #[test]
+    fn test_have_diverged() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        git(&repo.dir, &["tag", "base"]);
+        git(&repo.dir, &["branch", "side"]);
+
+        fs::write(repo.dir.join("a.txt"), "a\n").expect("write a");
+        git(&repo.dir, &["add", "a.txt"]);
+        git(&repo.dir, &["commit", "-m", "a"]);
+        git(&repo.dir, &["tag", "a"]);
+
+        git(&repo.dir, &["checkout", "side"]);
+        fs::write(repo.dir.join("b.txt"), "b\n").expect("write b");
+        git(&repo.dir, &["add", "b.txt"]);
+        git(&repo.dir, &["commit", "-m", "b"]);
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert!(backend.have_diverged("a", "HEAD").unwrap());
+        assert!(!backend.have_diverged("base", "HEAD").unwrap());
+        assert!(!backend.have_diverged("HEAD", "HEAD").unwrap());
+    }
+
+    #[test]
+    fn test_preview_revert_is_inverse_of_commit() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("README.md"), "goodbye\n").expect("modify readme");
+        git(&repo.dir, &["add", "README.md"]);
+        git(&repo.dir, &["commit", "-m", "change greeting"]);
+        fs::write(repo.dir.join("other.txt"), "x\n").expect("write other");
+        git(&repo.dir, &["add", "other.txt"]);
+        git(&repo.dir, &["commit", "-m", "unrelated"]);
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let preview = backend
+            .preview_revert("HEAD~1")
+            .expect("clean revert should preview");
+
+        assert!(preview.contains("-goodbye"), "got: {}", preview);
+        assert!(preview.contains("+hello"), "got: {}", preview);
+        assert!(!preview.contains("other.txt"), "got: {}", preview);
+
+        // Working tree is untouched
+        assert_eq!(
+            fs::read_to_string(repo.dir.join("README.md")).unwrap(),
+            "goodbye\n"
+        );
+    }
+
+    #[test]
+    fn test_preview_revert_reports_conflicts() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("README.md"), "second\n").expect("modify readme");
+        git(&repo.dir, &["add", "README.md"]);
+        git(&repo.dir, &["commit", "-m", "second"]);
+        fs::write(repo.dir.join("README.md"), "third\n").expect("modify readme");
+        git(&repo.dir, &["add", "README.md"]);
+        git(&repo.dir, &["commit", "-m", "third"]);
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        match backend.preview_revert("HEAD~1") {
+            Err(VcsError::Conflict(paths)) => assert_eq!(paths, vec!["README.md"]),
+            other => panic!("expected conflict, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_git_capabilities() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let caps = backend.capabilities();
+        assert!(!caps.change_ids);
+        assert!(caps.stash);
+        assert!(caps.signing);
+        assert!(caps.partial_commit);
+    }
+
+    #[test]
+    fn test_working_snapshot_diff_includes_staged_and_unstaged() {
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("staged.txt"), "staged\n").expect("write staged");
+        crate::vcs::test_utils::git(&repo.dir, &["add", "staged.txt"]);
+        fs::write(repo.dir.join("README.md"), "unstaged\n").expect("modify readme");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let diff = backend
+            .working_snapshot_diff()
+            .expect("should get snapshot diff");
+
+        assert!(diff.contains("+staged"), "staged edit missing: {}", diff);
+        assert!(
+            diff.contains("+unstaged"),
+            "unstaged edit missing: {}",
+            diff
+        );
+    }
+
+    #[test]
+    fn test_diff_fingerprint_is_stable_and_tracks_changes() {
+        use sha2::{Digest, Sha256};
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        let first = backend.diff_fingerprint("HEAD").expect("fingerprint");
+        assert_eq!(first.len(), 64);
+        assert_eq!(first, backend.diff_fingerprint("HEAD").unwrap());
+
+        // Matches the diff get_commit returns
+        let diff = backend.get_commit("HEAD").unwrap().diff;
+        assert_eq!(first, format!("{:x}", Sha256::digest(diff.as_bytes())));
+
+        fs::write(repo.dir.join("README.md"), "changed\n").expect("modify readme");
+        crate::vcs::test_utils::git(&repo.dir, &["add", "README.md"]);
+        crate::vcs::test_utils::git(&repo.dir, &["commit", "-m", "change"]);
+        assert_ne!(first, backend.diff_fingerprint("HEAD").unwrap());
+    }
+
+    #[test]
+    fn test_get_commits_in_range_first_parent_skips_side_branch() {
+        let repo = RepoGuard::new();
+        let git_repo = Repository::open(&repo.dir).expect("open repo");
+        let sig = Signature::now("Test User", "test@example.com").expect("signature");
+        let base = git_repo.head().unwrap().peel_to_commit().unwrap();
+
+        let base_tree = base.tree().unwrap();
+        let side_tree = tree_with(&git_repo, &base_tree, &[("side.txt", "side\n")]);
+        let side = git_repo
+            .commit(None, &sig, &sig, "side work", &side_tree, &[&base])
+            .unwrap();
+        let main_tree = tree_with(&git_repo, &base_tree, &[("main.txt", "main\n")]);
+        let main = git_repo
+            .commit(None, &sig, &sig, "main work", &main_tree, &[&base])
+            .unwrap();
+        let merge_tree = tree_with(&git_repo, &main_tree, &[("side.txt", "side\n")]);
+        let merge = git_repo
+            .commit(
+                None,
+                &sig,
+                &sig,
+                "merge side",
+                &merge_tree,
+                &[
+                    &git_repo.find_commit(main).unwrap(),
+                    &git_repo.find_commit(side).unwrap(),
+                ],
+            )
+            .unwrap();
+        git_repo
+            .reference("refs/tags/tip", merge, false, "test")
+            .unwrap();
+
+        let base_sha = base.id().to_string();
+        let summaries = |backend: &GitBackend| -> Vec<String> {
+            backend
+                .get_commits_in_range(&base_sha, "tip")
+                .unwrap()
+                .into_iter()
+                .map(|c| c.summary)
+                .collect()
+        };
+
+        let all = summaries(&GitBackend::from_cwd().unwrap());
+        assert!(all.contains(&"side work".to_string()), "got: {:?}", all);
+
+        let mainline = summaries(&GitBackend::from_cwd().unwrap().with_first_parent(true));
+        assert_eq!(mainline, vec!["main work", "merge side"]);
+    }
+
+    #[test]
+    fn test_get_commits_in_range_is_topological() {
+        let repo = RepoGuard::new();
+        let git_repo = Repository::open(&repo.dir).expect("open repo");
+        let base = git_repo.head().unwrap().peel_to_commit().unwrap();
+        let base_tree = base.tree().unwrap();
+        // Timestamps deliberately disagree with ancestry
+        let sig_at =
+            |secs| Signature::new("Test User", "test@example.com", &Time::new(secs, 0)).unwrap();
+
+        let a_tree = tree_with(&git_repo, &base_tree, &[("a.txt", "a\n")]);
+        let a = git_repo
+            .commit(None, &sig_at(3_000), &sig_at(3_000), "a", &a_tree, &[&base])
+            .unwrap();
+        let a = git_repo.find_commit(a).unwrap();
+        let b_tree = tree_with(&git_repo, &a_tree, &[("b.txt", "b\n")]);
+        let b = git_repo
+            .commit(None, &sig_at(1_000), &sig_at(1_000), "b", &b_tree, &[&a])
+            .unwrap();
+        let b = git_repo.find_commit(b).unwrap();
+        let c_tree = tree_with(&git_repo, &base_tree, &[("c.txt", "c\n")]);
+        let c = git_repo
+            .commit(None, &sig_at(2_000), &sig_at(2_000), "c", &c_tree, &[&base])
+            .unwrap();
+        let c = git_repo.find_commit(c).unwrap();
+        let m_tree = tree_with(&git_repo, &b_tree, &[("c.txt", "c\n")]);
+        let m = git_repo
+            .commit(
+                None,
+                &sig_at(500),
+                &sig_at(500),
+                "merge",
+                &m_tree,
+                &[&b, &c],
+            )
+            .unwrap();
+        git_repo
+            .reference("refs/tags/tip", m, false, "test")
+            .unwrap();
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let commits = backend
+            .get_commits_in_range(&base.id().to_string(), "tip")
+            .expect("should list commits");
+        let order: Vec<&str> = commits.iter().map(|c| c.commit_id.as_str()).collect();
+        assert_eq!(order.len(), 4, "got: {:?}", order);
+
+        for (pos, id) in order.iter().enumerate() {
+            let commit = git_repo
+                .find_commit(git2::Oid::from_str(id).unwrap())
+                .unwrap();
+            for parent in commit.parent_ids() {
+                if let Some(parent_pos) = order.iter().position(|o| *o == parent.to_string()) {
+                    assert!(parent_pos < pos, "parent after child in {:?}", order);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_changed_files_with_status() {
+        use crate::vcs::test_utils::git;
+        use git2::Delta;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        let body = "line one\nline two\nline three\nline four\n";
+        fs::write(repo.dir.join("gone.txt"), "bye\n").expect("write gone");
+        fs::write(repo.dir.join("old_name.txt"), body).expect("write old_name");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "setup"]);
+
+        fs::write(repo.dir.join("new.txt"), "new\n").expect("write new");
+        fs::write(repo.dir.join("README.md"), "changed\n").expect("modify readme");
+        fs::remove_file(repo.dir.join("gone.txt")).expect("delete gone");
+        fs::rename(repo.dir.join("old_name.txt"), repo.dir.join("new_name.txt")).expect("rename");
+        let git_repo = Repository::open(&repo.dir).expect("open repo");
+        let mut index = git_repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.update_all(["*"].iter(), None).unwrap();
+        index.write().unwrap();
+        git(&repo.dir, &["commit", "-m", "changes"]);
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let mut files = backend
+            .get_changed_files_with_status("HEAD")
+            .expect("should get changed files");
+        files.sort_by_key(|f| f.new_path.clone().or(f.old_path.clone()));
+
+        let summary: Vec<(Delta, Option<&str>, Option<&str>)> = files
+            .iter()
+            .map(|f| (f.status, f.old_path.as_deref(), f.new_path.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Delta::Modified, Some("README.md"), Some("README.md")),
+                (Delta::Deleted, Some("gone.txt"), None),
+                (Delta::Added, None, Some("new.txt")),
+                (Delta::Renamed, Some("old_name.txt"), Some("new_name.txt")),
+            ]
+        );
+
+        // Without rename detection the rename is a delete plus an add
+        let files = backend
+            .with_rename_detection(None)
+            .get_changed_files_with_status("HEAD")
+            .expect("should get changed files");
+        assert!(files.iter().all(|f| f.status != Delta::Renamed));
+        assert!(files
+            .iter()
+            .any(|f| f.status == Delta::Deleted && f.old_path.as_deref() == Some("old_name.txt")));
+    }
+
+    #[test]
+    fn test_max_files_truncates_diff() {
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        for i in 0..10 {
+            fs::write(repo.dir.join(format!("file{}.txt", i)), format!("{}\n", i))
+                .expect("write file");
+        }
+        crate::vcs::test_utils::git(&repo.dir, &["add", "."]);
+        crate::vcs::test_utils::git(&repo.dir, &["commit", "-m", "ten files"]);
+
+        let backend = GitBackend::from_cwd()
+            .expect("should open repo")
+            .with_max_files(Some(3));
+        let diff = backend.get_commit("HEAD").expect("should get commit").diff;
+
+        assert_eq!(diff.matches("diff --git ").count(), 3, "got: {}", diff);
+        assert!(diff.contains("file0.txt") && diff.contains("file2.txt"));
+        assert!(!diff.contains("file3.txt"));
+        assert!(diff.ends_with("... and 7 more files\n"), "got: {}", diff);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_textconv_driver_converts_content() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        git(&repo.dir, &["config", "diff.swap.textconv", "tr a b <"]);
+        fs::write(repo.dir.join(".gitattributes"), "*.dat diff=swap\n").expect("write attrs");
+        fs::write(repo.dir.join("data.dat"), "aaa\n").expect("write data");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "add data"]);
+        fs::write(repo.dir.join("data.dat"), "xaa\n").expect("modify data");
+        git(&repo.dir, &["add", "data.dat"]);
+        git(&repo.dir, &["commit", "-m", "change data"]);
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let diff = backend.get_commit("HEAD").expect("should get commit").diff;
+
+        assert!(
+            diff.contains("diff --git a/data.dat b/data.dat"),
+            "got: {}",
+            diff
+        );
+        assert!(
+            diff.contains("-bbb"),
+            "converted old content, got: {}",
+            diff
+        );
+        assert!(
+            diff.contains("+xbb"),
+            "converted new content, got: {}",
+            diff
+        );
+        assert!(!diff.contains("+xaa"), "raw content should be replaced");
+    }
+
+    #[test]
+    fn test_head_sha() {
+        let repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert_eq!(
+            backend.head_sha().unwrap(),
+            Some(backend.resolve_ref("HEAD").unwrap())
+        );
+
+        let fresh = crate::vcs::test_utils::make_temp_dir("git-head-sha");
+        Repository::init(&fresh).expect("init repo");
+        let fresh_backend = GitBackend::new(&fresh).expect("should open fresh repo");
+        assert_eq!(fresh_backend.head_sha().unwrap(), None);
+
+        drop(repo);
+        let _ = std::fs::remove_dir_all(&fresh);
+    }
+
+    #[test]
+    fn test_fzf_log_time_source() {
+        let repo = RepoGuard::new();
+        let git_repo = Repository::open(&repo.dir).expect("open repo");
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let author = Signature::new("A", "a@example.com", &Time::new(now - 3 * 86_400, 0)).unwrap();
+        let committer =
+            Signature::new("C", "c@example.com", &Time::new(now - 2 * 3_600, 0)).unwrap();
+        let parent = git_repo.head().unwrap().peel_to_commit().unwrap();
+        let tree = parent.tree().unwrap();
+        git_repo
+            .commit(
+                Some("HEAD"),
+                &author,
+                &committer,
+                "rebased",
+                &tree,
+                &[&parent],
+            )
+            .unwrap();
+
+        let by_committer = GitBackend::from_cwd()
+            .unwrap()
+            .get_commit_log_for_fzf()
+            .unwrap();
+        let line = by_committer
+            .lines()
+            .find(|l| l.contains("rebased"))
+            .unwrap();
+        assert!(line.contains("2 hours ago"), "got: {}", line);
+
+        let by_author = GitBackend::from_cwd()
+            .unwrap()
+            .with_committer_time(false)
+            .get_commit_log_for_fzf()
+            .unwrap();
+        let line = by_author.lines().find(|l| l.contains("rebased")).unwrap();
+        assert!(line.contains("3 days ago"), "got: {}", line);
+    }
+
+    #[test]
+    fn test_get_pending_diff_includes_all_changes() {
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("staged.txt"), "staged\n").expect("write staged");
+        crate::vcs::test_utils::git(&repo.dir, &["add", "staged.txt"]);
+        fs::write(repo.dir.join("README.md"), "unstaged\n").expect("modify readme");
+        fs::create_dir_all(repo.dir.join("new_dir")).expect("create dir");
+        fs::write(repo.dir.join("new_dir/untracked.txt"), "untracked\n").expect("write untracked");
+        fs::write(repo.dir.join("Cargo.lock"), "lock\n").expect("write lock");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let diff = backend.get_pending_diff().expect("should get pending diff");
+
+        assert!(diff.contains("+staged"), "staged missing: {}", diff);
+        assert!(diff.contains("+unstaged"), "unstaged missing: {}", diff);
+        assert!(diff.contains("+untracked"), "untracked missing: {}", diff);
+        assert!(!diff.contains("Cargo.lock"), "exclusions should apply");
+    }
+
+    #[test]
+    fn test_per_file_max_lines_truncates_only_large_file() {
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        let huge: String = (0..100).map(|i| format!("line {}\n", i)).collect();
+        fs::write(repo.dir.join("huge.txt"), huge).expect("write huge");
+        fs::write(repo.dir.join("small_a.txt"), "a\n").expect("write a");
+        fs::write(repo.dir.join("small_b.txt"), "b\n").expect("write b");
+        crate::vcs::test_utils::git(&repo.dir, &["add", "."]);
+        crate::vcs::test_utils::git(&repo.dir, &["commit", "-m", "files"]);
+
+        let backend = GitBackend::from_cwd()
+            .expect("should open repo")
+            .with_per_file_max_lines(Some(10));
+        let diff = backend.get_commit("HEAD").expect("should get commit").diff;
+
+        assert_eq!(diff.matches("... [file truncated] ...").count(), 1);
+        assert!(diff.contains("+line 9\n"));
+        assert!(!diff.contains("+line 10\n"), "got: {}", diff);
+        assert!(diff.contains("+a\n") && diff.contains("+b\n"));
+    }
+
+    #[test]
+    fn test_get_remotes() {
+        let repo = RepoGuard::new();
+        let git_repo = Repository::open(&repo.dir).expect("open repo");
+        git_repo
+            .remote("origin", "https://example.com/me/project.git")
+            .unwrap();
+        git_repo
+            .remote("upstream", "https://example.com/org/project.git")
+            .unwrap();
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let mut remotes = backend.get_remotes().expect("should list remotes");
+        remotes.sort();
+        assert_eq!(
+            remotes,
+            vec![
+                (
+                    "origin".to_string(),
+                    Some("https://example.com/me/project.git".to_string())
+                ),
+                (
+                    "upstream".to_string(),
+                    Some("https://example.com/org/project.git".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_commit_log_plain_has_no_ansi() {
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let log = backend.get_commit_log_plain().expect("should get log");
+        assert!(!log.contains('\x1b'), "got: {:?}", log);
+        assert!(log.contains(" init "), "got: {:?}", log);
+    }
+
+    #[test]
+    fn test_change_id_trailer_is_surfaced() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        git(&repo.dir, &["tag", "base"]);
+        fs::write(repo.dir.join("a.txt"), "a\n").expect("write a");
+        git(&repo.dir, &["add", "a.txt"]);
+        git(
+            &repo.dir,
+            &[
+                "commit",
+                "-m",
+                "Add a\n\nDetails.\n\nChange-Id: I0123456789abcdef0123456789abcdef01234567\n",
+            ],
+        );
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let expected = Some("I0123456789abcdef0123456789abcdef01234567".to_string());
+        assert_eq!(backend.get_commit("HEAD").unwrap().change_id, expected);
+        assert_eq!(
+            backend.get_commits_in_range("base", "HEAD").unwrap()[0].change_id,
+            expected
+        );
+        assert_eq!(backend.get_commit("base").unwrap().change_id, None);
+    }
+
+    #[test]
+    fn test_get_commit_diff_grep_keeps_matching_hunks() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        let lines: Vec<String> = (0..20).map(|i| format!("line {}", i)).collect();
+        fs::write(repo.dir.join("code.txt"), lines.join("\n") + "\n").expect("write code");
+        fs::write(repo.dir.join("other.txt"), "other\n").expect("write other");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "setup"]);
+
+        let mut changed = lines.clone();
+        changed[1] = "retry_count += 1".to_string();
+        changed[18] = "log(done)".to_string();
+        fs::write(repo.dir.join("code.txt"), changed.join("\n") + "\n").expect("modify code");
+        fs::write(repo.dir.join("other.txt"), "unrelated\n").expect("modify other");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "changes"]);
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let diff = backend
+            .get_commit_diff_grep("HEAD", r"retry_\w+")
+            .expect("should grep diff");
+
+        assert!(diff.contains("+++ b/code.txt"), "got: {}", diff);
+        assert_eq!(diff.matches("@@ -").count(), 1, "got: {}", diff);
+        assert!(diff.contains("+retry_count += 1"));
+        assert!(!diff.contains("log(done)"));
+        assert!(!diff.contains("other.txt"));
+
+        assert!(backend.get_commit_diff_grep("HEAD", "(").is_err());
+
+        // Output options don't change which hunks match
+        let backend = backend.with_line_numbers(true);
+        let diff = backend
+            .get_commit_diff_grep("HEAD", r"^retry_\w+")
+            .expect("should grep diff");
+        assert_eq!(diff.matches("@@ -").count(), 1, "got: {}", diff);
+        assert!(diff.contains("+retry_count += 1"), "got: {}", diff);
+        assert!(!diff.contains("log(done)"), "got: {}", diff);
+    }
+
+    #[test]
+    fn test_get_working_tree_diff_for_paths() {
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("a.txt"), "a\n").expect("write a");
+        fs::write(repo.dir.join("b.txt"), "b\n").expect("write b");
+        crate::vcs::test_utils::git(&repo.dir, &["add", "."]);
+        crate::vcs::test_utils::git(&repo.dir, &["commit", "-m", "files"]);
+
+        fs::write(repo.dir.join("a.txt"), "a2\n").expect("modify a");
+        fs::write(repo.dir.join("b.txt"), "b2\n").expect("modify b");
+        fs::write(repo.dir.join("README.md"), "readme2\n").expect("modify readme");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let diff = backend
+            .get_working_tree_diff_for(&[Path::new("a.txt"), Path::new("README.md")], false)
+            .expect("should get scoped diff");
+
+        assert!(diff.contains("+a2"), "got: {}", diff);
+        assert!(diff.contains("+readme2"), "got: {}", diff);
+        assert!(!diff.contains("b.txt"), "got: {}", diff);
+    }
+
+    #[test]
+    fn test_open_repository_honors_git_dir_override() {
+        use crate::vcs::test_utils::make_temp_dir;
+
+        let _repo = RepoGuard::new();
+        // A separate repo elsewhere, as GIT_DIR would point to
+        let other = make_temp_dir("git-dir-override");
+        let other_repo = Repository::init(&other).expect("init other repo");
+        let sig = Signature::now("Other", "other@example.com").unwrap();
+        let tree = other_repo
+            .find_tree(other_repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        other_repo
+            .commit(Some("HEAD"), &sig, &sig, "other repo", &tree, &[])
+            .unwrap();
+
+        let repo =
+            GitBackend::open_repository(Path::new("."), Some(&other.join(".git")), Some(&other))
+                .expect("should open GIT_DIR repo");
+        assert_eq!(
+            repo.workdir().map(|p| p.canonicalize().unwrap()),
+            Some(other.canonicalize().unwrap())
+        );
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.summary(), Some("other repo"));
+
+        assert!(matches!(
+            GitBackend::open_repository(Path::new("."), Some(&other.join("missing")), None),
+            Err(VcsError::NotARepository)
+        ));
+
+        let _ = std::fs::remove_dir_all(&other);
+    }
+
+    #[test]
+    fn test_get_conflict_details_returns_all_sides() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        let git_repo = Repository::open(&repo.dir).expect("open repo");
+        let base = git_repo.head().unwrap().peel_to_commit().unwrap();
+        let sig = Signature::now("Test User", "test@example.com").unwrap();
+        let theirs_tree = tree_with(
+            &git_repo,
+            &base.tree().unwrap(),
+            &[("README.md", "theirs\n")],
+        );
+        let theirs = git_repo
+            .commit(None, &sig, &sig, "theirs", &theirs_tree, &[&base])
+            .unwrap();
+
+        fs::write(repo.dir.join("README.md"), "ours\n").expect("modify readme");
+        git(&repo.dir, &["add", "README.md"]);
+        git(&repo.dir, &["commit", "-m", "ours"]);
+
+        let annotated = git_repo.find_annotated_commit(theirs).unwrap();
+        git_repo.merge(&[&annotated], None, None).unwrap();
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let details = backend
+            .get_conflict_details(Path::new("README.md"))
+            .expect("should get conflict details");
+        assert_eq!(
+            details,
+            ConflictDetails {
+                ancestor: Some("hello\n".to_string()),
+                ours: Some("ours\n".to_string()),
+                theirs: Some("theirs\n".to_string()),
+            }
+        );
+
+        assert!(backend
+            .get_conflict_details(Path::new("other.txt"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_coarse_time_buckets() {
+        const HOUR: i64 = 60 * 60;
+        const DAY: i64 = 24 * HOUR;
+        let coarse = TimeGranularity::Coarse;
+        assert_eq!(coarse.format(-5), "today");
+        assert_eq!(coarse.format(3 * HOUR), "today");
+        assert_eq!(coarse.format(DAY + HOUR), "yesterday");
+        assert_eq!(coarse.format(4 * DAY), "this week");
+        assert_eq!(coarse.format(20 * DAY), "this month");
+        assert_eq!(coarse.format(90 * DAY), "older");
+        assert_eq!(TimeGranularity::default().format(3 * HOUR), "3 hours ago");
+    }
+
+    #[test]
+    fn test_line_numbers_follow_hunk_header() {
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        let lines: Vec<String> = (1..=10).map(|i| format!("line {}", i)).collect();
+        fs::write(repo.dir.join("nums.txt"), lines.join("\n") + "\n").expect("write nums");
+        crate::vcs::test_utils::git(&repo.dir, &["add", "."]);
+        crate::vcs::test_utils::git(&repo.dir, &["commit", "-m", "nums"]);
+
+        let mut changed = lines.clone();
+        changed[5] = "six".to_string();
+        fs::write(repo.dir.join("nums.txt"), changed.join("\n") + "\n").expect("modify nums");
+
+        let backend = GitBackend::from_cwd()
+            .expect("should open repo")
+            .with_line_numbers(true);
+        let diff = backend
+            .get_working_tree_diff(false)
+            .expect("should get diff");
+
+        assert!(diff.contains("@@ -3,7 +3,7 @@"), "got: {}", diff);
+        assert!(diff.contains("\n   3    3  line 3\n"), "got: {}", diff);
+        assert!(diff.contains("\n   6      -line 6\n"), "got: {}", diff);
+        assert!(diff.contains("\n        6 +six\n"), "got: {}", diff);
+        assert!(diff.contains("\n   9    9  line 9\n"), "got: {}", diff);
+    }
+
+    #[test]
+    fn test_stage_files_reports_missing_path() {
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("new.txt"), "new\n").expect("write new");
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        match backend.stage_files(&[Path::new("new.txt"), Path::new("typo.txt")]) {
+            Err(VcsError::FileNotFound(path)) => assert_eq!(path, "typo.txt"),
+            other => panic!("expected FileNotFound, got: {:?}", other),
+        }
+        // Nothing was staged
+        assert_eq!(backend.get_working_tree_diff(true).unwrap(), "");
+
+        let staged = backend
+            .stage_files(&[Path::new("new.txt")])
+            .expect("should stage");
+        assert_eq!(staged, vec![PathBuf::from("new.txt")]);
+
+        // A deleted tracked file stages as a deletion
+        fs::remove_file(repo.dir.join("README.md")).expect("delete readme");
+        backend
+            .stage_files(&[Path::new("README.md")])
+            .expect("should stage deletion");
+        let staged_diff = backend.get_working_tree_diff(true).unwrap();
+        assert!(
+            staged_diff.contains("deleted file mode"),
+            "got: {}",
+            staged_diff
+        );
+    }
+
+    #[test]
+    fn test_commit_from_file_strips_comments() {
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("new.txt"), "new\n").expect("write new");
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        backend.stage_files(&[Path::new("new.txt")]).unwrap();
+
+        let msg_path = repo.dir.join("MSG");
+        fs::write(
+            &msg_path,
+            "# Please enter a message\nAdd new file\n\nBody text.\n# trailing comment\n",
+        )
+        .expect("write message");
+        backend
+            .commit_from_file(&msg_path)
+            .expect("should commit from file");
+
+        let info = backend.get_commit("HEAD").unwrap();
+        assert_eq!(info.message, "Add new file\n\nBody text.");
+
+        fs::write(&msg_path, "# only comments\n\n").expect("write message");
+        assert!(backend.commit_from_file(&msg_path).is_err());
+    }
+
+    #[test]
+    fn test_ref_kind() {
+        use crate::vcs::test_utils::git;
+
+        let repo = RepoGuard::new();
+        git(&repo.dir, &["branch", "feature"]);
+        git(&repo.dir, &["tag", "v1.0"]);
+        let git_repo = Repository::open(&repo.dir).expect("open repo");
+        let head = git_repo.head().unwrap().target().unwrap();
+        git_repo
+            .reference("refs/remotes/origin/main", head, false, "test")
+            .unwrap();
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert_eq!(backend.ref_kind("feature").unwrap(), RefKind::Branch);
+        assert_eq!(backend.ref_kind("v1.0").unwrap(), RefKind::Tag);
+        assert_eq!(
+            backend.ref_kind("origin/main").unwrap(),
+            RefKind::RemoteBranch
+        );
+        assert_eq!(
+            backend.ref_kind(&head.to_string()).unwrap(),
+            RefKind::Commit
+        );
+        assert_eq!(backend.ref_kind("HEAD~0").unwrap(), RefKind::Commit);
+        assert!(matches!(
+            backend.ref_kind("nope"),
+            Err(VcsError::InvalidRef(_))
+        ));
+    }
+
+    #[test]
+    fn test_get_range_per_commit_diff_with_headers() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        git(&repo.dir, &["tag", "base"]);
+        fs::write(repo.dir.join("a.txt"), "a\n").expect("write a");
+        git(&repo.dir, &["add", "a.txt"]);
+        git(&repo.dir, &["commit", "-m", "Add a"]);
+        fs::write(repo.dir.join("b.txt"), "b\n").expect("write b");
+        git(&repo.dir, &["add", "b.txt"]);
+        git(&repo.dir, &["commit", "-m", "Add b"]);
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let commits = backend.get_commits_in_range("base", "HEAD").unwrap();
+        let diff = backend
+            .get_range_per_commit_diff("base", "HEAD", true)
+            .expect("should get per-commit diff");
+
+        let header_a = format!("# {}: Add a\n", commits[0].short_id);
+        let header_b = format!("# {}: Add b\n", commits[1].short_id);
+        assert!(diff.starts_with(&header_a), "got: {}", diff);
+        let b_pos = diff.find(&header_b).expect("second header present");
+        assert!(diff[..b_pos].contains("+++ b/a.txt"));
+        assert!(diff[b_pos..].contains("+++ b/b.txt"));
+        assert!(!diff[b_pos..].contains("a.txt"));
+
+        let plain = backend
+            .get_range_per_commit_diff("base", "HEAD", false)
+            .unwrap();
+        assert!(!plain.contains("# "), "got: {}", plain);
+    }
+
+    #[test]
+    fn test_long_lines_are_summarized() {
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        let minified = "x".repeat(5000);
+        fs::write(
+            repo.dir.join("app.min.js"),
+            format!("{}\nshort\n", minified),
+        )
+        .expect("write minified");
+        crate::vcs::test_utils::git(&repo.dir, &["add", "."]);
+        crate::vcs::test_utils::git(&repo.dir, &["commit", "-m", "minified"]);
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let diff = backend.get_commit("HEAD").unwrap().diff;
+        assert!(diff.contains("+<long line: 5000 chars>\n"), "got: {}", diff);
+        assert!(diff.contains("+short\n"));
+        assert!(!diff.contains(&minified));
+
+        let full = GitBackend::from_cwd()
+            .unwrap()
+            .with_long_line_threshold(None)
+            .get_commit("HEAD")
+            .unwrap()
+            .diff;
+        assert!(full.contains(&minified));
+    }
+
+    #[test]
+    fn test_get_tree_lists_entries_for_tree_ish() {
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        fs::create_dir_all(repo.dir.join("src")).expect("create src");
+        fs::write(repo.dir.join("src/lib.rs"), "// lib\n").expect("write lib");
+        crate::vcs::test_utils::git(&repo.dir, &["add", "."]);
+        crate::vcs::test_utils::git(&repo.dir, &["commit", "-m", "add src"]);
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let entries = backend.get_tree("HEAD^{tree}").expect("should list tree");
+        let summary: Vec<(&str, Option<git2::ObjectType>)> =
+            entries.iter().map(|e| (e.name.as_str(), e.kind)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("README.md", Some(git2::ObjectType::Blob)),
+                ("src", Some(git2::ObjectType::Tree)),
+            ]
+        );
+
+        // get_commit still rejects non-commits
+        assert!(backend.get_commit("HEAD^{tree}").is_err());
+    }
+
+    #[test]
+    fn test_get_commit_diff_base64_round_trips() {
+        use base64::Engine;
+
+        let _repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let encoded = backend
+            .get_commit_diff_base64("HEAD")
+            .expect("should encode diff");
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .expect("valid base64");
+        assert_eq!(
+            String::from_utf8(decoded).unwrap(),
+            backend.get_commit("HEAD").unwrap().diff
+        );
+    }
+
+    #[test]
+    fn test_commit_uses_env_identity_without_config() {
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        {
+            let git_repo = Repository::open(&repo.dir).expect("open repo");
+            let mut config = git_repo
+                .config()
+                .unwrap()
+                .open_level(git2::ConfigLevel::Local)
+                .unwrap();
+            config.remove("user.name").unwrap();
+            config.remove("user.email").unwrap();
+        }
+
+        // RepoGuard holds the cwd lock, so no other commit test sees these
+        let vars = [
+            ("GIT_AUTHOR_NAME", "Env Author"),
+            ("GIT_AUTHOR_EMAIL", "author@ci.example"),
+            ("GIT_COMMITTER_NAME", "Env Committer"),
+            ("GIT_COMMITTER_EMAIL", "committer@ci.example"),
+        ];
+        for (key, value) in vars {
+            std::env::set_var(key, value);
+        }
+
+        fs::write(repo.dir.join("ci.txt"), "ci\n").expect("write file");
+        let backend = GitBackend::from_cwd()
+            .expect("should open repo")
+            .with_mailmap(false);
+        backend.stage_files(&[Path::new("ci.txt")]).unwrap();
+        let result = backend.commit("CI commit");
+
+        for (key, _) in vars {
+            std::env::remove_var(key);
+        }
+
+        result.expect("commit should use env identity");
+        let info = backend.get_commit("HEAD").unwrap();
+        assert_eq!(info.author, "Env Author <author@ci.example>");
+        assert_eq!(info.committer, "Env Committer <committer@ci.example>");
+    }
+
+    #[test]
+    fn test_diff_stash_against_index() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        let mut git_repo = Repository::open(&repo.dir).expect("open repo");
+        let sig = Signature::now("Test User", "test@example.com").unwrap();
+        fs::write(repo.dir.join("README.md"), "stashed\n").expect("modify readme");
+        git_repo.stash_save(&sig, "wip", None).expect("stash");
+
+        // Staging an unrelated file leaves the stash applicable
+        fs::write(repo.dir.join("other.txt"), "other\n").expect("write other");
+        git(&repo.dir, &["add", "other.txt"]);
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let clean = backend.diff_stash_against_index(0).expect("should compare");
+        assert!(!clean.conflicts);
+        assert!(clean.diff.contains("+stashed"), "got: {}", clean.diff);
+
+        // Staging a competing edit to the same line conflicts
+        fs::write(repo.dir.join("README.md"), "staged\n").expect("modify readme");
+        git(&repo.dir, &["add", "README.md"]);
+        let conflicting = backend.diff_stash_against_index(0).expect("should compare");
+        assert!(conflicting.conflicts);
+        assert!(
+            conflicting.diff.contains("-staged"),
+            "got: {}",
+            conflicting.diff
+        );
+        assert!(
+            conflicting.diff.contains("+stashed"),
+            "got: {}",
+            conflicting.diff
+        );
+    }
+
+    #[test]
+    fn test_walk_commits_in_range_stops_early() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        for i in 0..3 {
+            fs::write(repo.dir.join(format!("f{}.txt", i)), "x\n").expect("write file");
+            git(&repo.dir, &["add", "."]);
+            git(&repo.dir, &["commit", "-m", &format!("commit {}", i)]);
+        }
+        let backend = GitBackend::from_cwd().expect("should open repo");
+
+        let all = backend
+            .get_commits_in_range("HEAD~3", "HEAD")
+            .expect("should list commits");
+        let mut walked = Vec::new();
+        backend
+            .walk_commits_in_range("HEAD~3", "HEAD", &mut |commit| {
+                walked.push(commit.commit_id);
+                true
             })
-            .collect())
+            .expect("should walk");
+        assert_eq!(
+            walked,
+            all.iter().map(|c| c.commit_id.clone()).collect::<Vec<_>>()
+        );
+
+        let mut visited = 0;
+        backend
+            .walk_commits_in_range("HEAD~3", "HEAD", &mut |_| {
+                visited += 1;
+                visited < 2
+            })
+            .expect("should walk");
+        assert_eq!(visited, 2);
     }
 
-    fn get_file_content_at_ref(&self, reference: &str, path: &Path) -> Result<String, VcsError> {
-        let reference = reference.trim();
-        Self::validate_ref_format(reference)?;
+    #[test]
+    fn test_blame_range_attributes_lines() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-        // Resolve reference to commit
-        let obj = self
-            .repo
-            .revparse_single(reference)
-            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
-        let commit = obj
-            .peel_to_commit()
-            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
-        let tree = commit
-            .tree()
-            .map_err(|e| VcsError::Other(format!("failed to get tree: {}", e)))?;
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("lines.txt"), "a\nb\nc\nd\ne\n").expect("write file");
+        git(&repo.dir, &["add", "lines.txt"]);
+        git(&repo.dir, &["commit", "-m", "add lines"]);
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let first = backend.head_sha().expect("should get head").unwrap();
 
-        // Look up file in tree
-        let entry = tree
-            .get_path(path)
-            .map_err(|_| VcsError::FileNotFound(path.display().to_string()))?;
+        fs::write(repo.dir.join("lines.txt"), "a\nb\nc\nD\ne\n").expect("modify file");
+        git(&repo.dir, &["add", "lines.txt"]);
+        git(&repo.dir, &["commit", "-m", "edit line 4"]);
+        let second = backend.head_sha().expect("should get head").unwrap();
 
-        // Get blob content
-        let blob = self
-            .repo
-            .find_blob(entry.id())
-            .map_err(|_| VcsError::FileNotFound(path.display().to_string()))?;
+        let path = Path::new("lines.txt");
+        assert_eq!(
+            backend.blame_range(path, 4, 5).expect("should blame"),
+            vec![(4..5, second.clone()), (5..6, first.clone())]
+        );
+        assert_eq!(
+            backend.blame_range(path, 1, 2).expect("should blame"),
+            vec![(1..3, first)]
+        );
+        assert!(backend.blame_range(path, 3, 2).is_err());
+    }
 
-        Ok(String::from_utf8_lossy(blob.content()).into_owned())
+    #[test]
+    fn test_get_pr_summary() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let base_sha = backend.head_sha().expect("should get head").unwrap();
+
+        git(&repo.dir, &["checkout", "-b", "feature"]);
+        fs::write(repo.dir.join("feature.txt"), "one\ntwo\n").expect("write file");
+        git(&repo.dir, &["add", "feature.txt"]);
+        git(&repo.dir, &["commit", "-m", "add feature"]);
+        fs::write(repo.dir.join("feature.txt"), "one\n2\n").expect("modify file");
+        fs::write(repo.dir.join("Cargo.lock"), "lock\n").expect("write lock");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "tweak feature"]);
+
+        let summary = backend
+            .get_pr_summary(&base_sha, "feature")
+            .expect("should summarize");
+        assert_eq!(summary.merge_base, base_sha);
+        assert_eq!(summary.commits, 2);
+        assert_eq!((summary.insertions, summary.deletions), (2, 0));
+        assert_eq!(
+            summary.files,
+            vec![ChangedFile {
+                status: git2::Delta::Added,
+                old_path: None,
+                new_path: Some("feature.txt".to_string()),
+            }]
+        );
     }
 
-    fn get_current_branch(&self) -> Result<Option<String>, VcsError> {
-        let head = self
-            .repo
-            .head()
-            .map_err(|e| VcsError::Other(format!("failed to get HEAD: {}", e)))?;
+    #[test]
+    fn test_get_pr_summary_follows_rename_settings() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-        if head.is_branch() {
-            Ok(head.shorthand().map(|s| s.to_string()))
-        } else {
-            // Detached HEAD state
-            Ok(None)
-        }
+        let repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let base_sha = backend.head_sha().expect("should get head").unwrap();
+        fs::rename(repo.dir.join("README.md"), repo.dir.join("README")).expect("rename file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "rename readme"]);
+
+        let summary = backend
+            .get_pr_summary(&base_sha, "HEAD")
+            .expect("should summarize");
+        assert_eq!((summary.insertions, summary.deletions), (0, 0));
+        assert_eq!(
+            summary.files,
+            vec![ChangedFile {
+                status: git2::Delta::Renamed,
+                old_path: Some("README.md".to_string()),
+                new_path: Some("README".to_string()),
+            }]
+        );
+
+        let summary = backend
+            .with_rename_detection(None)
+            .get_pr_summary(&base_sha, "HEAD")
+            .expect("should summarize");
+        assert_eq!(summary.files.len(), 2, "files: {:?}", summary.files);
     }
 
-    fn get_commit_log_for_fzf(&self) -> Result<String, VcsError> {
-        let mut revwalk = self
-            .repo
-            .revwalk()
-            .map_err(|e| VcsError::Other(format!("failed to create revwalk: {}", e)))?;
+    #[test]
+    fn test_is_empty_commit() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-        // Start from HEAD
-        revwalk
-            .push_head()
-            .map_err(|e| VcsError::Other(format!("failed to push head: {}", e)))?;
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("file.txt"), "content\n").expect("write file");
+        git(&repo.dir, &["add", "file.txt"]);
+        git(&repo.dir, &["commit", "-m", "normal"]);
+        git(&repo.dir, &["commit", "--allow-empty", "-m", "empty"]);
 
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map(|d| d.as_secs() as i64)
-            .unwrap_or(0);
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert!(backend.is_empty_commit("HEAD").expect("should check"));
+        assert!(!backend.is_empty_commit("HEAD~1").expect("should check"));
+    }
 
-        let mut output = String::new();
-        for oid_result in revwalk {
-            let oid = oid_result.map_err(|e| VcsError::Other(format!("revwalk error: {}", e)))?;
-            let commit = self
-                .repo
-                .find_commit(oid)
-                .map_err(|e| VcsError::Other(format!("failed to find commit: {}", e)))?;
+    #[test]
+    fn test_drop_blank_only_hunks() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-            let short_id = &oid.to_string()[..7];
-            let summary = commit.summary().unwrap_or("");
-            let time_secs = commit.time().seconds();
-            let relative_time = format_relative_time(now - time_secs);
+        let repo = RepoGuard::new();
+        let original: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+        fs::write(repo.dir.join("code.txt"), &original).expect("write file");
+        fs::write(repo.dir.join("spacing.txt"), "a\nb\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "add files"]);
+
+        // A blank line near the top and a real change far below it
+        let modified = original.replacen("line 2\n", "line 2\n\n", 1).replacen(
+            "line 18\n",
+            "line eighteen\n",
+            1,
+        );
+        fs::write(repo.dir.join("code.txt"), modified).expect("modify file");
+        fs::write(repo.dir.join("spacing.txt"), "a\n\n  \nb\n").expect("modify file");
 
-            // Format: short_hash summary relative_time
-            // Using ANSI codes for color (yellow hash, default text, dim time)
-            output.push_str(&format!(
-                "\x1b[33m{}\x1b[0m {} \x1b[90m{}\x1b[0m\n",
-                short_id, summary, relative_time
-            ));
+        let backend = GitBackend::from_cwd()
+            .expect("should open repo")
+            .with_drop_blank_only_hunks(true);
+        let diff = backend
+            .get_working_tree_diff(false)
+            .expect("should get diff");
+        assert_eq!(diff.matches("@@ -").count(), 1, "got: {}", diff);
+        assert!(diff.contains("+line eighteen"), "got: {}", diff);
+        assert!(!diff.contains("spacing.txt"), "got: {}", diff);
+    }
+
+    #[test]
+    fn test_get_changelog_groups_by_type() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let base = backend.head_sha().expect("should get head").unwrap();
+        for (i, subject) in ["feat(api)!: drop v1", "fix: typo", "chore: bump deps"]
+            .iter()
+            .enumerate()
+        {
+            fs::write(repo.dir.join(format!("f{}.txt", i)), "x\n").expect("write file");
+            git(&repo.dir, &["add", "."]);
+            git(&repo.dir, &["commit", "-m", subject]);
         }
 
-        Ok(output)
+        let changelog = backend.get_changelog(&base, "HEAD").expect("should build");
+        let descriptions = |entries: &[crate::vcs::conventional::ChangelogEntry]| {
+            entries
+                .iter()
+                .map(|e| e.description.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(descriptions(&changelog.features), vec!["drop v1"]);
+        assert_eq!(descriptions(&changelog.fixes), vec!["typo"]);
+        assert_eq!(descriptions(&changelog.other), vec!["bump deps"]);
+        assert!(changelog.features[0].breaking);
+        assert_eq!(changelog.breaking().count(), 1);
+        assert_eq!(
+            changelog.other[0].commit_id,
+            backend.head_sha().expect("should get head").unwrap()
+        );
     }
 
-    fn resolve_ref(&self, reference: &str) -> Result<String, VcsError> {
-        let reference = reference.trim();
-        Self::validate_ref_format(reference)?;
-
-        // Use git2 to resolve reference to commit SHA
-        let obj = self
-            .repo
-            .revparse_single(reference)
-            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+    #[test]
+    fn test_get_commits_since_upstream() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-        let commit = obj
-            .peel_to_commit()
-            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+        let repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert!(backend.get_commits_since_upstream().is_err());
+
+        // Upstream at the current commit, then two local commits on top
+        {
+            let git_repo = Repository::open(&repo.dir).expect("open repo");
+            let head = git_repo.head().unwrap();
+            let branch_name = head.shorthand().unwrap().to_string();
+            git_repo
+                .remote("origin", "https://example.com/repo.git")
+                .expect("add remote");
+            git_repo
+                .reference(
+                    &format!("refs/remotes/origin/{}", branch_name),
+                    head.target().unwrap(),
+                    true,
+                    "test fetch",
+                )
+                .expect("create remote-tracking ref");
+            git_repo
+                .find_branch(&branch_name, git2::BranchType::Local)
+                .unwrap()
+                .set_upstream(Some(&format!("origin/{}", branch_name)))
+                .expect("set upstream");
+        }
+        for subject in ["ahead one", "ahead two"] {
+            fs::write(repo.dir.join(format!("{}.txt", subject)), "x\n").expect("write file");
+            git(&repo.dir, &["add", "."]);
+            git(&repo.dir, &["commit", "-m", subject]);
+        }
 
-        Ok(commit.id().to_string())
+        let commits = backend
+            .get_commits_since_upstream()
+            .expect("should list commits");
+        let subjects: Vec<_> = commits.iter().map(|c| c.summary.as_str()).collect();
+        assert_eq!(subjects, vec!["ahead one", "ahead two"]);
     }
 
-    fn get_working_tree_changed_files(&self) -> Result<Vec<String>, VcsError> {
-        use std::collections::HashSet;
+    #[test]
+    fn test_inline_tiny_new_files() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-        let mut opts = StatusOptions::new();
-        opts.include_untracked(true);
-        opts.exclude_submodules(true);
-        opts.include_ignored(false);
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("tiny.toml"), "a = 1\nb = 2\nc = 3\n").expect("write file");
+        let large: String = (1..=10).map(|i| format!("line {}\n", i)).collect();
+        fs::write(repo.dir.join("large.txt"), large).expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "add files"]);
+
+        let backend = GitBackend::from_cwd()
+            .expect("should open repo")
+            .with_inline_new_files(Some(5));
+        let diff = backend.get_commit("HEAD").expect("should get commit").diff;
+        assert!(
+            diff.contains("+++ b/tiny.toml\nNew file tiny.toml:\na = 1\nb = 2\nc = 3\n"),
+            "got: {}",
+            diff
+        );
+        let files = crate::vcs::patch::parse_patch(&diff);
+        let paths: Vec<_> = files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, ["large.txt", "tiny.toml"], "got: {}", diff);
+        assert!(!diff.contains("+a = 1"), "got: {}", diff);
+        assert!(diff.contains("+line 10"), "got: {}", diff);
+        assert!(!diff.contains("New file large.txt"), "got: {}", diff);
+    }
 
-        let statuses = self
-            .repo
-            .statuses(Some(&mut opts))
-            .map_err(|e| VcsError::Other(format!("failed to get status: {}", e)))?;
+    #[test]
+    fn test_workdir_matches() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-        let files: HashSet<String> = statuses
-            .iter()
-            .filter_map(|s| s.path().map(String::from))
-            .collect();
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("file.txt"), "one\n").expect("write file");
+        git(&repo.dir, &["add", "file.txt"]);
+        git(&repo.dir, &["commit", "-m", "add file"]);
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert!(backend.workdir_matches("HEAD").expect("should compare"));
+        assert!(!backend.workdir_matches("HEAD~1").expect("should compare"));
 
-        Ok(files.into_iter().collect())
+        fs::write(repo.dir.join("file.txt"), "two\n").expect("modify file");
+        assert!(!backend.workdir_matches("HEAD").expect("should compare"));
     }
 
-    fn get_merge_base(&self, ref1: &str, ref2: &str) -> Result<String, VcsError> {
-        let ref1 = ref1.trim();
-        let ref2 = ref2.trim();
+    #[test]
+    fn test_commit_with_trailers() {
+        use std::fs;
 
-        Self::validate_ref_format(ref1)?;
-        Self::validate_ref_format(ref2)?;
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("file.txt"), "content\n").expect("write file");
+        crate::vcs::test_utils::git(&repo.dir, &["add", "file.txt"]);
 
-        let obj1 = self
-            .repo
-            .revparse_single(ref1)
-            .map_err(|_| VcsError::InvalidRef(ref1.to_string()))?;
-        let oid1 = obj1
-            .peel_to_commit()
-            .map_err(|_| VcsError::InvalidRef(ref1.to_string()))?
-            .id();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let trailers = [
+            (
+                "Co-authored-by".to_string(),
+                "A <a@example.com>".to_string(),
+            ),
+            ("Signed-off-by".to_string(), "B <b@example.com>".to_string()),
+            (
+                "Co-authored-by".to_string(),
+                "A <a@example.com>".to_string(),
+            ),
+        ];
+        let sha = backend
+            .commit_with_trailers("feat: add file\n\nLonger body.", &trailers)
+            .expect("should commit");
+
+        let git_repo = Repository::open(&repo.dir).expect("open repo");
+        let commit = git_repo
+            .find_commit(git2::Oid::from_str(&sha).unwrap())
+            .expect("find commit");
+        assert_eq!(
+            commit.message().unwrap(),
+            "feat: add file\n\nLonger body.\n\n\
+             Co-authored-by: A <a@example.com>\n\
+             Signed-off-by: B <b@example.com>\n"
+        );
 
-        let obj2 = self
-            .repo
-            .revparse_single(ref2)
-            .map_err(|_| VcsError::InvalidRef(ref2.to_string()))?;
-        let oid2 = obj2
-            .peel_to_commit()
-            .map_err(|_| VcsError::InvalidRef(ref2.to_string()))?
-            .id();
+        // A subject that looks like a trailer is not a trailer block
+        assert_eq!(
+            append_trailers("fix: x", &trailers[1..2]),
+            "fix: x\n\nSigned-off-by: B <b@example.com>\n"
+        );
 
-        let merge_base = self
-            .repo
-            .merge_base(oid1, oid2)
-            .map_err(|e| VcsError::Other(format!("failed to find merge base: {}", e)))?;
+        // Joins an existing block and skips trailers it already has
+        assert_eq!(
+            append_trailers("fix: x\n\nSigned-off-by: B <b@example.com>\n", &trailers),
+            "fix: x\n\nSigned-off-by: B <b@example.com>\nCo-authored-by: A <a@example.com>\n"
+        );
+    }
 
-        Ok(merge_base.to_string())
+    #[test]
+    fn test_describe() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let head = backend.head_sha().expect("should get head").unwrap();
+        assert_eq!(
+            backend.describe("HEAD").expect("should describe"),
+            head[..7]
+        );
+
+        git(&repo.dir, &["tag", "v1.2.3"]);
+        assert_eq!(backend.describe("HEAD").expect("should describe"), "v1.2.3");
+
+        for i in 0..2 {
+            fs::write(repo.dir.join(format!("f{}.txt", i)), "x\n").expect("write file");
+            git(&repo.dir, &["add", "."]);
+            git(&repo.dir, &["commit", "-m", &format!("commit {}", i)]);
+        }
+        let head = backend.head_sha().expect("should get head").unwrap();
+        assert_eq!(
+            backend.describe("HEAD").expect("should describe"),
+            format!("v1.2.3-2-g{}", &head[..7])
+        );
     }
 
-    fn working_copy_parent_ref(&self) -> &'static str {
-        "HEAD"
+    #[test]
+    fn test_rename_detection_links_heavy_rewrites() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        let original: String = (1..=10).map(|i| format!("original line {}\n", i)).collect();
+        fs::create_dir_all(repo.dir.join("old")).expect("create dir");
+        fs::write(repo.dir.join("old/module.txt"), &original).expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "add module"]);
+
+        // Move across directories and rewrite most of the lines
+        let rewritten: String = (1..=10)
+            .map(|i| match i {
+                1..=3 => format!("original line {}\n", i),
+                _ => format!("rewritten line {}\n", i),
+            })
+            .collect();
+        fs::remove_dir_all(repo.dir.join("old")).expect("remove dir");
+        fs::create_dir_all(repo.dir.join("new")).expect("create dir");
+        fs::write(repo.dir.join("new/module.txt"), rewritten).expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "move module"]);
+
+        let strict = GitBackend::from_cwd()
+            .expect("should open repo")
+            .with_rename_detection(Some(RenameOptions::default()));
+        let diff = strict.get_commit("HEAD").expect("should get commit").diff;
+        assert!(!diff.contains("rename from"), "got: {}", diff);
+
+        let lenient = GitBackend::from_cwd()
+            .expect("should open repo")
+            .with_rename_detection(Some(RenameOptions {
+                rename_threshold: 20,
+                break_rewrites: true,
+                ..RenameOptions::default()
+            }));
+        let diff = lenient.get_commit("HEAD").expect("should get commit").diff;
+        assert!(diff.contains("rename from old/module.txt"), "got: {}", diff);
+        assert!(diff.contains("rename to new/module.txt"), "got: {}", diff);
+        assert!(diff.contains("+rewritten line 10"), "got: {}", diff);
     }
 
-    fn get_range_changed_files(&self, from: &str, to: &str) -> Result<Vec<String>, VcsError> {
-        let from = from.trim();
-        let to = to.trim();
+    #[test]
+    fn test_get_commit_diff_in_subtree() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-        Self::validate_ref_format(from)?;
-        Self::validate_ref_format(to)?;
+        let repo = RepoGuard::new();
+        for dir in ["a", "ab", "b"] {
+            fs::create_dir_all(repo.dir.join(dir)).expect("create dir");
+            fs::write(repo.dir.join(dir).join("lib.rs"), format!("// {}\n", dir))
+                .expect("write file");
+        }
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "touch a, ab and b"]);
 
-        let from_obj = self
-            .repo
-            .revparse_single(from)
-            .map_err(|_| VcsError::InvalidRef(from.to_string()))?;
-        let from_tree = from_obj
-            .peel_to_commit()
-            .map_err(|_| VcsError::InvalidRef(from.to_string()))?
-            .tree()
-            .map_err(|e| VcsError::Other(format!("failed to get from tree: {}", e)))?;
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let diff = backend
+            .get_commit_diff_in_subtree("HEAD", Path::new("a"))
+            .expect("should get diff");
+        assert!(diff.contains("+// a\n"), "got: {}", diff);
+        assert!(!diff.contains("ab/lib.rs"), "got: {}", diff);
+        assert_eq!(diff.matches("diff --git").count(), 1, "got: {}", diff);
 
-        let to_obj = self
-            .repo
-            .revparse_single(to)
-            .map_err(|_| VcsError::InvalidRef(to.to_string()))?;
-        let to_tree = to_obj
-            .peel_to_commit()
-            .map_err(|_| VcsError::InvalidRef(to.to_string()))?
-            .tree()
-            .map_err(|e| VcsError::Other(format!("failed to get to tree: {}", e)))?;
+        let trailing = backend
+            .get_commit_diff_in_subtree("HEAD", Path::new("a/"))
+            .expect("should get diff");
+        assert_eq!(trailing, diff);
+    }
 
-        let diff = self
-            .repo
-            .diff_tree_to_tree(Some(&from_tree), Some(&to_tree), None)
-            .map_err(|e| VcsError::Other(format!("failed to create diff: {}", e)))?;
+    #[test]
+    fn test_get_pr_template() {
+        use std::fs;
 
-        Ok(diff
-            .deltas()
-            .filter_map(|d| {
-                d.new_file()
-                    .path()
-                    .and_then(|p| p.to_str().map(String::from))
-            })
-            .collect())
+        let repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert_eq!(backend.get_pr_template().expect("should look up"), None);
+
+        fs::write(repo.dir.join("PULL_REQUEST_TEMPLATE.md"), "root\n").expect("write template");
+        fs::create_dir_all(repo.dir.join(".github")).expect("create dir");
+        fs::write(
+            repo.dir.join(".github/pull_request_template.md"),
+            "## Summary\n",
+        )
+        .expect("write template");
+        assert_eq!(
+            backend.get_pr_template().expect("should look up"),
+            Some("## Summary\n".to_string())
+        );
     }
 
-    fn get_parent_ref_or_empty(&self, reference: &str) -> Result<String, VcsError> {
-        let reference = reference.trim();
-        Self::validate_ref_format(reference)?;
+    #[test]
+    fn test_find_conflict_markers_in_staged() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-        let obj = self
-            .repo
-            .revparse_single(reference)
-            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
-        let commit = obj
-            .peel_to_commit()
-            .map_err(|_| VcsError::InvalidRef(reference.to_string()))?;
+        let repo = RepoGuard::new();
+        fs::write(
+            repo.dir.join("merged.txt"),
+            "ok\n<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> feature\n",
+        )
+        .expect("write file");
+        fs::write(repo.dir.join("clean.md"), "Title\n========\n").expect("write file");
+        fs::write(
+            repo.dir.join("heading.md"),
+            "Title\n=======\n\nSection\n-------\n",
+        )
+        .expect("write file");
+        fs::write(repo.dir.join("blob.bin"), b"\0<<<<<<< HEAD\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
 
-        if commit.parent_count() > 0 {
-            // Has parent - return the parent ref
-            Ok(format!("{}^", reference))
-        } else {
-            // No parent (root commit) - return git's empty tree SHA
-            // This is a well-known constant: the SHA of an empty tree
-            Ok("4b825dc642cb6eb9a060e54bf8d69288fbee4904".to_string())
-        }
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert_eq!(
+            backend
+                .find_conflict_markers_in_staged()
+                .expect("should scan"),
+            vec![
+                ("merged.txt".to_string(), 2),
+                ("merged.txt".to_string(), 4),
+                ("merged.txt".to_string(), 6),
+            ]
+        );
     }
 
-    fn get_commits_in_range(
-        &self,
-        from: &str,
-        to: &str,
-    ) -> Result<Vec<StackedCommitInfo>, VcsError> {
-        let from = from.trim();
-        let to = to.trim();
+    #[test]
+    fn test_author_commit_counts() {
+        let repo = RepoGuard::new();
+        let git_repo = Repository::open(&repo.dir).expect("open repo");
+        let base = git_repo.head().unwrap().target().unwrap();
+
+        let authors = [
+            ("Alice", "alice@example.com"),
+            ("Bob", "bob@example.com"),
+            ("Alice", "Alice@Example.com"),
+        ];
+        for (i, (name, email)) in authors.iter().enumerate() {
+            let sig = Signature::now(name, email).unwrap();
+            let parent = git_repo.head().unwrap().peel_to_commit().unwrap();
+            let tree = tree_with(
+                &git_repo,
+                &parent.tree().unwrap(),
+                &[(&format!("f{}.txt", i), "x\n")],
+            );
+            git_repo
+                .commit(Some("HEAD"), &sig, &sig, "change", &tree, &[&parent])
+                .expect("commit");
+        }
 
-        Self::validate_ref_format(from)?;
-        Self::validate_ref_format(to)?;
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert_eq!(
+            backend
+                .author_commit_counts(&base.to_string(), "HEAD")
+                .expect("should count"),
+            vec![
+                ("alice@example.com".to_string(), 2),
+                ("bob@example.com".to_string(), 1),
+            ]
+        );
+    }
 
-        // Resolve refs to OIDs
-        let from_obj = self
-            .repo
-            .revparse_single(from)
-            .map_err(|_| VcsError::InvalidRef(from.to_string()))?;
-        let from_oid = from_obj
-            .peel_to_commit()
-            .map_err(|_| VcsError::InvalidRef(from.to_string()))?
-            .id();
+    #[test]
+    fn test_stage_hunk_stages_only_that_hunk() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-        let to_obj = self
-            .repo
-            .revparse_single(to)
-            .map_err(|_| VcsError::InvalidRef(to.to_string()))?;
-        let to_oid = to_obj
-            .peel_to_commit()
-            .map_err(|_| VcsError::InvalidRef(to.to_string()))?
-            .id();
+        let repo = RepoGuard::new();
+        let original: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+        fs::write(repo.dir.join("file.txt"), &original).expect("write file");
+        git(&repo.dir, &["add", "file.txt"]);
+        git(&repo.dir, &["commit", "-m", "add file"]);
 
-        // Set up revwalk from 'to' to 'from' (exclusive)
-        let mut revwalk = self
-            .repo
-            .revwalk()
-            .map_err(|e| VcsError::Other(format!("failed to create revwalk: {}", e)))?;
-        revwalk
-            .push(to_oid)
-            .map_err(|e| VcsError::Other(format!("failed to push to revwalk: {}", e)))?;
-        revwalk
-            .hide(from_oid)
-            .map_err(|e| VcsError::Other(format!("failed to hide from revwalk: {}", e)))?;
+        let modified = original.replacen("line 2\n", "line two\n", 1).replacen(
+            "line 18\n",
+            "line eighteen\n",
+            1,
+        );
+        fs::write(repo.dir.join("file.txt"), modified).expect("modify file");
 
-        // Collect commits in reverse order (oldest first)
-        let mut commits: Vec<StackedCommitInfo> = Vec::new();
-        for oid_result in revwalk {
-            let oid = oid_result.map_err(|e| VcsError::Other(format!("revwalk error: {}", e)))?;
-            let commit = self
-                .repo
-                .find_commit(oid)
-                .map_err(|e| VcsError::Other(format!("failed to find commit: {}", e)))?;
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        backend
+            .stage_hunk(Path::new("file.txt"), 0)
+            .expect("should stage hunk");
+
+        let staged = backend.get_working_tree_diff(true).expect("staged diff");
+        assert!(staged.contains("+line two"), "got: {}", staged);
+        assert!(!staged.contains("+line eighteen"), "got: {}", staged);
+        let unstaged = backend.get_working_tree_diff(false).expect("unstaged diff");
+        assert!(unstaged.contains("+line eighteen"), "got: {}", unstaged);
+        assert!(!unstaged.contains("+line two"), "got: {}", unstaged);
+
+        assert!(backend.stage_hunk(Path::new("file.txt"), 1).is_err());
+    }
 
-            let commit_id = oid.to_string();
-            let short_id = commit_id[..7.min(commit_id.len())].to_string();
-            let summary = commit.summary().unwrap_or("").to_string();
+    #[test]
+    fn test_preview_squash() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-            // Filter commits with no file changes (e.g., merge commits)
-            if self
-                .get_changed_files(&commit_id)
-                .map(|f| !f.is_empty())
-                .unwrap_or(false)
-            {
-                commits.push(StackedCommitInfo {
-                    commit_id,
-                    short_id,
-                    change_id: None,
-                    summary,
-                });
-            }
+        let repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let base = backend.head_sha().expect("should get head").unwrap();
+        let messages = [
+            "feat: add parser\n\nSigned-off-by: A <a@example.com>",
+            "fix: handle empty input",
+            "docs: explain parser\n\nSigned-off-by: A <a@example.com>",
+        ];
+        for (i, message) in messages.iter().enumerate() {
+            fs::write(repo.dir.join(format!("f{}.txt", i)), "x\n").expect("write file");
+            git(&repo.dir, &["add", "."]);
+            git(&repo.dir, &["commit", "-m", message]);
         }
 
-        // Reverse to get oldest first
-        commits.reverse();
-        Ok(commits)
+        let (message, diff) = backend
+            .preview_squash(&base, "HEAD")
+            .expect("should preview");
+        assert_eq!(
+            message,
+            "feat: add parser\n\nfix: handle empty input\n\ndocs: explain parser\n\n\
+             Signed-off-by: A <a@example.com>\n"
+        );
+        assert_eq!(
+            diff,
+            backend
+                .get_range_diff(&base, "HEAD", false)
+                .expect("should get range diff")
+        );
     }
 
-    fn name(&self) -> &'static str {
-        "git"
-    }
-}
+    #[test]
+    fn test_dominant_language() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::vcs::test_utils::RepoGuard;
+        let repo = RepoGuard::new();
+        for name in ["a.rs", "b.rs", "c.rs", "README.md"] {
+            fs::write(repo.dir.join(name), "x\n").expect("write file");
+        }
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "mostly rust"]);
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert_eq!(
+            backend.dominant_language("HEAD").expect("should detect"),
+            Some("rust")
+        );
+
+        // A tie resolves to the alphabetically first language
+        for name in ["x.py", "y.go"] {
+            fs::write(repo.dir.join(name), "x\n").expect("write file");
+        }
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "tie"]);
+        assert_eq!(
+            backend.dominant_language("HEAD").expect("should detect"),
+            Some("go")
+        );
+    }
 
     #[test]
-    fn test_get_commit_returns_valid_info() {
+    fn test_fzf_log_respects_no_color() {
         let _repo = RepoGuard::new();
         let backend = GitBackend::from_cwd().expect("should open repo");
 
-        let info = backend.get_commit("HEAD").expect("should get commit");
-        assert!(!info.commit_id.is_empty());
-        assert!(info.change_id.is_none()); // Git has no change IDs
-        assert_eq!(info.message, "init");
-        assert!(info.author.contains("Test User"));
-        assert!(!info.diff.is_empty());
+        // RepoGuard holds the cwd lock, so no other test sees NO_COLOR
+        let original = std::env::var_os("NO_COLOR");
+        std::env::set_var("NO_COLOR", "1");
+        let plain = backend.get_commit_log_for_fzf();
+        match original {
+            Some(value) => std::env::set_var("NO_COLOR", value),
+            None => std::env::remove_var("NO_COLOR"),
+        }
+
+        let plain = plain.expect("should get log");
+        assert!(!plain.is_empty());
+        assert!(!plain.contains('\x1b'), "got: {:?}", plain);
     }
 
     #[test]
-    fn test_get_working_tree_diff_returns_string() {
-        let _repo = RepoGuard::new();
+    fn test_get_file_workdir_diff() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("foo.rs"), "fn foo() {}\n").expect("write file");
+        fs::write(repo.dir.join("bar.rs"), "fn bar() {}\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "add files"]);
+
+        fs::write(repo.dir.join("foo.rs"), "fn foo() { todo!() }\n").expect("modify file");
+        fs::write(repo.dir.join("bar.rs"), "fn bar() { todo!() }\n").expect("modify file");
+        git(&repo.dir, &["add", "bar.rs"]);
+
         let backend = GitBackend::from_cwd().expect("should open repo");
+        let diff = backend
+            .get_file_workdir_diff(Path::new("foo.rs"))
+            .expect("should diff");
+        assert!(diff.contains("+fn foo() { todo!() }"), "got: {}", diff);
+        assert!(!diff.contains("bar.rs"), "got: {}", diff);
 
-        // Should succeed even if empty
-        let diff = backend.get_working_tree_diff(false);
-        assert!(diff.is_ok());
+        // Staged changes count too
+        let diff = backend
+            .get_file_workdir_diff(Path::new("bar.rs"))
+            .expect("should diff");
+        assert!(diff.contains("+fn bar() { todo!() }"), "got: {}", diff);
+
+        assert!(matches!(
+            backend.get_file_workdir_diff(Path::new("missing.rs")),
+            Err(VcsError::FileNotFound(_))
+        ));
     }
 
     #[test]
-    fn test_get_changed_files_returns_paths() {
-        let _repo = RepoGuard::new();
+    fn test_commits_touching_file() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
         let backend = GitBackend::from_cwd().expect("should open repo");
+        let base = backend.head_sha().expect("should get head").unwrap();
+        let edits = [
+            ("foo.rs", "one", "add foo"),
+            ("bar.rs", "one", "add bar"),
+            ("foo.rs", "two", "edit foo"),
+            ("bar.rs", "two", "edit bar"),
+        ];
+        for (name, content, subject) in edits {
+            fs::write(repo.dir.join(name), content).expect("write file");
+            git(&repo.dir, &["add", name]);
+            git(&repo.dir, &["commit", "-m", subject]);
+        }
 
-        let files = backend.get_changed_files("HEAD").expect("should get files");
-        assert!(files.contains(&"README.md".to_string()));
+        let commits = backend
+            .commits_touching_file(&base, "HEAD", Path::new("foo.rs"))
+            .expect("should list commits");
+        let subjects: Vec<_> = commits.iter().map(|c| c.summary.as_str()).collect();
+        assert_eq!(subjects, vec!["add foo", "edit foo"]);
     }
 
     #[test]
-    fn test_get_current_branch() {
-        let _repo = RepoGuard::new();
-        let backend = GitBackend::from_cwd().expect("should open repo");
+    fn test_max_hunks_per_file_keeps_largest() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-        let branch = backend.get_current_branch().expect("should get branch");
-        assert!(branch.is_some());
+        let repo = RepoGuard::new();
+        let original: Vec<String> = (1..=60).map(|i| format!("line {}", i)).collect();
+        fs::write(repo.dir.join("file.txt"), original.join("\n") + "\n").expect("write file");
+        git(&repo.dir, &["add", "file.txt"]);
+        git(&repo.dir, &["commit", "-m", "add file"]);
+
+        // Five hunks, ten lines apart; the ones at 21 and 41 change the most
+        let mut modified = original.clone();
+        for (start, len) in [(1, 1), (11, 1), (21, 3), (31, 1), (41, 2)] {
+            for line in &mut modified[start - 1..start - 1 + len] {
+                *line = format!("{} changed", line);
+            }
+        }
+        fs::write(repo.dir.join("file.txt"), modified.join("\n") + "\n").expect("modify file");
+
+        let backend = GitBackend::from_cwd()
+            .expect("should open repo")
+            .with_max_hunks_per_file(Some(2));
+        let diff = backend
+            .get_working_tree_diff(false)
+            .expect("should get diff");
+        assert_eq!(diff.matches("@@ -").count(), 2, "got: {}", diff);
+        assert!(diff.contains("+line 21 changed"), "got: {}", diff);
+        assert!(diff.contains("+line 42 changed"), "got: {}", diff);
+        assert!(!diff.contains("+line 1 changed"), "got: {}", diff);
+
+        let files = crate::vcs::patch::parse_patch(&diff);
+        assert_eq!(files.len(), 1);
+        assert_eq!(
+            files[0].header.last().map(String::as_str),
+            Some("... [3 smaller hunks omitted] ..."),
+            "got: {}",
+            diff
+        );
+        assert!(files[0]
+            .hunks
+            .iter()
+            .all(|hunk| hunk.lines.iter().all(|line| !line.starts_with("..."))));
     }
 
     #[test]
-    fn test_get_file_content_at_ref() {
-        let _repo = RepoGuard::new();
+    fn test_is_reachable_from_head() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("main.txt"), "main\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "main work"]);
+
+        // A sibling branch forking from HEAD~1, without moving HEAD
+        {
+            let git_repo = Repository::open(&repo.dir).expect("open repo");
+            let fork = git_repo
+                .revparse_single("HEAD~1")
+                .and_then(|obj| obj.peel_to_commit())
+                .expect("find fork point");
+            let tree = tree_with(&git_repo, &fork.tree().unwrap(), &[("sibling.txt", "x\n")]);
+            let sig = Signature::now("Test User", "test@example.com").unwrap();
+            git_repo
+                .commit(
+                    Some("refs/heads/sibling"),
+                    &sig,
+                    &sig,
+                    "sibling work",
+                    &tree,
+                    &[&fork],
+                )
+                .expect("commit on sibling");
+        }
+
         let backend = GitBackend::from_cwd().expect("should open repo");
+        assert!(backend
+            .is_reachable_from_head("HEAD")
+            .expect("should check"));
+        assert!(backend
+            .is_reachable_from_head("HEAD~1")
+            .expect("should check"));
+        assert!(!backend
+            .is_reachable_from_head("sibling")
+            .expect("should check"));
+    }
 
-        let content = backend
-            .get_file_content_at_ref("HEAD", Path::new("README.md"))
-            .expect("should get content");
-        assert_eq!(content.trim(), "hello");
+    #[test]
+    fn test_cherry_pick_shares_change_digest() {
+        let repo = RepoGuard::new();
+        let git_repo = Repository::open(&repo.dir).expect("open repo");
+        let sig = Signature::now("Test User", "test@example.com").unwrap();
+        let root = git_repo.head().unwrap().peel_to_commit().unwrap();
+
+        let base_tree = tree_with(&git_repo, &root.tree().unwrap(), &[("f.txt", "a\nb\nc\n")]);
+        let base_id = git_repo
+            .commit(Some("HEAD"), &sig, &sig, "base", &base_tree, &[&root])
+            .unwrap();
+        let base = git_repo.find_commit(base_id).unwrap();
+
+        // The change to pick, on the main line
+        let change_tree = tree_with(&git_repo, &base_tree, &[("f.txt", "a\nb\nc\nd\n")]);
+        let change_id = git_repo
+            .commit(Some("HEAD"), &sig, &sig, "append d", &change_tree, &[&base])
+            .unwrap();
+        let change = git_repo.find_commit(change_id).unwrap();
+
+        // A sibling where the file has shifted, so the hunk lands elsewhere
+        let sibling_tree = tree_with(&git_repo, &base_tree, &[("f.txt", "z\na\nb\nc\n")]);
+        let sibling_id = git_repo
+            .commit(
+                Some("refs/heads/sibling"),
+                &sig,
+                &sig,
+                "prepend z",
+                &sibling_tree,
+                &[&base],
+            )
+            .unwrap();
+        let sibling = git_repo.find_commit(sibling_id).unwrap();
+
+        let mut picked = git_repo
+            .cherrypick_commit(&change, &sibling, 0, None)
+            .expect("cherry-pick");
+        let picked_tree = git_repo
+            .find_tree(picked.write_tree_to(&git_repo).unwrap())
+            .unwrap();
+        let other = Signature::now("Other Person", "other@example.com").unwrap();
+        let picked_id = git_repo
+            .commit(
+                Some("refs/heads/sibling"),
+                &other,
+                &other,
+                "append d (cherry picked)",
+                &picked_tree,
+                &[&sibling],
+            )
+            .unwrap();
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let digest = |id: git2::Oid| {
+            backend
+                .get_commit_change_digest(&id.to_string())
+                .expect("should digest")
+        };
+        assert_eq!(digest(change_id), digest(picked_id));
+        assert_ne!(digest(change_id), digest(sibling_id));
     }
 
     #[test]
-    fn test_invalid_ref_returns_error() {
-        let _repo = RepoGuard::new();
+    fn test_get_merge_cc_diff_marks_resolution_against_each_parent() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("f.txt"), "one\nshared\nthree\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "base"]);
+
+        {
+            let git_repo = Repository::open(&repo.dir).expect("open repo");
+            let base = git_repo.head().unwrap().peel_to_commit().unwrap();
+            let base_tree = base.tree().unwrap();
+            let sig = Signature::now("Test User", "test@example.com").unwrap();
+            let commit_on = |files: &[(&str, &str)], message: &str| {
+                let tree = tree_with(&git_repo, &base_tree, files);
+                let id = git_repo
+                    .commit(None, &sig, &sig, message, &tree, &[&base])
+                    .expect("commit side");
+                git_repo.find_commit(id).unwrap()
+            };
+            let ours = commit_on(&[("f.txt", "one\nours\nthree\n")], "ours");
+            let theirs = commit_on(
+                &[("f.txt", "one\ntheirs\nthree\n"), ("side.txt", "theirs\n")],
+                "theirs",
+            );
+
+            // Resolve the conflict with content from neither side, and take
+            // side.txt as-is from theirs
+            let merged = tree_with(
+                &git_repo,
+                &base_tree,
+                &[
+                    ("f.txt", "one\nresolved\nthree\n"),
+                    ("side.txt", "theirs\n"),
+                ],
+            );
+            let merge = git_repo
+                .commit(None, &sig, &sig, "merge", &merged, &[&ours, &theirs])
+                .expect("commit merge");
+            git_repo
+                .head()
+                .unwrap()
+                .set_target(merge, "merge")
+                .expect("move HEAD");
+        }
+
         let backend = GitBackend::from_cwd().expect("should open repo");
+        let diff = backend
+            .get_merge_cc_diff("HEAD")
+            .expect("should diff merge");
+        assert!(diff.starts_with("diff --cc f.txt\n"), "diff: {}", diff);
+        assert!(
+            diff.contains("\n@@@ -1,3 -1,3 +1,3 @@@\n"),
+            "diff: {}",
+            diff
+        );
+        assert!(diff.contains("\n  one\n"), "diff: {}", diff);
+        assert!(diff.contains("\n- ours\n"), "diff: {}", diff);
+        assert!(diff.contains("\n -theirs\n"), "diff: {}", diff);
+        assert!(diff.contains("\n++resolved\n"), "diff: {}", diff);
+        assert!(
+            !diff.contains("side.txt"),
+            "theirs' file is not combined: {}",
+            diff
+        );
 
-        let result = backend.get_commit("nonexistent12345");
-        assert!(result.is_err());
+        let err = backend.get_merge_cc_diff("HEAD~1").unwrap_err();
+        assert!(matches!(err, VcsError::InvalidRef(_)));
     }
 
     #[test]
-    fn test_get_file_content_at_ref_missing_file() {
-        let _repo = RepoGuard::new();
-        let backend = GitBackend::from_cwd().expect("should open repo");
+    fn test_fold_context_over_folds_long_unchanged_run() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-        let result = backend.get_file_content_at_ref("HEAD", Path::new("nonexistent.txt"));
+        let repo = RepoGuard::new();
+        let lines: Vec<String> = (1..=30).map(|n| format!("line {}", n)).collect();
+        fs::write(repo.dir.join("f.txt"), lines.join("\n") + "\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "add file"]);
+
+        // Two changes 25 lines apart, in one hunk thanks to wide context
+        let mut edited = lines.clone();
+        edited[1] = "changed 2".to_string();
+        edited[27] = "changed 28".to_string();
+        fs::write(repo.dir.join("f.txt"), edited.join("\n") + "\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "edit"]);
+        fs::write(repo.dir.join(".lumen.toml"), "context_lines = 20\n").expect("write settings");
+
+        let backend = GitBackend::from_cwd()
+            .expect("should open repo")
+            .with_fold_context_over(Some(4));
+        let diff = backend.get_commit("HEAD").expect("should get commit").diff;
+
+        assert_eq!(diff.matches("@@ -").count(), 1, "diff: {}", diff);
         assert!(
-            matches!(result, Err(VcsError::FileNotFound(_))),
-            "Expected FileNotFound error, got: {:?}",
-            result
+            diff.contains(
+                "+changed 2\n line 3\n line 4\n... 21 unchanged lines ...\n line 26\n line 27\n-line 28\n"
+            ),
+            "diff: {}",
+            diff
         );
+        assert!(!diff.contains(" line 14\n"), "diff: {}", diff);
     }
 
     #[test]
-    fn test_get_commit_log_for_fzf() {
-        let _repo = RepoGuard::new();
-        let backend = GitBackend::from_cwd().expect("should open repo");
+    fn test_fold_context_keeps_lines_next_to_a_deep_change() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-        let log = backend.get_commit_log_for_fzf().expect("should get log");
-        assert!(!log.is_empty(), "commit log should not be empty");
-        // Log should contain the short hash from the commit
-        assert!(
-            log.lines().next().is_some(),
-            "log should have at least one line"
+        let repo = RepoGuard::new();
+        let lines: Vec<String> = (1..=40).map(|n| format!("line {}", n)).collect();
+        fs::write(repo.dir.join("f.txt"), lines.join("\n") + "\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "add file"]);
+
+        let mut edited = lines.clone();
+        edited[29] = "changed 30".to_string();
+        fs::write(repo.dir.join("f.txt"), edited.join("\n") + "\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "edit"]);
+        fs::write(repo.dir.join(".lumen.toml"), "context_lines = 20\n").expect("write settings");
+
+        let backend = GitBackend::from_cwd()
+            .expect("should open repo")
+            .with_fold_context_over(Some(4));
+        let diff = backend.get_commit("HEAD").expect("should get commit").diff;
+
+        let hunk = &diff[diff.find("@@ -").expect("hunk header")..];
+        let body = &hunk[hunk.find('\n').unwrap() + 1..];
+        assert_eq!(
+            body,
+            "... 16 unchanged lines ...\n line 26\n line 27\n line 28\n line 29\n-line 30\n+changed 30\n line 31\n line 32\n line 33\n line 34\n... 6 unchanged lines ...\n",
+            "diff: {}",
+            diff
         );
     }
 
     #[test]
-    fn test_get_working_tree_diff_staged() {
-        use crate::vcs::test_utils::{git, make_temp_dir};
+    fn test_get_commit_parent_summary() {
+        use crate::vcs::test_utils::git;
         use std::fs;
 
-        let _lock = crate::vcs::test_utils::cwd_lock()
-            .lock()
-            .unwrap_or_else(|e| e.into_inner());
-        let dir = make_temp_dir("git-staged");
-        let original = std::env::current_dir().expect("get cwd");
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("auth.rs"), "fn auth() {}\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(
+            &repo.dir,
+            &["commit", "-m", "add auth module\n\nWith details."],
+        );
+        fs::write(repo.dir.join("auth.rs"), "fn auth() -> bool { true }\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "return auth result"]);
 
-        git(&dir, &["init"]);
-        git(&dir, &["config", "user.email", "test@example.com"]);
-        git(&dir, &["config", "user.name", "Test User"]);
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let info = backend.get_commit("HEAD").expect("should get commit");
+        assert_eq!(info.parent_summary.as_deref(), Some("add auth module"));
 
-        // Initial commit
-        fs::write(dir.join("file.txt"), "initial\n").expect("write file");
-        git(&dir, &["add", "."]);
-        git(&dir, &["commit", "-m", "init"]);
+        let root = backend
+            .get_commit("HEAD~2")
+            .expect("should get root commit");
+        assert_eq!(root.parent_summary, None);
+    }
 
-        // Stage one change, leave another unstaged
-        fs::write(dir.join("file.txt"), "staged change\n").expect("modify file");
-        git(&dir, &["add", "file.txt"]);
-        fs::write(dir.join("file.txt"), "staged change\nunstaged change\n").expect("modify again");
+    #[test]
+    fn test_infer_base_branch_prefers_upstream() {
+        let repo = RepoGuard::new();
+        {
+            let git_repo = Repository::open(&repo.dir).expect("open repo");
+            let head = git_repo.head().unwrap().target().unwrap();
+            let commit = git_repo.find_commit(head).unwrap();
+            git_repo
+                .branch("feature", &commit, false)
+                .expect("create branch");
+            git_repo
+                .set_head("refs/heads/feature")
+                .expect("checkout feature");
+            git_repo
+                .remote("origin", "https://example.com/repo.git")
+                .expect("add remote");
+            for remote_branch in ["develop", "main"] {
+                git_repo
+                    .reference(
+                        &format!("refs/remotes/origin/{}", remote_branch),
+                        head,
+                        true,
+                        "test fetch",
+                    )
+                    .expect("create remote-tracking ref");
+            }
+            git_repo
+                .reference_symbolic(
+                    "refs/remotes/origin/HEAD",
+                    "refs/remotes/origin/main",
+                    true,
+                    "test fetch",
+                )
+                .expect("create origin/HEAD");
+            git_repo
+                .find_branch("feature", git2::BranchType::Local)
+                .unwrap()
+                .set_upstream(Some("origin/develop"))
+                .expect("set upstream");
+        }
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert_eq!(
+            backend.infer_base_branch().expect("should infer"),
+            Some("origin/develop".to_string())
+        );
+    }
+
+    #[test]
+    fn test_infer_base_branch_falls_back_to_origin_head() {
+        let repo = RepoGuard::new();
+        {
+            let git_repo = Repository::open(&repo.dir).expect("open repo");
+            let head = git_repo.head().unwrap().target().unwrap();
+            git_repo
+                .reference("refs/remotes/origin/trunk", head, true, "test fetch")
+                .expect("create remote-tracking ref");
+            git_repo
+                .reference_symbolic(
+                    "refs/remotes/origin/HEAD",
+                    "refs/remotes/origin/trunk",
+                    true,
+                    "test fetch",
+                )
+                .expect("create origin/HEAD");
+        }
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert_eq!(
+            backend.infer_base_branch().expect("should infer"),
+            Some("origin/trunk".to_string())
+        );
+    }
+
+    #[test]
+    fn test_infer_base_branch_local_main_only() {
+        let repo = RepoGuard::new();
+        {
+            let git_repo = Repository::open(&repo.dir).expect("open repo");
+            let mut head = git_repo.head().unwrap();
+            let original = head.name().unwrap().to_string();
+            if original != "refs/heads/main" {
+                let commit = head.peel_to_commit().unwrap();
+                git_repo
+                    .branch("main", &commit, false)
+                    .expect("create main");
+                git_repo.set_head("refs/heads/main").expect("checkout main");
+                head.delete().expect("delete original branch");
+            }
+        }
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert_eq!(
+            backend.infer_base_branch().expect("should infer"),
+            Some("main".to_string())
+        );
+    }
 
-        std::env::set_current_dir(&dir).expect("set cwd");
+    #[test]
+    fn test_diff_limits_abort_oversized_diff() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(repo.dir.join(name), "some content\n".repeat(50)).expect("write file");
+        }
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "add files"]);
 
         let backend = GitBackend::from_cwd().expect("should open repo");
+        let limited = |limits| {
+            GitBackend::from_cwd()
+                .expect("should open repo")
+                .with_diff_limits(Some(limits))
+                .get_commit("HEAD")
+        };
+        assert!(backend.get_commit("HEAD").is_ok());
 
-        // Staged diff should only show "staged change"
-        let staged_diff = backend
-            .get_working_tree_diff(true)
-            .expect("should get staged diff");
-        assert!(
-            staged_diff.contains("staged change"),
-            "staged diff should contain staged changes"
-        );
+        let err = limited(DiffLimits {
+            max_total_bytes: Some(100),
+            max_files: None,
+        })
+        .unwrap_err();
         assert!(
-            !staged_diff.contains("unstaged change"),
-            "staged diff should NOT contain unstaged changes"
+            matches!(err, VcsError::DiffTooLarge { bytes, .. } if bytes > 100),
+            "got: {:?}",
+            err
         );
 
-        // Unstaged diff should show the additional unstaged change
-        let unstaged_diff = backend
-            .get_working_tree_diff(false)
-            .expect("should get unstaged diff");
+        let err = limited(DiffLimits {
+            max_total_bytes: None,
+            max_files: Some(2),
+        })
+        .unwrap_err();
         assert!(
-            unstaged_diff.contains("unstaged change"),
-            "unstaged diff should contain unstaged changes"
+            matches!(err, VcsError::DiffTooLarge { files: 3, .. }),
+            "got: {:?}",
+            err
         );
-
-        // Cleanup
-        let _ = std::env::set_current_dir(&original);
-        let _ = fs::remove_dir_all(&dir);
     }
 
     #[test]
-    fn test_get_range_diff() {
-        use crate::vcs::test_utils::{git, make_temp_dir};
+    fn test_get_pending_diffs_by_file() {
+        use crate::vcs::test_utils::git;
         use std::fs;
 
-        let _lock = crate::vcs::test_utils::cwd_lock()
-            .lock()
-            .unwrap_or_else(|e| e.into_inner());
-        let dir = make_temp_dir("git-range");
-        let original = std::env::current_dir().expect("get cwd");
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("staged.txt"), "old\n").expect("write file");
+        fs::write(repo.dir.join("unstaged.txt"), "old\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "add files"]);
 
-        git(&dir, &["init"]);
-        git(&dir, &["config", "user.email", "test@example.com"]);
-        git(&dir, &["config", "user.name", "Test User"]);
+        fs::write(repo.dir.join("staged.txt"), "staged change\n").expect("write file");
+        git(&repo.dir, &["add", "staged.txt"]);
+        fs::write(repo.dir.join("unstaged.txt"), "unstaged change\n").expect("write file");
+        fs::write(repo.dir.join("untracked.txt"), "untracked\n").expect("write file");
+        fs::write(repo.dir.join("Cargo.lock"), "excluded\n").expect("write file");
 
-        // Commit A
-        fs::write(dir.join("file.txt"), "commit A\n").expect("write file");
-        git(&dir, &["add", "."]);
-        git(&dir, &["commit", "-m", "commit A"]);
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let files = backend
+            .get_pending_diffs_by_file()
+            .expect("should get pending diffs");
+        let paths: Vec<_> = files.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, vec!["staged.txt", "unstaged.txt", "untracked.txt"]);
+
+        for (path, diff) in &files {
+            assert_eq!(diff.matches("diff --git").count(), 1, "diff: {}", diff);
+            assert!(diff.contains(&format!("b/{}", path)), "diff: {}", diff);
+        }
+        assert!(files[0].1.contains("+staged change"));
+        assert!(files[1].1.contains("+unstaged change"));
+        assert!(files[2].1.contains("+untracked"));
 
-        // Commit B
-        fs::write(dir.join("file.txt"), "commit B\n").expect("modify file");
-        git(&dir, &["add", "."]);
-        git(&dir, &["commit", "-m", "commit B"]);
+        // The pieces are exactly the combined pending diff
+        let joined: String = files.iter().map(|(_, diff)| diff.as_str()).collect();
+        assert_eq!(joined, backend.get_pending_diff().expect("should get diff"));
+    }
 
-        std::env::set_current_dir(&dir).expect("set cwd");
+    #[cfg(unix)]
+    #[test]
+    fn test_run_hook_uses_core_hooks_path() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        let repo = RepoGuard::new();
+        let write_hook = |dir: &Path, marker: &str| {
+            fs::create_dir_all(dir).expect("create hooks dir");
+            let hook = dir.join("pre-commit");
+            fs::write(&hook, format!("#!/bin/sh\ntouch {}\n", marker)).expect("write hook");
+            fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).expect("chmod hook");
+        };
+        write_hook(&repo.dir.join(".git/hooks"), "default-ran");
+        write_hook(&repo.dir.join(".husky"), "custom-ran");
+        git(&repo.dir, &["config", "core.hooksPath", ".husky"]);
 
         let backend = GitBackend::from_cwd().expect("should open repo");
+        assert_eq!(backend.hooks_dir(), repo.dir.join(".husky"));
+        assert!(backend
+            .run_hook("pre-commit", &[])
+            .expect("hook should run"));
+        assert!(repo.dir.join("custom-ran").exists());
+        assert!(!repo.dir.join("default-ran").exists());
+
+        assert!(!backend
+            .run_hook("commit-msg", &[])
+            .expect("missing hook is skipped"));
+    }
 
-        // Range diff HEAD~1..HEAD (two-dot)
-        let diff = backend
-            .get_range_diff("HEAD~1", "HEAD", false)
-            .expect("should get range diff");
-        assert!(
-            diff.contains("commit A") || diff.contains("commit B"),
-            "range diff should contain changes"
-        );
+    #[test]
+    fn test_combined_changed_files() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-        // Three-dot range diff also works
-        let diff_3dot = backend
-            .get_range_diff("HEAD~1", "HEAD", true)
-            .expect("should get three-dot diff");
-        assert!(
-            !diff_3dot.is_empty() || diff.contains("commit"),
-            "three-dot diff should work"
-        );
+        let repo = RepoGuard::new();
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let base = backend
+            .head_sha()
+            .expect("should read HEAD")
+            .expect("should have a commit");
+        fs::write(repo.dir.join("committed.txt"), "one\n").expect("write file");
+        fs::write(repo.dir.join("both.txt"), "one\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "committed work"]);
+
+        fs::write(repo.dir.join("both.txt"), "two\n").expect("write file");
+        fs::write(repo.dir.join("staged.txt"), "new\n").expect("write file");
+        git(&repo.dir, &["add", "staged.txt"]);
+        fs::write(repo.dir.join("untracked.txt"), "new\n").expect("write file");
 
-        // Cleanup
-        let _ = std::env::set_current_dir(&original);
-        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(
+            backend
+                .combined_changed_files(&base)
+                .expect("should list files"),
+            vec!["both.txt", "committed.txt", "staged.txt", "untracked.txt"]
+        );
     }
 
     #[test]
-    fn test_range_diff_excludes_lock_files() {
-        use crate::vcs::test_utils::{git, make_temp_dir};
+    fn test_get_commit_change_ratio() {
+        use crate::vcs::test_utils::git;
         use std::fs;
 
-        let _lock = crate::vcs::test_utils::cwd_lock()
-            .lock()
-            .unwrap_or_else(|e| e.into_inner());
-        let dir = make_temp_dir("git-range-exclusion");
-        let original = std::env::current_dir().expect("get cwd");
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("f.txt"), "1\n2\n3\n4\n5\n6\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "add lines"]);
+        fs::write(repo.dir.join("f.txt"), "1\n").expect("write file");
+        // Lock file additions don't count
+        fs::write(repo.dir.join("Cargo.lock"), "a\nb\nc\nd\ne\nf\ng\nh\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "remove lines"]);
 
-        git(&dir, &["init"]);
-        git(&dir, &["config", "user.email", "test@example.com"]);
-        git(&dir, &["config", "user.name", "Test User"]);
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let removal = backend
+            .get_commit_change_ratio("HEAD")
+            .expect("should compute ratio");
+        assert!((removal - 1.0).abs() < f64::EPSILON, "ratio: {}", removal);
+        let addition = backend
+            .get_commit_change_ratio("HEAD~1")
+            .expect("should compute ratio");
+        assert!(addition < 0.5, "ratio: {}", addition);
+
+        // A pure rename changes no lines
+        fs::rename(repo.dir.join("f.txt"), repo.dir.join("g.txt")).expect("rename file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "rename f to g"]);
+        let rename = backend
+            .get_commit_change_ratio("HEAD")
+            .expect("should compute ratio");
+        assert_eq!(rename, 0.0);
+    }
 
-        // Commit A with lock file
-        fs::write(dir.join("file.txt"), "A\n").expect("write file");
-        fs::write(dir.join("package-lock.json"), "{\"v\":1}\n").expect("write lock");
-        git(&dir, &["add", "."]);
-        git(&dir, &["commit", "-m", "A"]);
+    #[test]
+    fn test_get_message_for_reuse_strips_comments() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-        // Commit B - modify both
-        fs::write(dir.join("file.txt"), "B\n").expect("modify file");
-        fs::write(dir.join("package-lock.json"), "{\"v\":2}\n").expect("modify lock");
-        git(&dir, &["add", "."]);
-        git(&dir, &["commit", "-m", "B"]);
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("f.txt"), "x\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(
+            &repo.dir,
+            &[
+                "commit",
+                "-m",
+                "Fix parser\n\n# Please enter the commit message\nHandles  `#` inside lines.\n",
+            ],
+        );
 
-        std::env::set_current_dir(&dir).expect("set cwd");
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        assert_eq!(
+            backend
+                .get_message_for_reuse("HEAD")
+                .expect("should read message"),
+            "Fix parser\n\nHandles  `#` inside lines.\n"
+        );
 
+        git(&repo.dir, &["config", "core.commentChar", ";"]);
         let backend = GitBackend::from_cwd().expect("should open repo");
-        let diff = backend
-            .get_range_diff("HEAD~1", "HEAD", false)
-            .expect("should get range diff");
+        assert!(backend
+            .get_message_for_reuse("HEAD")
+            .expect("should read message")
+            .contains("# Please enter"));
+    }
 
+    #[test]
+    fn test_exclude_globs_and_cleared_defaults() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        fs::create_dir_all(repo.dir.join("api")).expect("create dir");
+        fs::write(repo.dir.join("api/foo.pb.go"), "package api\n").expect("write file");
+        fs::write(repo.dir.join("main.go"), "package main\n").expect("write file");
+        fs::write(repo.dir.join("Cargo.lock"), "lock\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "add go code"]);
+
+        let backend = GitBackend::from_cwd()
+            .expect("should open repo")
+            .with_exclude_globs(vec!["*.pb.go".to_string()])
+            .expect("valid globs");
+        let diff = backend.get_commit("HEAD").expect("should get commit").diff;
+        assert!(diff.contains("main.go"), "diff: {}", diff);
+        assert!(!diff.contains("foo.pb.go"), "diff: {}", diff);
         assert!(
-            diff.contains("file.txt"),
-            "range diff should contain file.txt"
-        );
-        assert!(
-            !diff.contains("package-lock.json"),
-            "range diff should NOT contain package-lock.json"
+            !diff.contains("Cargo.lock"),
+            "defaults still apply: {}",
+            diff
         );
 
-        // Cleanup
-        let _ = std::env::set_current_dir(&original);
-        let _ = fs::remove_dir_all(&dir);
+        let diff = backend
+            .clear_default_excludes()
+            .get_commit("HEAD")
+            .expect("should get commit")
+            .diff;
+        assert!(diff.contains("Cargo.lock"), "diff: {}", diff);
+        assert!(!diff.contains("foo.pb.go"), "diff: {}", diff);
+
+        let invalid = GitBackend::from_cwd()
+            .expect("should open repo")
+            .with_exclude_globs(vec!["[oops".to_string()]);
+        assert!(invalid.is_err());
     }
 
     #[test]
-    fn test_diff_excludes_lock_files() {
-        use crate::vcs::test_utils::{git, make_temp_dir};
+    fn test_list_pickable_commits_and_branches() {
+        use crate::vcs::test_utils::git;
+
+        let repo = RepoGuard::new();
+        git(&repo.dir, &["branch", "feature"]);
+        git(&repo.dir, &["tag", "v1.0"]);
+
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let items = backend
+            .list_pickable(&[PickKind::Commit, PickKind::Branch])
+            .expect("should list items");
+
+        let commit = &items[0];
+        assert_eq!(commit.kind, PickKind::Commit);
+        assert_eq!(commit.reference.len(), 7);
+        assert!(commit
+            .display
+            .starts_with(&format!("commit {} init", commit.reference)));
+
+        let branches: Vec<_> = items
+            .iter()
+            .filter(|item| item.kind == PickKind::Branch)
+            .collect();
+        assert!(branches
+            .iter()
+            .any(|item| item.reference == "feature" && item.display == "branch feature"));
+        assert!(!items.iter().any(|item| item.kind == PickKind::Tag));
+    }
+
+    #[test]
+    fn test_linguist_generated_files_excluded() {
+        use crate::vcs::test_utils::git;
         use std::fs;
 
-        let _lock = crate::vcs::test_utils::cwd_lock()
-            .lock()
-            .unwrap_or_else(|e| e.into_inner());
-        let dir = make_temp_dir("git-exclusion");
-        let original = std::env::current_dir().expect("get cwd");
+        let repo = RepoGuard::new();
+        fs::write(
+            repo.dir.join(".gitattributes"),
+            "*.min.js linguist-generated=true\n",
+        )
+        .expect("write attributes");
+        fs::create_dir_all(repo.dir.join("dist")).expect("create dir");
+        fs::write(repo.dir.join("dist/app.min.js"), "var a=1;\n").expect("write file");
+        fs::write(repo.dir.join("dist/app.js"), "var a = 1;\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "build"]);
 
-        git(&dir, &["init"]);
-        git(&dir, &["config", "user.email", "test@example.com"]);
-        git(&dir, &["config", "user.name", "Test User"]);
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let diff = backend.get_commit("HEAD").expect("should get commit").diff;
+        assert!(diff.contains("dist/app.js"), "diff: {}", diff);
+        assert!(!diff.contains("app.min.js"), "diff: {}", diff);
 
-        // Create files including lock files
-        fs::write(dir.join("test.txt"), "hello\n").expect("write test.txt");
-        fs::write(dir.join("package-lock.json"), "{}\n").expect("write package-lock.json");
-        fs::write(dir.join("Cargo.lock"), "lock\n").expect("write Cargo.lock");
+        fs::write(repo.dir.join("dist/app.min.js"), "var a=2;\n").expect("write file");
+        fs::write(repo.dir.join("dist/app.js"), "var a = 2;\n").expect("write file");
+        let diff = backend
+            .get_working_tree_diff(false)
+            .expect("should diff working tree");
+        assert!(diff.contains("dist/app.js"), "diff: {}", diff);
+        assert!(!diff.contains("app.min.js"), "diff: {}", diff);
+    }
 
-        git(&dir, &["add", "."]);
-        git(&dir, &["commit", "-m", "init with lock files"]);
+    #[test]
+    fn test_pure_rename_detected_by_default() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-        std::env::set_current_dir(&dir).expect("set cwd");
+        let repo = RepoGuard::new();
+        let body: String = (1..=10).map(|i| format!("body line {}\n", i)).collect();
+        fs::write(repo.dir.join("a.txt"), &body).expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "add a"]);
+        fs::rename(repo.dir.join("a.txt"), repo.dir.join("b.txt")).expect("rename file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "rename a to b"]);
 
         let backend = GitBackend::from_cwd().expect("should open repo");
-        let info = backend.get_commit("HEAD").expect("should get commit");
-
-        // Diff should contain test.txt but NOT lock files
-        assert!(
-            info.diff.contains("test.txt"),
-            "diff should contain test.txt"
-        );
-        assert!(
-            !info.diff.contains("package-lock.json"),
-            "diff should NOT contain package-lock.json"
-        );
+        let diff = backend.get_commit("HEAD").expect("should get commit").diff;
         assert!(
-            !info.diff.contains("Cargo.lock"),
-            "diff should NOT contain Cargo.lock"
+            diff.contains("rename from a.txt\nrename to b.txt"),
+            "got: {}",
+            diff
         );
+        assert!(!diff.contains("body line 1\n"), "got: {}", diff);
 
-        // Cleanup
-        let _ = std::env::set_current_dir(&original);
-        let _ = fs::remove_dir_all(&dir);
+        let range = backend
+            .get_range_diff("HEAD~1", "HEAD", false)
+            .expect("should diff range");
+        assert!(range.contains("rename from a.txt"), "got: {}", range);
+
+        let plain = GitBackend::from_cwd()
+            .expect("should open repo")
+            .with_rename_detection(None)
+            .get_commit("HEAD")
+            .expect("should get commit")
+            .diff;
+        assert_eq!(plain.matches("body line 1\n").count(), 2, "got: {}", plain);
     }
 
     #[test]
-    fn test_working_tree_diff_excludes_lock_files() {
-        use crate::vcs::test_utils::{git, make_temp_dir};
+    fn test_working_tree_changed_files_many_files() {
+        use crate::vcs::test_utils::git;
         use std::fs;
 
-        let _lock = crate::vcs::test_utils::cwd_lock()
-            .lock()
-            .unwrap_or_else(|e| e.into_inner());
-        let dir = make_temp_dir("git-wt-exclusion");
-        let original = std::env::current_dir().expect("get cwd");
+        let repo = RepoGuard::new();
+        for dir in 0..10 {
+            let dir = repo.dir.join(format!("dir{}", dir));
+            fs::create_dir_all(&dir).expect("create dir");
+            for file in 0..50 {
+                fs::write(dir.join(format!("f{}.txt", file)), format!("{}\n", file))
+                    .expect("write file");
+            }
+        }
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "many files"]);
+        let index_before = fs::read(repo.dir.join(".git/index")).expect("read index");
 
-        git(&dir, &["init"]);
-        git(&dir, &["config", "user.email", "test@example.com"]);
-        git(&dir, &["config", "user.name", "Test User"]);
+        fs::write(repo.dir.join("dir3/f7.txt"), "changed\n").expect("write file");
+        fs::remove_file(repo.dir.join("dir9/f49.txt")).expect("remove file");
+        fs::write(repo.dir.join("dir0/new.txt"), "new\n").expect("write file");
 
-        // Initial commit
-        fs::write(dir.join("test.txt"), "hello\n").expect("write test.txt");
-        fs::write(dir.join("package-lock.json"), "{}\n").expect("write package-lock.json");
-        git(&dir, &["add", "."]);
-        git(&dir, &["commit", "-m", "init"]);
+        let backend = GitBackend::from_cwd().expect("should open repo");
+        let mut files = backend
+            .get_working_tree_changed_files()
+            .expect("should list files");
+        files.sort();
+        assert_eq!(files, vec!["dir0/new.txt", "dir3/f7.txt", "dir9/f49.txt"]);
+        assert_eq!(
+            fs::read(repo.dir.join(".git/index")).expect("read index"),
+            index_before,
+            "status must not rewrite the index"
+        );
+    }
 
-        // Modify both files
-        fs::write(dir.join("test.txt"), "world\n").expect("modify test.txt");
-        fs::write(dir.join("package-lock.json"), "{\"v\": 2}\n").expect("modify package-lock.json");
+    #[test]
+    fn test_get_commit_diff_for_languages() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
 
-        std::env::set_current_dir(&dir).expect("set cwd");
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("lib.rs"), "pub fn answer() -> u32 { 42 }\n").expect("write file");
+        fs::write(repo.dir.join("app.ts"), "export const answer = 42;\n").expect("write file");
+        fs::write(repo.dir.join("notes"), "no extension\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "add rust and ts"]);
 
         let backend = GitBackend::from_cwd().expect("should open repo");
         let diff = backend
-            .get_working_tree_diff(false)
-            .expect("should get diff");
-
-        // Diff should contain test.txt but NOT package-lock.json
+            .get_commit_diff_for_languages("HEAD", &["rust"])
+            .expect("should filter diff");
+        assert!(diff.contains("+++ b/lib.rs"), "diff: {}", diff);
+        assert!(diff.contains("+pub fn answer()"), "diff: {}", diff);
+        assert!(!diff.contains("app.ts"), "diff: {}", diff);
+        assert!(!diff.contains("notes"), "diff: {}", diff);
+
+        let both = backend
+            .get_commit_diff_for_languages("HEAD", &["Rust", "typescript"])
+            .expect("should filter diff");
         assert!(
-            diff.contains("test.txt"),
-            "working tree diff should contain test.txt"
+            both.contains("lib.rs") && both.contains("app.ts"),
+            "diff: {}",
+            both
         );
-        assert!(
-            !diff.contains("package-lock.json"),
-            "working tree diff should NOT contain package-lock.json"
+        assert_eq!(
+            backend
+                .get_commit_diff_for_languages("HEAD", &["python"])
+                .expect("should filter diff"),
+            ""
         );
 
-        // Cleanup
-        let _ = std::env::set_current_dir(&original);
-        let _ = fs::remove_dir_all(&dir);
+        // A rename into a matching language shows as the rename, not an add
+        fs::rename(repo.dir.join("notes"), repo.dir.join("notes.rs")).expect("rename file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "rename notes"]);
+        let renamed = backend
+            .get_commit_diff_for_languages("HEAD", &["rust"])
+            .expect("should filter diff");
+        assert!(renamed.contains("rename from notes\n"), "diff: {}", renamed);
+        assert!(
+            renamed.contains("rename to notes.rs\n"),
+            "diff: {}",
+            renamed
+        );
+        assert!(!renamed.contains("new file mode"), "diff: {}", renamed);
     }
 
     #[test]
-    fn test_get_working_tree_diff_empty() {
-        let _repo = RepoGuard::new();
+    fn test_diff_stats_exclude_lock_files() {
+        use crate::vcs::test_utils::git;
+        use std::fs;
+
+        let repo = RepoGuard::new();
+        fs::write(repo.dir.join("a.txt"), "one\ntwo\nthree\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "add a"]);
+
+        fs::write(repo.dir.join("a.txt"), "one\n2\n").expect("write file");
+        fs::write(repo.dir.join("b.txt"), "new\nfile\n").expect("write file");
+        fs::write(repo.dir.join("Cargo.lock"), "x\ny\nz\n").expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "edit a, add b"]);
+
         let backend = GitBackend::from_cwd().expect("should open repo");
+        let expected = DiffStats {
+            files_changed: 2,
+            insertions: 3,
+            deletions: 2,
+        };
+        assert_eq!(
+            backend.get_diff_stats("HEAD").expect("should get stats"),
+            expected
+        );
+        let info = backend.get_commit("HEAD").expect("should get commit");
+        assert_eq!(info.stats, expected);
+        assert_eq!(
+            expected.to_string(),
+            "2 files changed, 3 insertions(+), 2 deletions(-)"
+        );
+        assert_eq!(
+            DiffStats {
+                files_changed: 1,
+                insertions: 1,
+                deletions: 0,
+            }
+            .to_string(),
+            "1 file changed, 1 insertion(+)"
+        );
 
-        // Clean working tree should return empty string
-        let diff = backend
-            .get_working_tree_diff(false)
-            .expect("should succeed on clean tree");
-        assert!(
-            diff.is_empty(),
-            "clean working tree should return empty diff"
+        // A pure rename is one file with no changed lines, as in git
+        let body: String = (1..=10).map(|i| format!("body line {}\n", i)).collect();
+        fs::write(repo.dir.join("c.txt"), &body).expect("write file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "add c"]);
+        fs::rename(repo.dir.join("c.txt"), repo.dir.join("d.txt")).expect("rename file");
+        git(&repo.dir, &["add", "."]);
+        git(&repo.dir, &["commit", "-m", "rename c to d"]);
+        let renamed = DiffStats {
+            files_changed: 1,
+            insertions: 0,
+            deletions: 0,
+        };
+        assert_eq!(
+            backend.get_diff_stats("HEAD").expect("should get stats"),
+            renamed
+        );
+        assert_eq!(
+            backend.get_commit("HEAD").expect("should get commit").stats,
+            renamed
         );
     }
 
     #[test]
-    fn test_get_range_diff_identical_commits() {
-        let _repo = RepoGuard::new();
+    fn test_get_commit_signature() {
+        let repo = RepoGuard::new();
         let backend = GitBackend::from_cwd().expect("should open repo");
+        assert_eq!(
+            backend
+                .get_commit_signature("HEAD")
+                .expect("should read signature"),
+            None
+        );
 
-        // Diff of HEAD..HEAD should be empty
-        let diff = backend
-            .get_range_diff("HEAD", "HEAD", false)
-            .expect("should succeed for identical commits");
-        assert!(diff.is_empty(), "diff of identical commits should be empty");
+        // Sign a commit with a placeholder signature; no GPG needed to read it
+        let signature = "-----BEGIN PGP SIGNATURE-----\n\nfake\n-----END PGP SIGNATURE-----";
+        let signed_id = {
+            let git_repo = Repository::open(&repo.dir).expect("open repo");
+            let head = git_repo.head().unwrap().peel_to_commit().unwrap();
+            let sig = Signature::now("Test User", "test@example.com").unwrap();
+            let content = git_repo
+                .commit_create_buffer(&sig, &sig, "signed", &head.tree().unwrap(), &[&head])
+                .expect("create commit buffer");
+            let content = content.as_str().expect("utf-8 commit").to_string();
+            let id = git_repo
+                .commit_signed(&content, signature, None)
+                .expect("create signed commit");
+            (id, content)
+        };
+
+        let (found, signed_data) = backend
+            .get_commit_signature(&signed_id.0.to_string())
+            .expect("should read signature")
+            .expect("commit is signed");
+        assert_eq!(found, signature);
+        assert_eq!(signed_data, signed_id.1);
     }
 
     #[test]
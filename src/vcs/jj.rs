@@ -32,18 +32,11 @@ use jj_lib::tree_merge::MergeOptions;
 use jj_lib::workspace::{default_working_copy_factories, Workspace};
 use pollster::FutureExt;
 
-use super::backend::{CommitInfo, StackedCommitInfo, VcsBackend, VcsError};
-
-/// Files to exclude from diff output (same as GIT_DIFF_EXCLUSIONS in git_entity).
-const DIFF_EXCLUDED_FILES: &[&str] = &[
-    "package-lock.json",
-    "yarn.lock",
-    "pnpm-lock.yaml",
-    "Cargo.lock",
-];
-
-/// Path patterns to exclude from diff output.
-const DIFF_EXCLUDED_PATTERNS: &[&str] = &["node_modules/"];
+use super::backend::{
+    Capabilities, CommitInfo, DiffConfig, DiffStats, StackedCommitInfo, VcsBackend, VcsError,
+};
+use super::exclude::{is_linguist_generated, PathFilter};
+use super::settings::RepoSettings;
 
 /// Detect git-style refs and suggest jj equivalents.
 /// Returns Some(jj_suggestion) if git syntax detected.
@@ -90,29 +83,16 @@ fn truncate_hash(hash: &str, max_len: usize) -> &str {
     &hash[..max_len.min(hash.len())]
 }
 
-/// Check if a path should be excluded from diff output.
-fn should_exclude_path(path: &str) -> bool {
-    // Check exact file matches
-    if let Some(filename) = path.rsplit('/').next() {
-        if DIFF_EXCLUDED_FILES.contains(&filename) {
-            return true;
-        }
-    }
-    // Check pattern matches
-    for pattern in DIFF_EXCLUDED_PATTERNS {
-        if path.contains(pattern) {
-            return true;
-        }
-    }
-    false
-}
-
 /// Jujutsu backend using jj-lib for native repo access.
 pub struct JjBackend {
     workspace: Workspace,
     repo: Arc<ReadonlyRepo>,
     settings: UserSettings,
     workspace_path: std::path::PathBuf,
+    /// Exclusion rules shared with the git backend
+    paths: PathFilter,
+    /// Colocated git repository, consulted for `.gitattributes`
+    git_repo: Option<git2::Repository>,
 }
 
 impl JjBackend {
@@ -138,14 +118,46 @@ impl JjBackend {
             .load_at_head()
             .map_err(|e| VcsError::Other(format!("failed to load repo: {}", e)))?;
 
+        let root = workspace.workspace_root().to_path_buf();
+        let paths = PathFilter::new(Some(&root), RepoSettings::load(&root)?.excludes);
+        let git_repo = git2::Repository::open(&root).ok();
+
         Ok(JjBackend {
             workspace,
             repo,
             settings,
             workspace_path: workspace_path.to_path_buf(),
+            paths,
+            git_repo,
         })
     }
 
+    /// Check if a path is excluded by the rules shared with the git backend
+    /// or, in colocated workspaces, marked `linguist-generated`.
+    fn is_excluded_path(&self, path: &str) -> bool {
+        self.paths.is_excluded(path)
+            || self
+                .git_repo
+                .as_ref()
+                .is_some_and(|repo| is_linguist_generated(repo, path))
+    }
+
+    /// Exclude paths matching any of `globs` from diffs, like
+    /// `GitBackend::with_exclude_globs`.
+    #[allow(dead_code)] // Not exposed on the CLI yet
+    pub fn with_exclude_globs(mut self, globs: Vec<String>) -> Result<Self, VcsError> {
+        self.paths.set_globs(&globs)?;
+        Ok(self)
+    }
+
+    /// Stop applying the built-in excludes, like
+    /// `GitBackend::clear_default_excludes`.
+    #[allow(dead_code)] // Not exposed on the CLI yet
+    pub fn clear_default_excludes(mut self) -> Self {
+        self.paths.clear_defaults();
+        self
+    }
+
     /// Create RevsetParseContext and call the provided function with it.
     /// This handles the lifetime complexity of the context's internal references.
     fn with_revset_context<T, F>(&self, f: F) -> Result<T, VcsError>
@@ -220,7 +232,17 @@ impl JjBackend {
     }
 
     /// Generate a unified diff for a commit (comparing to its first parent).
-    fn generate_diff(&self, commit: &Commit) -> Result<String, VcsError> {
+    fn generate_diff(&self, commit: &Commit, context_lines: usize) -> Result<String, VcsError> {
+        Ok(self.generate_diff_with_stats(commit, context_lines)?.0)
+    }
+
+    /// Generate a commit's unified diff along with its totals, counted from
+    /// the tree diff as the hunks are rendered.
+    fn generate_diff_with_stats(
+        &self,
+        commit: &Commit,
+        context_lines: usize,
+    ) -> Result<(String, DiffStats), VcsError> {
         let repo = self.repo.as_ref();
 
         // Get parent tree (or empty tree for root commits)
@@ -240,6 +262,7 @@ impl JjBackend {
 
         // Generate diff output
         let mut diff_output = String::new();
+        let mut stats = DiffStats::default();
 
         // Use the tree diff stream to iterate over changes.
         // Note: We use pollster::block_on() because lumen is a single-threaded CLI tool
@@ -255,8 +278,8 @@ impl JjBackend {
 
             let path_str = entry.path.as_internal_file_string();
 
-            // Skip excluded files (same as GIT_DIFF_EXCLUSIONS)
-            if should_exclude_path(path_str) {
+            // Skip excluded files (rules shared with the git backend)
+            if self.is_excluded_path(path_str) {
                 continue;
             }
 
@@ -264,46 +287,63 @@ impl JjBackend {
             let old_content = self.get_content_from_value(repo, &entry.path, &diff.before)?;
             let new_content = self.get_content_from_value(repo, &entry.path, &diff.after)?;
 
-            self.format_diff_entry(&mut diff_output, path_str, &old_content, &new_content);
+            if let Some((insertions, deletions)) = self.format_diff_entry(
+                &mut diff_output,
+                path_str,
+                &old_content,
+                &new_content,
+                context_lines,
+            ) {
+                stats.files_changed += 1;
+                stats.insertions += insertions;
+                stats.deletions += deletions;
+            }
         }
 
-        Ok(diff_output)
+        Ok((diff_output, stats))
     }
 
-    /// Format a single diff entry
+    /// Format a single diff entry.
+    /// Returns the (insertions, deletions) rendered, or None when the entry
+    /// shows no change.
     fn format_diff_entry(
         &self,
         output: &mut String,
         path_str: &str,
         old_content: &Option<String>,
         new_content: &Option<String>,
-    ) {
+        context_lines: usize,
+    ) -> Option<(usize, usize)> {
         if old_content.is_none() && new_content.is_some() {
             // Added file
             output.push_str(&format!("diff --git a/{} b/{}\n", path_str, path_str));
             output.push_str("new file mode 100644\n");
             output.push_str("--- /dev/null\n");
             output.push_str(&format!("+++ b/{}\n", path_str));
-            if let Some(content) = new_content {
-                self.format_hunk(output, "", content);
-            }
+            new_content
+                .as_ref()
+                .map(|content| self.format_hunk(output, "", content, context_lines))
         } else if old_content.is_some() && new_content.is_none() {
             // Deleted file
             output.push_str(&format!("diff --git a/{} b/{}\n", path_str, path_str));
             output.push_str("deleted file mode 100644\n");
             output.push_str(&format!("--- a/{}\n", path_str));
             output.push_str("+++ /dev/null\n");
-            if let Some(content) = old_content {
-                self.format_hunk(output, content, "");
-            }
+            old_content
+                .as_ref()
+                .map(|content| self.format_hunk(output, content, "", context_lines))
         } else if let (Some(old), Some(new)) = (old_content, new_content) {
             if old != new {
                 // Modified file
                 output.push_str(&format!("diff --git a/{} b/{}\n", path_str, path_str));
                 output.push_str(&format!("--- a/{}\n", path_str));
                 output.push_str(&format!("+++ b/{}\n", path_str));
-                self.format_hunk(output, old, new);
+                Some(self.format_hunk(output, old, new, context_lines))
+            } else {
+                None
             }
+        } else {
+            None
         }
     }
 
@@ -381,11 +421,15 @@ impl JjBackend {
     }
 
     /// Format a unified diff using jj-lib's proper diff algorithm.
-    /// Produces hunks with context lines for better readability.
-    fn format_hunk(&self, output: &mut String, old: &str, new: &str) {
-        /// Number of context lines around changes (matches git default of 3)
-        const CONTEXT_LINES: usize = 3;
-
+    /// Produces hunks with `context_lines` lines of context around changes.
+    /// Returns the number of (added, removed) lines.
+    fn format_hunk(
+        &self,
+        output: &mut String,
+        old: &str,
+        new: &str,
+        context_lines: usize,
+    ) -> (usize, usize) {
         // Use jj-lib's diff algorithm
         let hunks = diff([old.as_bytes(), new.as_bytes()]);
 
@@ -403,6 +447,8 @@ impl JjBackend {
         let mut hunk_old_count = 0usize;
         let mut hunk_new_count = 0usize;
         let mut in_hunk = false;
+        let mut added = 0usize;
+        let mut removed = 0usize;
 
         // Helper to count lines in byte slice
         fn count_lines(content: &[u8]) -> usize {
@@ -432,14 +478,14 @@ impl JjBackend {
 
                     if in_hunk {
                         // Add context lines after a change
-                        for line in lines.iter().take(CONTEXT_LINES) {
+                        for line in lines.iter().take(context_lines) {
                             pending_output.push_str(&format!(" {}\n", line));
                             hunk_old_count += 1;
                             hunk_new_count += 1;
                         }
 
-                        // If we have more than CONTEXT_LINES, end this hunk
-                        if line_count > CONTEXT_LINES * 2 {
+                        // If the run is longer than both contexts, end this hunk
+                        if line_count > context_lines * 2 {
                             // Flush current hunk
                             output.push_str(&format!(
                                 "@@ -{},{} +{},{} @@\n",
@@ -474,13 +520,13 @@ impl JjBackend {
                     if !in_hunk {
                         // Start new hunk with context
                         in_hunk = true;
-                        hunk_old_start = old_pos.saturating_sub(CONTEXT_LINES) + 1;
-                        hunk_new_start = new_pos.saturating_sub(CONTEXT_LINES) + 1;
+                        hunk_old_start = old_pos.saturating_sub(context_lines) + 1;
+                        hunk_new_start = new_pos.saturating_sub(context_lines) + 1;
                         hunk_old_count = 0;
                         hunk_new_count = 0;
 
                         // Add leading context from old file
-                        let context_start = old_pos.saturating_sub(CONTEXT_LINES);
+                        let context_start = old_pos.saturating_sub(context_lines);
                         for i in context_start..old_pos {
                             if i < old_lines.len() {
                                 pending_output.push_str(&format!(" {}\n", old_lines[i]));
@@ -494,12 +540,14 @@ impl JjBackend {
                     for_each_line(old_content, |line| {
                         pending_output.push_str(&format!("-{}\n", line));
                         hunk_old_count += 1;
+                        removed += 1;
                     });
 
                     // Add added lines
                     for_each_line(new_content, |line| {
                         pending_output.push_str(&format!("+{}\n", line));
                         hunk_new_count += 1;
+                        added += 1;
                     });
 
                     old_pos += old_content_lines;
@@ -530,7 +578,7 @@ impl JjBackend {
 
         // Handle edge case: empty old or new content
         if hunks.is_empty() || (old.is_empty() && new.is_empty()) {
-            return;
+            return (added, removed);
         }
 
         // If no hunks were generated but content differs, fall back to simple diff
@@ -552,12 +600,18 @@ impl JjBackend {
             for line in new.lines() {
                 output.push_str(&format!("+{}\n", line));
             }
+            return (new_line_count, old_line_count);
         }
+        (added, removed)
     }
 }
 
 impl VcsBackend for JjBackend {
-    fn get_commit(&self, reference: &str) -> Result<CommitInfo, VcsError> {
+    fn get_commit_with(
+        &self,
+        reference: &str,
+        config: &DiffConfig,
+    ) -> Result<CommitInfo, VcsError> {
         let reference = reference.trim();
 
         // Resolve the commit using revset
@@ -569,33 +623,75 @@ impl VcsBackend for JjBackend {
         let message = commit.description().to_string();
         let author_sig = commit.author();
         let author = format!("{} <{}>", author_sig.name, author_sig.email);
+        let committer_sig = commit.committer();
+        let committer = format!("{} <{}>", committer_sig.name, committer_sig.email);
 
         // Format date from author timestamp
         let date = chrono::DateTime::from_timestamp_millis(author_sig.timestamp.timestamp.0)
             .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
             .unwrap_or_default();
 
+        // The root commit has no message, so commits on it have no parent summary
+        let parent_summary = match commit.parent_ids().first() {
+            Some(parent_id) if parent_id != self.repo.store().root_commit_id() => {
+                let parent = self
+                    .repo
+                    .store()
+                    .get_commit(parent_id)
+                    .map_err(|e| VcsError::Other(format!("failed to get parent: {}", e)))?;
+                Some(
+                    parent
+                        .description()
+                        .lines()
+                        .next()
+                        .unwrap_or("")
+                        .to_string(),
+                )
+            }
+            _ => None,
+        };
+
         // Generate diff
-        let diff = self.generate_diff(&commit)?;
+        let (diff, stats) =
+            self.generate_diff_with_stats(&commit, config.context_lines as usize)?;
 
         Ok(CommitInfo {
             commit_id,
             change_id: Some(change_id),
             message,
+            parent_summary,
             diff,
+            stats,
             author,
+            committer,
             date,
         })
     }
 
-    fn get_working_tree_diff(&self, _staged: bool) -> Result<String, VcsError> {
+    fn get_working_tree_diff_with(
+        &self,
+        _staged: bool,
+        config: &DiffConfig,
+    ) -> Result<String, VcsError> {
         // For jj, working tree changes are part of @ commit
         // Get diff of @ vs @-
         let wc_commit = self.resolve_single_commit("@")?;
-        self.generate_diff(&wc_commit)
+        self.generate_diff(&wc_commit, config.context_lines as usize)
+    }
+
+    fn working_snapshot_diff(&self) -> Result<String, VcsError> {
+        // The working copy is already a commit, so there's nothing to combine
+        self.get_working_tree_diff(false)
     }
 
-    fn get_range_diff(&self, from: &str, to: &str, _three_dot: bool) -> Result<String, VcsError> {
+    fn get_range_diff_with(
+        &self,
+        from: &str,
+        to: &str,
+        _three_dot: bool,
+        config: &DiffConfig,
+    ) -> Result<String, VcsError> {
+        let context_lines = config.context_lines as usize;
         // Get diff between two commits
         let from_commit = self.resolve_single_commit(from)?;
         let to_commit = self.resolve_single_commit(to)?;
@@ -616,15 +712,21 @@ impl VcsBackend for JjBackend {
 
             let path_str = entry.path.as_internal_file_string();
 
-            // Skip excluded files (same as GIT_DIFF_EXCLUSIONS)
-            if should_exclude_path(path_str) {
+            // Skip excluded files (rules shared with the git backend)
+            if self.is_excluded_path(path_str) {
                 continue;
             }
 
             let old_content = self.get_content_from_value(repo, &entry.path, &diff.before)?;
             let new_content = self.get_content_from_value(repo, &entry.path, &diff.after)?;
 
-            self.format_diff_entry(&mut diff_output, path_str, &old_content, &new_content);
+            self.format_diff_entry(
+                &mut diff_output,
+                path_str,
+                &old_content,
+                &new_content,
+                context_lines,
+            );
         }
 
         Ok(diff_output)
@@ -653,8 +755,8 @@ impl VcsBackend for JjBackend {
         let mut files = Vec::new();
         for entry in entries {
             let path_str = entry.path.as_internal_file_string();
-            // Skip excluded files (same as GIT_DIFF_EXCLUSIONS)
-            if !should_exclude_path(path_str) {
+            // Skip excluded files (rules shared with the git backend)
+            if !self.is_excluded_path(path_str) {
                 files.push(path_str.to_string());
             }
         }
@@ -819,8 +921,8 @@ impl VcsBackend for JjBackend {
         let mut files = Vec::new();
         for entry in entries {
             let path_str = entry.path.as_internal_file_string();
-            // Skip excluded files (same as GIT_DIFF_EXCLUSIONS)
-            if !should_exclude_path(path_str) {
+            // Skip excluded files (rules shared with the git backend)
+            if !self.is_excluded_path(path_str) {
                 files.push(path_str.to_string());
             }
         }
@@ -853,8 +955,8 @@ impl VcsBackend for JjBackend {
         let mut files = Vec::new();
         for entry in entries {
             let path_str = entry.path.as_internal_file_string();
-            // Skip excluded files (same as GIT_DIFF_EXCLUSIONS)
-            if !should_exclude_path(path_str) {
+            // Skip excluded files (rules shared with the git backend)
+            if !self.is_excluded_path(path_str) {
                 files.push(path_str.to_string());
             }
         }
@@ -973,6 +1075,15 @@ impl VcsBackend for JjBackend {
     fn name(&self) -> &'static str {
         "jj"
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            change_ids: true,
+            stash: false,
+            signing: false,
+            partial_commit: false,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1247,6 +1358,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_diff_config_zero_context() {
+        use std::fs;
+
+        let Some(repo) = JjRepoGuard::new() else {
+            eprintln!("Skipping test: jj not available");
+            return;
+        };
+
+        fs::write(repo.dir.join("f.txt"), "one\ntwo\nthree\nfour\nfive\n").expect("write file");
+        crate::vcs::test_utils::jj(&repo.dir, &["new"]);
+        fs::write(repo.dir.join("f.txt"), "one\ntwo\nTHREE\nfour\nfive\n").expect("write file");
+        crate::vcs::test_utils::jj(&repo.dir, &["status"]); // Snapshot
+
+        let backend = JjBackend::new(&repo.dir).expect("should load backend");
+        let diff = backend
+            .get_working_tree_diff_with(false, &DiffConfig { context_lines: 0 })
+            .expect("should diff working copy");
+        assert!(diff.contains("-three\n+THREE\n"), "diff: {}", diff);
+        assert!(!diff.contains(" two\n"), "no context expected: {}", diff);
+        assert!(!diff.contains(" four\n"), "no context expected: {}", diff);
+    }
+
     #[test]
     fn test_get_range_diff() {
         use std::fs;
@@ -1346,6 +1480,121 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_lumenignore_per_directory() {
+        use std::fs;
+
+        let Some(repo) = JjRepoGuard::new() else {
+            eprintln!("Skipping test: jj not available");
+            return;
+        };
+
+        for dir in ["apps/web", "apps/api"] {
+            fs::create_dir_all(repo.dir.join(dir)).expect("create dir");
+            fs::write(repo.dir.join(dir).join("types.css.d.ts"), "types\n").expect("write file");
+            fs::write(repo.dir.join(dir).join("schema.sql"), "schema\n").expect("write file");
+        }
+        fs::write(repo.dir.join("apps/web/.lumenignore"), "*.css.d.ts\n").expect("write ignore");
+        fs::write(repo.dir.join("apps/api/.lumenignore"), "*.sql\n").expect("write ignore");
+        crate::vcs::test_utils::jj(&repo.dir, &["status"]); // Snapshot
+
+        let backend = JjBackend::new(&repo.dir).expect("should load backend");
+        let diff = backend.get_commit("@").expect("should get commit").diff;
+        assert!(!diff.contains("apps/web/types.css.d.ts"), "got: {}", diff);
+        assert!(diff.contains("apps/web/schema.sql"), "got: {}", diff);
+        assert!(!diff.contains("apps/api/schema.sql"), "got: {}", diff);
+        assert!(diff.contains("apps/api/types.css.d.ts"), "got: {}", diff);
+    }
+
+    #[test]
+    fn test_exclude_globs_and_cleared_defaults() {
+        use std::fs;
+
+        let Some(repo) = JjRepoGuard::new() else {
+            eprintln!("Skipping test: jj not available");
+            return;
+        };
+
+        fs::create_dir_all(repo.dir.join("api")).expect("create dir");
+        fs::write(repo.dir.join("api/foo.pb.go"), "package api\n").expect("write file");
+        fs::write(repo.dir.join("main.go"), "package main\n").expect("write file");
+        fs::write(repo.dir.join("Cargo.lock"), "lock\n").expect("write file");
+        crate::vcs::test_utils::jj(&repo.dir, &["status"]); // Snapshot
+
+        let backend = JjBackend::new(&repo.dir)
+            .expect("should load backend")
+            .with_exclude_globs(vec!["*.pb.go".to_string()])
+            .expect("valid globs");
+        let diff = backend.get_commit("@").expect("should get commit").diff;
+        assert!(diff.contains("main.go"), "diff: {}", diff);
+        assert!(!diff.contains("foo.pb.go"), "diff: {}", diff);
+        assert!(!diff.contains("Cargo.lock"), "diff: {}", diff);
+
+        let diff = backend
+            .clear_default_excludes()
+            .get_commit("@")
+            .expect("should get commit")
+            .diff;
+        assert!(diff.contains("Cargo.lock"), "diff: {}", diff);
+    }
+
+    #[test]
+    fn test_linguist_generated_excluded_when_colocated() {
+        use std::fs;
+
+        let Some(repo) = JjRepoGuard::new() else {
+            eprintln!("Skipping test: jj not available");
+            return;
+        };
+        if !repo.dir.join(".git").exists() {
+            eprintln!("Skipping test: jj workspace is not colocated");
+            return;
+        }
+
+        fs::write(
+            repo.dir.join(".gitattributes"),
+            "*.min.js linguist-generated=true\n",
+        )
+        .expect("write attributes");
+        fs::create_dir_all(repo.dir.join("dist")).expect("create dir");
+        fs::write(repo.dir.join("dist/app.min.js"), "var a=1;\n").expect("write file");
+        fs::write(repo.dir.join("dist/app.js"), "var a = 1;\n").expect("write file");
+        crate::vcs::test_utils::jj(&repo.dir, &["status"]); // Snapshot
+
+        let backend = JjBackend::new(&repo.dir).expect("should load backend");
+        let diff = backend.get_commit("@").expect("should get commit").diff;
+        assert!(diff.contains("dist/app.js"), "diff: {}", diff);
+        assert!(!diff.contains("app.min.js"), "diff: {}", diff);
+    }
+
+    #[test]
+    fn test_diff_stats_exclude_lock_files() {
+        use std::fs;
+
+        let Some(repo) = JjRepoGuard::new() else {
+            eprintln!("Skipping test: jj not available");
+            return;
+        };
+
+        fs::write(repo.dir.join("a.txt"), "one\ntwo\nthree\n").expect("write file");
+        crate::vcs::test_utils::jj(&repo.dir, &["commit", "-m", "add a"]);
+        fs::write(repo.dir.join("a.txt"), "one\n2\n").expect("write file");
+        fs::write(repo.dir.join("b.txt"), "new\nfile\n").expect("write file");
+        fs::write(repo.dir.join("Cargo.lock"), "x\ny\nz\n").expect("write file");
+        crate::vcs::test_utils::jj(&repo.dir, &["status"]); // Snapshot
+
+        let backend = JjBackend::new(&repo.dir).expect("should load backend");
+        let stats = backend.get_commit("@").expect("should get commit").stats;
+        assert_eq!(
+            stats,
+            DiffStats {
+                files_changed: 2,
+                insertions: 3,
+                deletions: 2,
+            }
+        );
+    }
+
     #[test]
     fn test_commit_info_field_format() {
         let Some(repo) = JjRepoGuard::new() else {
@@ -0,0 +1,53 @@
+//! File language detection shared by the diff view and the VCS backends.
+
+use std::path::Path;
+
+/// Detect a file's language from its extension
+pub fn detect_language(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    let language = match ext.as_str() {
+        "rs" => "rust",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "tsx",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "jsx",
+        "py" => "python",
+        "go" => "go",
+        "rb" => "ruby",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "swift" => "swift",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "cpp",
+        "cs" => "csharp",
+        "css" => "css",
+        "html" | "htm" => "html",
+        "json" => "json",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "md" | "markdown" => "markdown",
+        "sh" | "bash" | "zsh" => "bash",
+        "sql" => "sql",
+        _ => return None,
+    };
+    Some(language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language_by_extension() {
+        assert_eq!(detect_language(Path::new("src/main.rs")), Some("rust"));
+        assert_eq!(detect_language(Path::new("web/app.ts")), Some("typescript"));
+        assert_eq!(detect_language(Path::new("script.PY")), Some("python"));
+        assert_eq!(detect_language(Path::new("Cargo.toml")), Some("toml"));
+    }
+
+    #[test]
+    fn test_detect_language_unknown() {
+        assert_eq!(detect_language(Path::new("data.xyz")), None);
+        assert_eq!(detect_language(Path::new("Makefile")), None);
+    }
+}
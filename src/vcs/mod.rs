@@ -3,18 +3,28 @@
 //! Provides a unified interface for working with git and jj repositories.
 
 mod backend;
+#[allow(dead_code)] // Changelog output is not consumed by commands yet
+pub mod conventional;
 mod detection;
+mod exclude;
 mod git;
 #[cfg(feature = "jj")]
 mod jj;
+mod language;
+#[allow(dead_code)] // Post-processing helpers, not all used by commands yet
+pub mod patch;
+#[allow(dead_code)] // Not wired into commands yet
+pub mod redact;
+mod settings;
 #[cfg(test)]
 pub mod test_utils;
 
 pub use backend::{CommitInfo, StackedCommitInfo, VcsBackend, VcsError};
-pub use detection::{detect_vcs_type, VcsType};
+pub use detection::{detect_backend, BackendKind};
 pub use git::GitBackend;
 #[cfg(feature = "jj")]
 pub use jj::JjBackend;
+pub use language::detect_language;
 
 use std::path::Path;
 
@@ -36,25 +46,62 @@ impl From<VcsOverride> for VcsBackendType {
     }
 }
 
+/// Whether color output is disabled by a non-empty `NO_COLOR`
+/// (<https://no-color.org>).
+pub fn no_color() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// Remove ANSI SGR color sequences (`\x1b[...m`) from text, such as the
+/// output of `get_commit_log_for_fzf`. Other escape sequences are kept.
+pub fn strip_ansi(s: &str) -> String {
+    let mut output = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("\x1b[") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let params_len = after
+            .find(|c: char| !(c.is_ascii_digit() || c == ';'))
+            .unwrap_or(after.len());
+        if after[params_len..].starts_with('m') {
+            // SGR sequence: drop it
+            rest = &after[params_len + 1..];
+        } else {
+            output.push_str("\x1b[");
+            rest = after;
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
 /// Get the appropriate VCS backend for the current directory.
 ///
 /// If `override_type` is provided, uses that backend type explicitly.
 /// Otherwise auto-detects jj vs git repositories. Prefers jj when both are present (colocated).
+/// With `GIT_DIR` set, git is used even when no `.git` is found under `path`
+/// (e.g. dotfile repos whose git dir lives elsewhere).
 pub fn get_backend(
     path: &Path,
     override_type: Option<VcsBackendType>,
 ) -> Result<Box<dyn VcsBackend>, VcsError> {
-    let vcs_type = override_type.map_or_else(
-        || detect_vcs_type(path),
+    let kind = override_type.map_or_else(
+        || {
+            if std::env::var_os("GIT_DIR").is_some() {
+                BackendKind::Git
+            } else {
+                detect_backend(path)
+            }
+        },
         |ot| match ot {
-            VcsBackendType::Git => VcsType::Git,
-            VcsBackendType::Jj => VcsType::Jj,
+            VcsBackendType::Git => BackendKind::Git,
+            VcsBackendType::Jj => BackendKind::Jj,
         },
     );
 
-    match vcs_type {
-        VcsType::Git => GitBackend::new(path).map(|b| Box::new(b) as Box<dyn VcsBackend>),
-        VcsType::Jj => {
+    match kind {
+        BackendKind::Git => GitBackend::new(path).map(|b| Box::new(b) as Box<dyn VcsBackend>),
+        BackendKind::Jj => {
             #[cfg(feature = "jj")]
             {
                 JjBackend::new(path).map(|b| Box::new(b) as Box<dyn VcsBackend>)
@@ -66,7 +113,7 @@ pub fn get_backend(
                 GitBackend::new(path).map(|b| Box::new(b) as Box<dyn VcsBackend>)
             }
         }
-        VcsType::None => Err(VcsError::NotARepository),
+        BackendKind::None => Err(VcsError::NotARepository),
     }
 }
 
@@ -77,6 +124,20 @@ mod tests {
     #[cfg(feature = "jj")]
     use test_utils::{git, JjRepoGuard};
 
+    #[test]
+    fn test_strip_ansi_removes_log_colors() {
+        let line = "\x1b[33mabc1234\x1b[0m fix the thing \x1b[90m2 hours ago\x1b[0m";
+        assert_eq!(strip_ansi(line), "abc1234 fix the thing 2 hours ago");
+    }
+
+    #[test]
+    fn test_strip_ansi_keeps_plain_and_non_sgr_text() {
+        assert_eq!(strip_ansi("plain text"), "plain text");
+        assert_eq!(strip_ansi("\x1b[1;32mok\x1b[0m"), "ok");
+        // Cursor movement is not SGR and is left alone
+        assert_eq!(strip_ansi("a\x1b[2Kb"), "a\x1b[2Kb");
+    }
+
     #[test]
     fn test_get_backend_in_git_repo() {
         let repo = RepoGuard::new();
@@ -87,6 +148,8 @@ mod tests {
 
     #[test]
     fn test_get_backend_in_non_repo_fails() {
+        // Hold the cwd lock so a concurrent test's GIT_DIR can't leak in
+        let _repo = RepoGuard::new();
         let temp = tempfile::TempDir::new().unwrap();
         let result = get_backend(temp.path(), None);
         assert!(matches!(result, Err(VcsError::NotARepository)));
@@ -125,6 +188,39 @@ mod tests {
         assert!(!commit.commit_id.is_empty());
     }
 
+    #[test]
+    fn test_get_backend_honors_git_dir_outside_work_tree() {
+        use git2::{Repository, Signature};
+
+        // RepoGuard holds the cwd lock, so no other backend test sees GIT_DIR
+        let _repo = RepoGuard::new();
+        let dotfiles = test_utils::make_temp_dir("git-dir-dotfiles");
+        let git_repo = Repository::init_bare(&dotfiles).expect("init bare repo");
+        let sig = Signature::now("Other", "other@example.com").unwrap();
+        let tree = git_repo
+            .find_tree(git_repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        git_repo
+            .commit(Some("HEAD"), &sig, &sig, "dotfiles", &tree, &[])
+            .unwrap();
+        // A work tree without any `.git`
+        let home = test_utils::make_temp_dir("git-dir-home");
+
+        std::env::set_var("GIT_DIR", &dotfiles);
+        std::env::set_var("GIT_WORK_TREE", &home);
+        let backend = get_backend(&home, None);
+        std::env::remove_var("GIT_DIR");
+        std::env::remove_var("GIT_WORK_TREE");
+
+        let backend = backend.expect("should open the GIT_DIR repo");
+        assert_eq!(backend.name(), "git");
+        let commit = backend.get_commit("HEAD").expect("should get commit");
+        assert_eq!(commit.message, "dotfiles");
+
+        let _ = std::fs::remove_dir_all(&dotfiles);
+        let _ = std::fs::remove_dir_all(&home);
+    }
+
     #[test]
     fn test_vcs_override_to_backend_type_conversion() {
         assert_eq!(VcsBackendType::from(VcsOverride::Git), VcsBackendType::Git);
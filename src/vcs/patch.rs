@@ -0,0 +1,648 @@
+//! Structured view over unified diff text.
+//!
+//! Backends emit unified diffs as plain strings. This module parses that text
+//! into files and hunks so post-processing passes can work on structure
+//! instead of re-scanning raw lines.
+
+/// A single file section of a unified diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePatch {
+    /// Header lines before the first hunk (`diff --git`, `index`, `---`,
+    /// `+++`, ...), including notes such as `... [N smaller hunks omitted] ...`
+    /// and the content block of an inlined new file
+    pub header: Vec<String>,
+    /// Path of the file (new path, or old path for deletions)
+    pub path: String,
+    /// Hunks in file order
+    pub hunks: Vec<Hunk>,
+}
+
+/// A single `@@` hunk within a file patch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// The `@@ -a,b +c,d @@` header line
+    pub header: String,
+    /// Body lines, each starting with ' ', '+', '-' or '\', or a
+    /// `... N unchanged lines ...` fold marker standing in for N context lines
+    pub lines: Vec<String>,
+}
+
+/// Number of context lines a `... N unchanged lines ...` fold marker stands
+/// for, or None if `line` isn't one.
+pub fn fold_marker_len(line: &str) -> Option<usize> {
+    line.strip_prefix("... ")?
+        .strip_suffix(" unchanged lines ...")?
+        .parse()
+        .ok()
+}
+
+impl Hunk {
+    /// Added and removed lines, with the origin character stripped.
+    pub fn changed_lines(&self) -> impl Iterator<Item = &str> {
+        self.lines
+            .iter()
+            .filter_map(|line| line.strip_prefix('+').or_else(|| line.strip_prefix('-')))
+    }
+
+    /// Runs of consecutive lines with the given origin ('+' or '-'),
+    /// with the origin character stripped.
+    fn runs(&self, origin: char) -> Vec<Vec<&str>> {
+        let mut runs = Vec::new();
+        let mut current: Vec<&str> = Vec::new();
+        for line in &self.lines {
+            match line.strip_prefix(origin) {
+                Some(content) => current.push(content),
+                None => {
+                    if !current.is_empty() {
+                        runs.push(std::mem::take(&mut current));
+                    }
+                }
+            }
+        }
+        if !current.is_empty() {
+            runs.push(current);
+        }
+        runs
+    }
+}
+
+/// Extract the file path from a file header.
+/// Prefers the `+++ b/` path, falling back to `--- a/` for deletions and
+/// finally to the `diff --git` line.
+fn path_from_header(header: &[String]) -> String {
+    for line in header {
+        if let Some(path) = line.strip_prefix("+++ b/") {
+            return path.to_string();
+        }
+    }
+    for line in header {
+        if let Some(path) = line.strip_prefix("--- a/") {
+            return path.to_string();
+        }
+    }
+    header
+        .first()
+        .and_then(|line| line.strip_prefix("diff --git "))
+        .and_then(|rest| rest.rsplit(" b/").next())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Parse unified diff text into file patches.
+pub fn parse_patch(diff: &str) -> Vec<FilePatch> {
+    let mut files: Vec<FilePatch> = Vec::new();
+
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            files.push(FilePatch {
+                header: vec![line.to_string()],
+                path: String::new(),
+                hunks: Vec::new(),
+            });
+            continue;
+        }
+
+        let Some(file) = files.last_mut() else {
+            // Text before the first file header is not part of any patch
+            continue;
+        };
+
+        if line.starts_with("@@") {
+            file.hunks.push(Hunk {
+                header: line.to_string(),
+                lines: Vec::new(),
+            });
+        } else if let Some(hunk) = file.hunks.last_mut() {
+            hunk.lines.push(line.to_string());
+        } else {
+            file.header.push(line.to_string());
+        }
+    }
+
+    for file in &mut files {
+        file.path = path_from_header(&file.header);
+    }
+
+    files
+}
+
+/// Render file patches back into unified diff text.
+pub fn render_patch(files: &[FilePatch]) -> String {
+    let mut output = String::new();
+    for file in files {
+        for line in &file.header {
+            output.push_str(line);
+            output.push('\n');
+        }
+        for hunk in &file.hunks {
+            output.push_str(&hunk.header);
+            output.push('\n');
+            for line in &hunk.lines {
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+    }
+    output
+}
+
+/// Produce a unified diff between two strings, without a repository.
+/// Identical inputs give an empty string.
+pub fn diff_strings(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    similar::TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(old_label, new_label)
+        .to_string()
+}
+
+/// One line of a combined diff: a line of the result, or a line one or
+/// more parents had that the result lost.
+struct CombinedRow<'a> {
+    text: &'a str,
+    /// One column per parent: ' ' (same as that parent), '+' or '-'
+    markers: Vec<char>,
+    /// For result lines, the line's index in the result
+    result_line: Option<usize>,
+}
+
+/// Produce the hunks of a `diff --cc` style combined diff of a merge
+/// result against each of its parents, without the file header.
+///
+/// Like git's dense combined diff, only regions where the result differs
+/// from every parent are shown; a region taken verbatim from one parent is
+/// not. Each line carries one marker column per parent, and a line lost
+/// from several parents is shown once with a `-` in each of their columns.
+/// Identical results give an empty string.
+pub fn combined_diff_hunks(parents: &[&str], result: &str, context: usize) -> String {
+    use similar::DiffOp;
+
+    let result_lines: Vec<&str> = result.split_inclusive('\n').collect();
+    let n = result_lines.len();
+
+    // unchanged[i][k]: result line k is unchanged from parent i
+    let mut unchanged = vec![vec![false; n]; parents.len()];
+    // removed[i][k]: lines of parent i lost just before result line k
+    let mut removed: Vec<Vec<Vec<&str>>> = vec![vec![Vec::new(); n + 1]; parents.len()];
+    for (i, parent) in parents.iter().enumerate() {
+        let diff = similar::TextDiff::from_lines(*parent, result);
+        let old = diff.old_slices();
+        for op in diff.ops() {
+            match *op {
+                DiffOp::Equal { new_index, len, .. } => {
+                    unchanged[i][new_index..new_index + len].fill(true);
+                }
+                DiffOp::Delete {
+                    old_index,
+                    old_len,
+                    new_index,
+                }
+                | DiffOp::Replace {
+                    old_index,
+                    old_len,
+                    new_index,
+                    ..
+                } => removed[i][new_index].extend_from_slice(&old[old_index..old_index + old_len]),
+                DiffOp::Insert { .. } => {}
+            }
+        }
+    }
+
+    let lost_row = |text, parent: usize| {
+        let mut markers = vec![' '; parents.len()];
+        markers[parent] = '-';
+        CombinedRow {
+            text,
+            markers,
+            result_line: None,
+        }
+    };
+
+    // Rows in output order: each gap's lost lines, then the result line
+    let mut rows: Vec<(usize, CombinedRow)> = Vec::new();
+    for k in 0..=n {
+        let mut lost_rows: Vec<CombinedRow> = Vec::new();
+        for (i, lost) in removed.iter().enumerate() {
+            // Match this parent's lost lines, in order, to earlier parents'
+            let mut matches = Vec::with_capacity(lost[k].len());
+            let mut next = 0;
+            for text in &lost[k] {
+                let found = lost_rows[next..]
+                    .iter()
+                    .position(|row| row.text == *text)
+                    .map(|offset| next + offset);
+                if let Some(idx) = found {
+                    next = idx + 1;
+                }
+                matches.push(found);
+            }
+
+            // Matched lines gain this parent's column; the rest become new
+            // rows placed before the next matched line
+            let mut old = std::mem::take(&mut lost_rows).into_iter();
+            let mut taken = 0;
+            let mut pending = Vec::new();
+            for (text, found) in lost[k].iter().zip(matches) {
+                let Some(idx) = found else {
+                    pending.push(*text);
+                    continue;
+                };
+                lost_rows.extend(old.by_ref().take(idx - taken));
+                lost_rows.extend(pending.drain(..).map(|text| lost_row(text, i)));
+                let mut row = old.next().expect("matched row exists");
+                row.markers[i] = '-';
+                lost_rows.push(row);
+                taken = idx + 1;
+            }
+            lost_rows.extend(old);
+            lost_rows.extend(pending.into_iter().map(|text| lost_row(text, i)));
+        }
+        rows.extend(lost_rows.into_iter().map(|row| (k, row)));
+        if k < n {
+            let markers = (0..parents.len())
+                .map(|i| if unchanged[i][k] { ' ' } else { '+' })
+                .collect();
+            let row = CombinedRow {
+                text: result_lines[k],
+                markers,
+                result_line: Some(k),
+            };
+            rows.push((k, row));
+        }
+    }
+
+    // Result line ranges around changes that differ from every parent
+    let line_interesting = |k: usize| unchanged.iter().all(|parent| !parent[k]);
+    let gap_interesting = |k: usize| removed.iter().all(|parent| !parent[k].is_empty());
+    let mut windows: Vec<(usize, usize)> = Vec::new();
+    for k in 0..=n {
+        let end = if k < n && line_interesting(k) {
+            k + 1 + context
+        } else if gap_interesting(k) {
+            k + context
+        } else {
+            continue;
+        };
+        let window = (k.saturating_sub(context), end.min(n));
+        match windows.last_mut() {
+            Some(last) if window.0 <= last.1 => last.1 = last.1.max(window.1),
+            _ => windows.push(window),
+        }
+    }
+
+    let consumes = |row: &CombinedRow, i: usize| match row.result_line {
+        Some(_) => row.markers[i] == ' ',
+        None => row.markers[i] == '-',
+    };
+    let in_window = |position: usize, row: &CombinedRow, (start, end): (usize, usize)| {
+        if row.result_line.is_some() {
+            (start..end).contains(&position)
+        } else {
+            // Lost lines right after the window only belong to it at the
+            // end of the file or when they are the change itself
+            (start..end).contains(&position)
+                || (position == end && (end == n || gap_interesting(end)))
+        }
+    };
+
+    let range = |before: usize, count: usize| {
+        let start = if count == 0 { before } else { before + 1 };
+        format!("{},{}", start, count)
+    };
+    let count_into = |counts: &mut [usize], row: &CombinedRow| {
+        for (i, count) in counts.iter_mut().enumerate() {
+            if consumes(row, i) {
+                *count += 1;
+            }
+        }
+    };
+
+    let marker = "@".repeat(parents.len() + 1);
+    let mut output = String::new();
+    // Windows are ordered and disjoint, so rows are scanned once: `cursor`
+    // is the first unscanned row and `consumed` the parent lines before it
+    let mut cursor = 0;
+    let mut consumed = vec![0usize; parents.len()];
+    for window in windows {
+        let Some(first) = rows[cursor..]
+            .iter()
+            .position(|(position, row)| in_window(*position, row, window))
+            .map(|offset| cursor + offset)
+        else {
+            continue;
+        };
+        for (_, row) in &rows[cursor..first] {
+            count_into(&mut consumed, row);
+        }
+        let last = rows[first..]
+            .iter()
+            .position(|(position, row)| !in_window(*position, row, window))
+            .map_or(rows.len(), |offset| first + offset);
+
+        let mut counts = vec![0usize; parents.len()];
+        let mut result_count = 0;
+        let mut body = String::new();
+        for (_, row) in &rows[first..last] {
+            count_into(&mut counts, row);
+            if row.result_line.is_some() {
+                result_count += 1;
+            }
+            body.extend(&row.markers);
+            body.push_str(row.text);
+            if !row.text.ends_with('\n') {
+                body.push('\n');
+            }
+        }
+
+        output.push_str(&marker);
+        for (before, count) in consumed.iter_mut().zip(&counts) {
+            output.push_str(&format!(" -{}", range(*before, *count)));
+            *before += count;
+        }
+        output.push_str(&format!(" +{} {}\n", range(window.0, result_count), marker));
+        output.push_str(&body);
+        cursor = last;
+    }
+    output
+}
+
+/// Keep only the hunks accepted by `keep`, dropping files left without hunks.
+pub fn retain_hunks(diff: &str, keep: impl Fn(&Hunk) -> bool) -> String {
+    let mut files = parse_patch(diff);
+    for file in &mut files {
+        file.hunks.retain(&keep);
+    }
+    files.retain(|file| !file.hunks.is_empty());
+    render_patch(&files)
+}
+
+/// Minimum number of lines for a removed/added run to count as a move.
+/// Shorter runs (a lone `}` or blank line) match across files too easily.
+pub const MIN_MOVED_LINES: usize = 3;
+
+/// A block of lines removed from one file and added verbatim to another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovedBlock {
+    pub from_path: String,
+    pub to_path: String,
+    pub line_count: usize,
+    /// Index of the source hunk as (file, hunk)
+    from_hunk: (usize, usize),
+    /// Index of the destination hunk as (file, hunk)
+    to_hunk: (usize, usize),
+}
+
+/// Find runs of removed lines in one file that reappear verbatim within a
+/// run of added lines in a different file, so a block moved next to new
+/// lines still counts.
+pub fn find_moved_blocks(files: &[FilePatch], min_lines: usize) -> Vec<MovedBlock> {
+    let mut moves = Vec::new();
+
+    for (from_file_idx, from_file) in files.iter().enumerate() {
+        for (from_hunk_idx, from_hunk) in from_file.hunks.iter().enumerate() {
+            for removed in from_hunk.runs('-') {
+                if removed.len() < min_lines {
+                    continue;
+                }
+
+                'search: for (to_file_idx, to_file) in files.iter().enumerate() {
+                    if to_file_idx == from_file_idx {
+                        continue;
+                    }
+                    for (to_hunk_idx, to_hunk) in to_file.hunks.iter().enumerate() {
+                        let found = to_hunk.runs('+').iter().any(|added| {
+                            added
+                                .windows(removed.len())
+                                .any(|window| window == removed.as_slice())
+                        });
+                        if found {
+                            moves.push(MovedBlock {
+                                from_path: from_file.path.clone(),
+                                to_path: to_file.path.clone(),
+                                line_count: removed.len(),
+                                from_hunk: (from_file_idx, from_hunk_idx),
+                                to_hunk: (to_file_idx, to_hunk_idx),
+                            });
+                            break 'search;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    moves
+}
+
+/// Annotate hunks that contain moved blocks.
+///
+/// The note is appended to the hunk header's trailing section
+/// (`@@ -1,5 +0,0 @@ [moved 5 lines to b.rs]`), which keeps line counts
+/// intact so the output is still a valid unified diff.
+pub fn mark_moved_blocks(diff: &str) -> String {
+    let mut files = parse_patch(diff);
+    let moves = find_moved_blocks(&files, MIN_MOVED_LINES);
+    if moves.is_empty() {
+        return diff.to_string();
+    }
+
+    for block in &moves {
+        let (file, hunk) = block.from_hunk;
+        files[file].hunks[hunk].header.push_str(&format!(
+            " [moved {} lines to {}]",
+            block.line_count, block.to_path
+        ));
+        let (file, hunk) = block.to_hunk;
+        files[file].hunks[hunk].header.push_str(&format!(
+            " [moved {} lines from {}]",
+            block.line_count, block.from_path
+        ));
+    }
+
+    render_patch(&files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOVE_DIFF: &str = "\
+diff --git a/a.rs b/a.rs
+index 1111111..2222222 100644
+--- a/a.rs
++++ b/a.rs
+@@ -1,6 +1,1 @@
+ fn keep() {}
+-fn moved() {
+-    let x = 1;
+-    let y = 2;
+-    x + y
+-}
+diff --git a/b.rs b/b.rs
+index 3333333..4444444 100644
+--- a/b.rs
++++ b/b.rs
+@@ -1,1 +1,6 @@
+ fn other() {}
++fn moved() {
++    let x = 1;
++    let y = 2;
++    x + y
++}
+";
+
+    #[test]
+    fn test_diff_strings_added_line() {
+        let diff = diff_strings("a\nb\n", "a\nb\nc\n", "old.txt", "new.txt");
+        assert!(
+            diff.starts_with("--- old.txt\n+++ new.txt\n"),
+            "got: {}",
+            diff
+        );
+        assert!(diff.contains("@@ -1,2 +1,3 @@"), "got: {}", diff);
+        assert!(diff.contains("\n+c\n"), "got: {}", diff);
+    }
+
+    #[test]
+    fn test_diff_strings_removed_line() {
+        let diff = diff_strings("a\nb\nc\n", "a\nc\n", "a", "b");
+        assert!(diff.contains("\n-b\n"), "got: {}", diff);
+        assert!(
+            !diff
+                .lines()
+                .any(|l| l.starts_with('+') && !l.starts_with("+++")),
+            "got: {}",
+            diff
+        );
+    }
+
+    #[test]
+    fn test_diff_strings_identical_is_empty() {
+        assert_eq!(diff_strings("same\n", "same\n", "a", "b"), "");
+    }
+
+    #[test]
+    fn test_parse_patch_round_trips() {
+        let files = parse_patch(MOVE_DIFF);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "a.rs");
+        assert_eq!(files[1].path, "b.rs");
+        assert_eq!(files[0].hunks.len(), 1);
+        assert_eq!(render_patch(&files), MOVE_DIFF);
+    }
+
+    #[test]
+    fn test_parse_patch_deleted_file_uses_old_path() {
+        let diff = "\
+diff --git a/gone.txt b/gone.txt
+deleted file mode 100644
+--- a/gone.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+";
+        let files = parse_patch(diff);
+        assert_eq!(files[0].path, "gone.txt");
+    }
+
+    #[test]
+    fn test_fold_markers_survive_retain_hunks() {
+        let diff = "\
+diff --git a/f.txt b/f.txt
+--- a/f.txt
++++ b/f.txt
+@@ -10,26 +10,26 @@
+... 16 unchanged lines ...
+ line 29
+-line 30
++changed 30
+ line 31
+@@ -50,1 +50,1 @@
+-old
++new
+";
+        let files = parse_patch(diff);
+        let hunk = &files[0].hunks[0];
+        assert_eq!(fold_marker_len(&hunk.lines[0]), Some(16));
+        assert_eq!(fold_marker_len(" line 29"), None);
+        assert_eq!(
+            hunk.changed_lines().collect::<Vec<_>>(),
+            ["line 30", "changed 30"]
+        );
+
+        let kept = retain_hunks(diff, |hunk| hunk.changed_lines().any(|l| l.contains("30")));
+        assert!(
+            kept.contains("... 16 unchanged lines ...\n line 29\n"),
+            "got: {}",
+            kept
+        );
+        assert!(!kept.contains("+new"), "got: {}", kept);
+    }
+
+    #[test]
+    fn test_moved_block_between_files_is_flagged() {
+        let files = parse_patch(MOVE_DIFF);
+        let moves = find_moved_blocks(&files, MIN_MOVED_LINES);
+
+        assert_eq!(moves.len(), 1, "expected one move, got: {:?}", moves);
+        assert_eq!(moves[0].from_path, "a.rs");
+        assert_eq!(moves[0].to_path, "b.rs");
+        assert_eq!(moves[0].line_count, 5);
+
+        let marked = mark_moved_blocks(MOVE_DIFF);
+        assert!(
+            marked.contains("@@ -1,6 +1,1 @@ [moved 5 lines to b.rs]"),
+            "source hunk should be marked, got: {}",
+            marked
+        );
+        assert!(
+            marked.contains("@@ -1,1 +1,6 @@ [moved 5 lines from a.rs]"),
+            "destination hunk should be marked, got: {}",
+            marked
+        );
+    }
+
+    #[test]
+    fn test_moved_block_between_added_lines_is_flagged() {
+        let diff = MOVE_DIFF
+            .replace("+fn moved() {", "+// Moved here\n+fn moved() {")
+            .replace("+    x + y\n+}\n", "+    x + y\n+}\n+fn added() {}\n")
+            .replace("@@ -1,1 +1,6 @@", "@@ -1,1 +1,8 @@");
+        let moves = find_moved_blocks(&parse_patch(&diff), MIN_MOVED_LINES);
+
+        assert_eq!(moves.len(), 1, "expected one move, got: {:?}", moves);
+        assert_eq!(moves[0].to_path, "b.rs");
+        assert_eq!(moves[0].line_count, 5);
+    }
+
+    #[test]
+    fn test_unrelated_add_delete_not_flagged() {
+        let diff = MOVE_DIFF.replace("+    let y = 2;", "+    let y = 3;");
+        assert!(find_moved_blocks(&parse_patch(&diff), MIN_MOVED_LINES).is_empty());
+        assert_eq!(mark_moved_blocks(&diff), diff);
+    }
+
+    #[test]
+    fn test_combined_diff_skips_changes_taken_from_one_parent() {
+        let ours = "a\nours\nc\n";
+        let theirs = "a\ntheirs\nc\n";
+        assert_eq!(combined_diff_hunks(&[ours, theirs], ours, 3), "");
+
+        let hunks = combined_diff_hunks(&[ours, theirs], "a\nboth\nc\n", 0);
+        assert_eq!(hunks, "@@@ -2,1 -2,1 +2,1 @@@\n- ours\n -theirs\n++both\n");
+    }
+
+    #[test]
+    fn test_combined_diff_merges_lines_lost_from_every_parent() {
+        let ours = "a\nx\nours\n";
+        let theirs = "a\nx\ntheirs\n";
+        let hunks = combined_diff_hunks(&[ours, theirs], "a\nboth\n", 0);
+        assert_eq!(
+            hunks,
+            "@@@ -2,2 -2,2 +2,1 @@@\n--x\n- ours\n -theirs\n++both\n"
+        );
+    }
+}
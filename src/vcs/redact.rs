@@ -0,0 +1,167 @@
+//! Redaction of personal data and secrets in diff text.
+//!
+//! Diffs sent to external services can carry email addresses (authors in
+//! headers, contact lines in code) and credentials committed by mistake.
+//! These helpers mask them with placeholders before the text leaves the
+//! machine.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Placeholder for masked email addresses.
+pub const EMAIL_PLACEHOLDER: &str = "<redacted-email>";
+
+/// Placeholder for masked secret-looking tokens.
+pub const TOKEN_PLACEHOLDER: &str = "<redacted-token>";
+
+/// Tokens shorter than this are never treated as secrets
+const MIN_TOKEN_LEN: usize = 20;
+
+/// Shannon entropy, in bits per char, above which a token looks random
+const MIN_TOKEN_ENTROPY: f64 = 3.5;
+
+static EMAIL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}")
+        .expect("valid email regex")
+});
+
+static TOKEN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(r"[A-Za-z0-9_\-+=]{{{},}}", MIN_TOKEN_LEN)).expect("valid token regex")
+});
+
+/// Mask email addresses and secret-looking tokens in diff text.
+///
+/// A token is a run of at least 20 base64/identifier characters that mixes
+/// letters with several digits and has high entropy, like API keys. Tokens
+/// never span `/`, so paths are not mistaken for keys, and lowercase hex
+/// runs are taken to be object ids or checksums rather than secrets.
+/// Long identifiers made of words are left alone, and so are the file
+/// header lines (`diff --git`, `index`, `---`, `+++`, renames), which keeps
+/// the structure of the diff and object ids such as submodule SHAs intact.
+pub fn redact_diff(diff: &str) -> String {
+    diff.split_inclusive('\n')
+        .map(|line| {
+            if is_header_line(line) {
+                return line.to_string();
+            }
+            let line = EMAIL.replace_all(line, EMAIL_PLACEHOLDER);
+            TOKEN
+                .replace_all(&line, |caps: &regex::Captures| {
+                    let token = &caps[0];
+                    if looks_like_secret(token) {
+                        TOKEN_PLACEHOLDER.to_string()
+                    } else {
+                        token.to_string()
+                    }
+                })
+                .into_owned()
+        })
+        .collect()
+}
+
+/// Whether a line is part of a file header rather than diff content.
+fn is_header_line(line: &str) -> bool {
+    const HEADERS: &[&str] = &[
+        "diff --git ",
+        "index ",
+        "--- ",
+        "+++ ",
+        "rename from ",
+        "rename to ",
+        "copy from ",
+        "copy to ",
+        "old mode ",
+        "new mode ",
+        "new file mode ",
+        "deleted file mode ",
+        "similarity index ",
+        "dissimilarity index ",
+    ];
+    HEADERS.iter().any(|header| line.starts_with(header))
+}
+
+/// Whether a token is random enough to be a credential.
+fn looks_like_secret(token: &str) -> bool {
+    if token.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')) {
+        return false;
+    }
+    let digits = token.chars().filter(char::is_ascii_digit).count();
+    let has_letters = token.chars().any(|c| c.is_ascii_alphabetic());
+    digits >= 3 && has_letters && shannon_entropy(token) >= MIN_TOKEN_ENTROPY
+}
+
+/// Shannon entropy of a string's chars, in bits per char.
+fn shannon_entropy(text: &str) -> f64 {
+    let mut counts = std::collections::HashMap::new();
+    for c in text.chars() {
+        *counts.entry(c).or_insert(0usize) += 1;
+    }
+    let len = text.chars().count() as f64;
+    counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_masks_email_and_api_key() {
+        let diff = "\
++// Contact: jane.doe@example.com
++const API_KEY: &str = \"sk-live-4f9a8B2c7D1e6F3a9b0C5d8E\";
+";
+        let redacted = redact_diff(diff);
+        assert_eq!(
+            redacted,
+            "\
++// Contact: <redacted-email>
++const API_KEY: &str = \"<redacted-token>\";
+"
+        );
+    }
+
+    #[test]
+    fn test_leaves_normal_code_untouched() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+@@ -1,2 +1,2 @@
+-fn get_merge_base_returns_ancestor() {}
++let total_count = compute_total_for_all_items(items, 42);
+";
+        assert_eq!(redact_diff(diff), diff);
+    }
+
+    #[test]
+    fn test_keeps_digit_heavy_paths() {
+        let diff = "\
+diff --git a/src/api/v2/handlers/user123_test b/src/api/v2/handlers/user123_test
+--- a/src/api/v2/handlers/user123_test
++++ b/src/api/v2/handlers/user123_test
+@@ -1 +1 @@
+-use crate::api/v2/handlers/user123_test;
++mod v2;
+";
+        assert_eq!(redact_diff(diff), diff);
+    }
+
+    #[test]
+    fn test_keeps_submodule_sha_lines() {
+        let diff = "\
+diff --git a/vendor/lib b/vendor/lib
+index 3f2880c4d1a9b7e2f5c6a8b9d0e1f2a3b4c5d6e7..9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b 160000
+--- a/vendor/lib
++++ b/vendor/lib
+@@ -1 +1 @@
+-Subproject commit 3f2880c4d1a9b7e2f5c6a8b9d0e1f2a3b4c5d6e7
++Subproject commit 9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b
+";
+        assert_eq!(redact_diff(diff), diff);
+    }
+}
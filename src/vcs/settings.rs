@@ -0,0 +1,89 @@
+//! Per-repository settings loaded from `.lumen.toml`.
+//!
+//! The file lives at the root of the working directory and lets a project
+//! tune exclusions and diff context without touching git config.
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::backend::VcsError;
+
+/// Name of the settings file at the working directory root.
+pub const SETTINGS_FILE: &str = ".lumen.toml";
+
+/// Settings read from `.lumen.toml`. Every field is optional.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct RepoSettings {
+    /// Extra paths to exclude from diffs, on top of the built-in lock files.
+    /// Entries ending in `/` match directories, others match file names; a
+    /// leading `/` anchors an entry at the repo root.
+    pub excludes: Vec<String>,
+    /// Lines of context around each hunk
+    pub context_lines: Option<u32>,
+    /// Branch to compare against when no base is given
+    pub default_base: Option<String>,
+}
+
+impl RepoSettings {
+    /// Parse settings from TOML text.
+    pub fn parse(content: &str) -> Result<Self, VcsError> {
+        toml_edit::de::from_str(content)
+            .map_err(|e| VcsError::Other(format!("invalid {}: {}", SETTINGS_FILE, e)))
+    }
+
+    /// Load settings from `.lumen.toml` in the given directory.
+    /// A missing file yields the defaults.
+    pub fn load(workdir: &Path) -> Result<Self, VcsError> {
+        let path = workdir.join(SETTINGS_FILE);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(VcsError::Io(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_well_formed_settings() {
+        let settings = RepoSettings::parse(
+            "excludes = [\"dist/\", \"schema.graphql\"]\ncontext_lines = 1\ndefault_base = \"develop\"\n",
+        )
+        .expect("should parse");
+
+        assert_eq!(settings.excludes, vec!["dist/", "schema.graphql"]);
+        assert_eq!(settings.context_lines, Some(1));
+        assert_eq!(settings.default_base.as_deref(), Some("develop"));
+    }
+
+    #[test]
+    fn test_parse_empty_settings_uses_defaults() {
+        assert_eq!(RepoSettings::parse("").unwrap(), RepoSettings::default());
+    }
+
+    #[test]
+    fn test_parse_malformed_settings_is_error() {
+        let err = RepoSettings::parse("context_lines = \"three\"\n").unwrap_err();
+        assert!(
+            err.to_string().contains(".lumen.toml"),
+            "error should name the file, got: {}",
+            err
+        );
+
+        assert!(RepoSettings::parse("excludes = [").is_err());
+    }
+
+    #[test]
+    fn test_load_missing_file_uses_defaults() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        assert_eq!(
+            RepoSettings::load(dir.path()).unwrap(),
+            RepoSettings::default()
+        );
+    }
+}
@@ -31,7 +31,7 @@ pub fn make_temp_dir(prefix: &str) -> PathBuf {
 }
 
 /// Run a git command in a directory using git2.
-/// Supports common operations: init, config, add, commit, checkout, log.
+/// Supports common operations: init, config, add, commit, tag, branch, checkout.
 /// For unsupported operations, falls back to CLI.
 pub fn git(dir: &Path, args: &[&str]) {
     if args.is_empty() {
@@ -98,6 +98,24 @@ pub fn git(dir: &Path, args: &[&str]) {
             repo.commit(Some("HEAD"), &sig, &sig, msg, &tree, &[&parent])
                 .expect("failed to create commit");
         }
+        "tag" if args.len() >= 2 => {
+            let repo = Repository::open(dir).expect("failed to open repo");
+            let head = repo
+                .head()
+                .and_then(|h| h.peel_to_commit())
+                .expect("failed to get HEAD commit");
+            repo.tag_lightweight(args[1], head.as_object(), false)
+                .expect("failed to create tag");
+        }
+        "branch" if args.len() >= 2 => {
+            let repo = Repository::open(dir).expect("failed to open repo");
+            let head = repo
+                .head()
+                .and_then(|h| h.peel_to_commit())
+                .expect("failed to get HEAD commit");
+            repo.branch(args[1], &head, false)
+                .expect("failed to create branch");
+        }
         "checkout" if args.len() >= 3 && args[1] == "-b" => {
             let repo = Repository::open(dir).expect("failed to open repo");
             let head = repo.head().expect("failed to get HEAD");